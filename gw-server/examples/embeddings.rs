@@ -1,4 +1,3 @@
-use dotenv;
 use rig_core::{client::EmbeddingsClient, embeddings::EmbeddingModel};
 use tracing::info;
 
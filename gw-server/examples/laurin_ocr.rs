@@ -5,7 +5,6 @@ use std::{
 };
 
 use base64::{Engine, prelude::BASE64_STANDARD};
-use dotenv;
 use futures::StreamExt;
 use image::GenericImageView;
 use rig_core::{
@@ -36,7 +35,7 @@ impl PathUtil {
     }
 
     fn path_to_dest_doc(&self) -> PathBuf {
-        PathBuf::from(OUT_DIR).join(&format!("page_{:03}.md", self.0))
+        PathBuf::from(OUT_DIR).join(format!("page_{:03}.md", self.0))
     }
 
     fn create_outdir() {
@@ -0,0 +1,76 @@
+use crate::{
+    application::{
+        middleware::check_auth,
+        model::{BackendActivityResponse, RecentRequestResponse},
+    },
+    model::{ApplicationConfig, SecurityConfig},
+};
+use axum::{
+    extract::State,
+    http::{HeaderValue, StatusCode, header::WWW_AUTHENTICATE},
+    response::{Html, Json as JsonBody, Response},
+    routing::{Router, get},
+};
+use std::sync::Arc;
+
+// a single page built on the admin-endpoints (no data is embedded)
+const DASHBOARD_HTML: &str = include_str!("dashboard/index.html");
+const BASIC_AUTH_CHALLENGE: &str = "Basic realm=\"mai-server admin\", charset=\"UTF-8\"";
+
+pub fn create_router(
+    config: Arc<dyn ApplicationConfig>,
+    security_config: Arc<dyn SecurityConfig>,
+) -> Router {
+    Router::new()
+        .route("/admin/dashboard", get(get_dashboard))
+        .route("/admin/activity", get(get_activity))
+        .route("/admin/requests", get(get_recent_requests))
+        .layer(axum::middleware::from_fn_with_state(
+            security_config,
+            check_auth,
+        ))
+        .layer(axum::middleware::map_response(challenge_browsers))
+        .with_state(config)
+}
+
+// makes browsers ask for the credentials (the api-key as password) ; they send them along with
+// every request of the dashboard then
+async fn challenge_browsers(mut response: Response) -> Response {
+    if response.status() == StatusCode::UNAUTHORIZED {
+        response.headers_mut().insert(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static(BASIC_AUTH_CHALLENGE),
+        );
+    }
+    response
+}
+
+async fn get_dashboard() -> Html<&'static str> {
+    Html(DASHBOARD_HTML)
+}
+
+async fn get_activity(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+) -> JsonBody<Vec<BackendActivityResponse>> {
+    JsonBody(
+        config
+            .idle_unload_service()
+            .activity_report()
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+    )
+}
+
+async fn get_recent_requests(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+) -> JsonBody<Vec<RecentRequestResponse>> {
+    JsonBody(
+        config
+            .metrics_service()
+            .recent_requests()
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+    )
+}
@@ -0,0 +1,45 @@
+use crate::{application::model::HealthReportResponse, model::ApplicationConfig};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json as JsonBody,
+    routing::{Router, get},
+};
+use std::sync::Arc;
+
+// both routes are deliberately not secured so they can be used by orchestrators and load-balancers
+pub fn create_router(config: Arc<dyn ApplicationConfig>) -> Router {
+    Router::new()
+        .route("/healthz", get(get_liveness))
+        .route("/readyz", get(get_readiness))
+        .with_state(config)
+}
+
+// the gateway is alive as long as it answers ; the report only shows the current process-states
+async fn get_liveness(
+    State(application_config): State<Arc<dyn ApplicationConfig>>,
+) -> (StatusCode, JsonBody<HealthReportResponse>) {
+    let report: HealthReportResponse = application_config
+        .health_service()
+        .check_liveness()
+        .await
+        .into();
+    (StatusCode::OK, JsonBody::from(report))
+}
+
+// the gateway is ready unless a backend failed (backends switching models still accept requests)
+async fn get_readiness(
+    State(application_config): State<Arc<dyn ApplicationConfig>>,
+) -> (StatusCode, JsonBody<HealthReportResponse>) {
+    let report: HealthReportResponse = application_config
+        .health_service()
+        .check_readiness()
+        .await
+        .into();
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, JsonBody::from(report))
+}
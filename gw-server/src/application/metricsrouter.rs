@@ -0,0 +1,39 @@
+use crate::{
+    application::middleware::check_auth,
+    model::{ApplicationConfig, SecurityConfig},
+};
+use axum::{
+    extract::State,
+    http::{StatusCode, header::CONTENT_TYPE},
+    routing::{Router, get},
+};
+use std::sync::Arc;
+
+const PROMETHEUS_TEXT_FORMAT: &str = "text/plain; version=0.0.4";
+
+pub fn create_router(
+    config: Arc<dyn ApplicationConfig>,
+    security_config: Arc<dyn SecurityConfig>,
+) -> Router {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .layer(axum::middleware::from_fn_with_state(
+            security_config,
+            check_auth,
+        ))
+        .with_state(config)
+}
+
+async fn get_metrics(
+    State(application_config): State<Arc<dyn ApplicationConfig>>,
+) -> (
+    StatusCode,
+    [(axum::http::HeaderName, &'static str); 1],
+    String,
+) {
+    (
+        StatusCode::OK,
+        [(CONTENT_TYPE, PROMETHEUS_TEXT_FORMAT)],
+        application_config.metrics_service().render_metrics().await,
+    )
+}
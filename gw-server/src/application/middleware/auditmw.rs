@@ -0,0 +1,325 @@
+use crate::{
+    application::middleware::RequestContext,
+    domain::{model::AuditRecord, ports::AuditServiceInPort},
+    infrastructure::telemetry::trace_id,
+    model::ApplicationConfig,
+};
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::{
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
+use tracing::{Span, error};
+
+// request-bodies are buffered completely if they are captured ; larger bodies are forwarded
+// unbuffered and recorded as truncated
+const MAX_CAPTURED_REQUEST_BYTES: usize = 64 * 1024 * 1024;
+// json-responses are only inspected for token-counts up to this size
+const MAX_INSPECTED_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+
+/// writes an audit-record for every request once its response (including a streamed body)
+/// is done; does nothing unless the audit-log is enabled
+pub async fn audit_request(
+    State(application_config): State<Arc<dyn ApplicationConfig>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(audit_service) = application_config.audit_service() else {
+        return next.run(req).await;
+    };
+
+    let timestamp = SystemTime::now();
+    let start = Instant::now();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_else(|| req.uri().path().to_owned());
+    let method = req.method().to_string();
+    let context = RequestContext::of(&mut req);
+    let trace_id = trace_id(&Span::current());
+
+    let (req, request_body) = if audit_service.captures_request_bodies() {
+        let (parts, body) = req.into_parts();
+        let (body, request_body) = capture_request_body(body, MAX_CAPTURED_REQUEST_BYTES).await;
+        (Request::from_parts(parts, body), request_body)
+    } else {
+        (req, None)
+    };
+
+    let res = next.run(req).await;
+
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let inspector = if content_type.starts_with("text/event-stream") {
+        ResponseInspector::EventStream {
+            pending: Vec::new(),
+            content: String::new(),
+            reasoning_content: String::new(),
+        }
+    } else if content_type.starts_with("application/json") {
+        ResponseInspector::Json(Vec::new())
+    } else {
+        ResponseInspector::Ignored
+    };
+
+    let mut observation = AuditObservation {
+        audit_service,
+        context,
+        start,
+        inspector,
+        prompt_tokens: None,
+        completion_tokens: None,
+        record: AuditRecord {
+            timestamp,
+            key_name: None,
+            method,
+            route,
+            model: None,
+            status: res.status().as_u16(),
+            duration: Default::default(),
+            queue_time: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            trace_id,
+            request_body,
+            response_body: None,
+        },
+    };
+
+    if matches!(observation.inspector, ResponseInspector::Ignored) {
+        return res;
+    }
+    let (parts, body) = res.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            observation.inspect(bytes);
+        }
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+enum ResponseInspector {
+    EventStream {
+        pending: Vec<u8>,
+        content: String,
+        reasoning_content: String,
+    },
+    Json(Vec<u8>),
+    Ignored,
+}
+
+struct AuditObservation {
+    audit_service: Arc<dyn AuditServiceInPort>,
+    context: RequestContext,
+    start: Instant,
+    inspector: ResponseInspector,
+    prompt_tokens: Option<u64>,
+    completion_tokens: Option<u64>,
+    record: AuditRecord,
+}
+
+impl AuditObservation {
+    fn inspect(&mut self, bytes: &Bytes) {
+        match &mut self.inspector {
+            ResponseInspector::EventStream {
+                pending,
+                content,
+                reasoning_content,
+            } => {
+                pending.extend_from_slice(bytes);
+                while let Some(line_end) = pending.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=line_end).collect();
+                    let Some(event) = String::from_utf8_lossy(&line)
+                        .trim()
+                        .strip_prefix("data:")
+                        .and_then(|data| serde_json::from_str::<Value>(data.trim()).ok())
+                    else {
+                        continue;
+                    };
+                    let (prompt_tokens, completion_tokens) = token_counts(&event);
+                    self.prompt_tokens = prompt_tokens.or(self.prompt_tokens);
+                    self.completion_tokens = completion_tokens.or(self.completion_tokens);
+                    if let Some(delta) = event.pointer("/choices/0/delta") {
+                        if let Some(text) = delta.get("content").and_then(Value::as_str) {
+                            content.push_str(text);
+                        }
+                        if let Some(text) = delta.get("reasoning_content").and_then(Value::as_str) {
+                            reasoning_content.push_str(text);
+                        }
+                    }
+                }
+            }
+            ResponseInspector::Json(body) => {
+                if body.len() + bytes.len() <= MAX_INSPECTED_RESPONSE_BYTES {
+                    body.extend_from_slice(bytes);
+                } else {
+                    self.inspector = ResponseInspector::Ignored;
+                }
+            }
+            ResponseInspector::Ignored => {}
+        }
+    }
+}
+
+// the record is written as soon as the response (including a streamed body) is done
+impl Drop for AuditObservation {
+    fn drop(&mut self) {
+        let context = self.context.snapshot();
+        let mut record = self.record.clone();
+        record.key_name = context.key_name;
+        record.model = context.model;
+        record.queue_time = context.queue_time;
+        record.duration = self.start.elapsed();
+        record.prompt_tokens = self.prompt_tokens;
+        record.completion_tokens = self.completion_tokens;
+
+        let captures_response_bodies = self.audit_service.captures_response_bodies();
+        match &self.inspector {
+            ResponseInspector::EventStream {
+                content,
+                reasoning_content,
+                ..
+            } => {
+                if captures_response_bodies {
+                    let mut response_body = json!({ "content": content });
+                    if !reasoning_content.is_empty() {
+                        response_body["reasoning_content"] = json!(reasoning_content);
+                    }
+                    record.response_body = Some(response_body);
+                }
+            }
+            ResponseInspector::Json(body) => {
+                if let Ok(response) = serde_json::from_slice::<Value>(body) {
+                    (record.prompt_tokens, record.completion_tokens) = token_counts(&response);
+                    if captures_response_bodies {
+                        record.response_body = Some(response);
+                    }
+                }
+            }
+            ResponseInspector::Ignored => {}
+        }
+
+        self.audit_service.record(record);
+    }
+}
+
+/// token-counts as reported in 'usage' (openai) or 'timings' (llama-server)
+fn token_counts(value: &Value) -> (Option<u64>, Option<u64>) {
+    let count = |pointer: &str| value.pointer(pointer).and_then(Value::as_u64);
+    (
+        count("/usage/prompt_tokens").or_else(|| count("/timings/prompt_n")),
+        count("/usage/completion_tokens").or_else(|| count("/timings/predicted_n")),
+    )
+}
+
+/// buffers the request-body to capture it ; a body exceeding 'max_bytes' (or failing to be read)
+/// is forwarded unbuffered with the chunks read so far put in front of the rest ; it is recorded
+/// by its size only, as the raw text could not be redacted
+async fn capture_request_body(body: Body, max_bytes: usize) -> (Body, Option<Value>) {
+    let mut stream = body.into_data_stream();
+    let mut chunks: Vec<Bytes> = Vec::new();
+    let mut size = 0;
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(bytes) => {
+                size += bytes.len();
+                chunks.push(bytes);
+                if size > max_bytes {
+                    let request_body = json!({ "truncated": true, "bytes": size });
+                    let body = Body::from_stream(
+                        futures::stream::iter(chunks.into_iter().map(Ok)).chain(stream),
+                    );
+                    return (body, Some(request_body));
+                }
+            }
+            Err(e) => {
+                error!("error reading request-body for the audit-log: {e}");
+                let body = Body::from_stream(
+                    futures::stream::iter(chunks.into_iter().map(Ok))
+                        .chain(futures::stream::once(async { Err(e) }))
+                        .chain(stream),
+                );
+                return (body, None);
+            }
+        }
+    }
+    let bytes = Bytes::from(chunks.concat());
+    let request_body = parse_captured_body(&bytes);
+    (Body::from(bytes), request_body)
+}
+
+// bodies other than json (e.g. multipart-uploads) are recorded by their size only, as they
+// could not be redacted
+fn parse_captured_body(bytes: &Bytes) -> Option<Value> {
+    if bytes.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_slice(bytes)
+                .unwrap_or_else(|_| json!({ "unparsed": true, "bytes": bytes.len() })),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn oversized_request_bodies_are_forwarded_completely_and_recorded_as_truncated() {
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::from_static(b"{\"input\":")),
+            Ok(Bytes::from_static(b"\"0123456789\"")),
+            Ok(Bytes::from_static(b"}")),
+        ];
+        let (body, request_body) =
+            capture_request_body(Body::from_stream(futures::stream::iter(chunks)), 12).await;
+
+        assert_eq!(
+            to_bytes(body, usize::MAX).await.unwrap(),
+            Bytes::from_static(b"{\"input\":\"0123456789\"}")
+        );
+        assert_eq!(
+            request_body,
+            Some(json!({ "truncated": true, "bytes": 21 }))
+        );
+
+        let (body, request_body) = capture_request_body(Body::from("{\"input\":\"hi\"}"), 64).await;
+        assert_eq!(
+            to_bytes(body, usize::MAX).await.unwrap(),
+            Bytes::from_static(b"{\"input\":\"hi\"}")
+        );
+        assert_eq!(request_body, Some(json!({ "input": "hi" })));
+    }
+
+    #[tokio::test]
+    async fn secrets_of_bodies_which_cannot_be_redacted_are_not_recorded() {
+        let secret = "sk-secret-0123456789";
+        let oversized = format!(
+            "{{\"api_key\":\"{secret}\",\"image\":\"data:image/png;base64,{}\"}}",
+            "A".repeat(1000)
+        );
+        let (_, request_body) = capture_request_body(Body::from(oversized.clone()), 64).await;
+        let request_body = request_body.unwrap();
+        assert_eq!(request_body["truncated"], true);
+        assert!(!request_body.to_string().contains(secret));
+
+        let malformed = format!("api_key={secret}");
+        let (_, request_body) = capture_request_body(Body::from(malformed), 1024).await;
+        assert_eq!(request_body, Some(json!({ "unparsed": true, "bytes": 28 })));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{application::middleware::RequestContext, model::ApplicationConfig};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use std::{sync::Arc, time::Instant};
+
+const UNMATCHED_ROUTE: &str = "unmatched";
+// the label of model-names which are not in the catalog (clients must not create time-series)
+const UNKNOWN_MODEL: &str = "unknown";
+
+struct RequestObservation {
+    application_config: Arc<dyn ApplicationConfig>,
+    route: String,
+    context: RequestContext,
+    status: u16,
+    start: Instant,
+}
+
+// the observation is recorded as soon as the response (including a streamed body) is done
+impl Drop for RequestObservation {
+    fn drop(&mut self) {
+        let model = self.context.snapshot().model.map(|model| {
+            self.application_config
+                .models_service()
+                .catalog_alias_of(&model)
+                .unwrap_or_else(|| UNKNOWN_MODEL.to_owned())
+        });
+        self.application_config.metrics_service().observe_request(
+            &self.route,
+            model.as_deref(),
+            self.status,
+            self.start.elapsed(),
+        );
+    }
+}
+
+pub async fn track_request_metrics(
+    State(application_config): State<Arc<dyn ApplicationConfig>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned());
+    let context = RequestContext::of(&mut req);
+
+    let res = next.run(req).await;
+
+    let observation = RequestObservation {
+        application_config,
+        route,
+        context,
+        status: res.status().as_u16(),
+        start,
+    };
+
+    let is_event_stream = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if is_event_stream {
+        let (parts, body) = res.into_parts();
+        let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let _ = &observation;
+            chunk
+        }));
+        Response::from_parts(parts, body)
+    } else {
+        res
+    }
+}
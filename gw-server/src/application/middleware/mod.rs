@@ -1,4 +1,12 @@
+mod auditmw;
+pub use auditmw::audit_request;
+mod metricsmw;
+pub use metricsmw::track_request_metrics;
+mod requestcontext;
+pub use requestcontext::{RequestContext, RequestContextData};
 mod requestloggermw;
 pub use requestloggermw::request_logger;
 mod securitymw;
-pub use securitymw::check_auth;
+pub use securitymw::{check_admin_auth, check_auth};
+mod tracingmw;
+pub use tracingmw::trace_request;
@@ -0,0 +1,54 @@
+use axum::extract::Request;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// per-request information which is collected by the handlers and evaluated by the middlewares
+#[derive(Clone, Default)]
+pub struct RequestContext(Arc<Mutex<RequestContextData>>);
+
+#[derive(Clone, Default, Debug)]
+pub struct RequestContextData {
+    pub model: Option<String>,
+    pub key_name: Option<String>,
+    pub queue_time: Option<Duration>,
+}
+
+impl RequestContext {
+    /// returns the context attached to the request (attaching a new one if there is none yet)
+    pub fn of(request: &mut Request) -> Self {
+        request
+            .extensions_mut()
+            .get_or_insert_default::<RequestContext>()
+            .clone()
+    }
+
+    pub fn set_model(&self, model: impl Into<String>) {
+        self.0
+            .lock()
+            .expect("request-context must not be poisoned")
+            .model = Some(model.into());
+    }
+
+    pub fn set_key_name(&self, key_name: impl Into<String>) {
+        self.0
+            .lock()
+            .expect("request-context must not be poisoned")
+            .key_name = Some(key_name.into());
+    }
+
+    pub fn set_queue_time(&self, queue_time: Duration) {
+        self.0
+            .lock()
+            .expect("request-context must not be poisoned")
+            .queue_time = Some(queue_time);
+    }
+
+    pub fn snapshot(&self) -> RequestContextData {
+        self.0
+            .lock()
+            .expect("request-context must not be poisoned")
+            .clone()
+    }
+}
@@ -1,33 +1,122 @@
-use crate::model::SecurityConfig;
+use crate::{
+    application::middleware::RequestContext,
+    model::{ADMIN_APIKEY_NAME, APIKEY_NAME, NO_APIKEY_NAME, SecurityConfig},
+};
 use axum::{
     extract::{Request, State},
     http::{StatusCode, header::AUTHORIZATION},
     middleware::Next,
     response::Response,
 };
+use base64::{Engine, prelude::BASE64_STANDARD};
 use std::sync::Arc;
+use tracing::info_span;
 
+/// accepts the api-key as well as the admin-key and the named api-keys ; the name of the key is
+/// set on the request-context
 pub async fn check_auth(
     State(security_config): State<Arc<dyn SecurityConfig>>,
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    let auth_header = req
-        .headers()
-        .get(AUTHORIZATION)
-        .ok_or(StatusCode::UNAUTHORIZED)?
-        .to_str()
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let key_name = info_span!("auth").in_scope(|| {
+        let auth_header = req
+            .headers()
+            .get(AUTHORIZATION)
+            .ok_or(StatusCode::UNAUTHORIZED)?
+            .to_str()
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
 
-    if let Some(security_apikey) = security_config.get_apikey() {
-        if let Some(bearer_token) = auth_header.strip_prefix("Bearer ")
-            && security_apikey == bearer_token
-        {
-            Ok(next.run(req).await)
+        let presented_key = presented_key(auth_header);
+        // every named key is compared so the time taken does not tell which one matched
+        let named_key = || {
+            let presented_key = presented_key.as_deref()?;
+            security_config.get_named_apikeys().iter().fold(
+                None,
+                |found, (key_name, named_apikey)| {
+                    if constant_time_eq(named_apikey, presented_key) {
+                        Some(key_name.clone())
+                    } else {
+                        found
+                    }
+                },
+            )
+        };
+        if let Some(security_apikey) = security_config.get_apikey() {
+            let presented_key = presented_key.as_deref().ok_or(StatusCode::UNAUTHORIZED)?;
+            if constant_time_eq(&security_apikey, presented_key) {
+                Ok(APIKEY_NAME.to_owned())
+            } else if security_config
+                .get_admin_apikey()
+                .is_some_and(|admin_apikey| constant_time_eq(&admin_apikey, presented_key))
+            {
+                Ok(ADMIN_APIKEY_NAME.to_owned())
+            } else {
+                named_key().ok_or(StatusCode::UNAUTHORIZED)
+            }
         } else {
-            Err(StatusCode::UNAUTHORIZED)
+            Ok(named_key().unwrap_or_else(|| NO_APIKEY_NAME.to_owned()))
+        }
+    })?;
+    RequestContext::of(&mut req).set_key_name(key_name);
+
+    Ok(next.run(req).await)
+}
+
+/// guards changes of the catalog (written to disk) ; they require the admin-key, which gateways
+/// without an api-key (on localhost) do not need either
+pub async fn check_admin_auth(
+    State(security_config): State<Arc<dyn SecurityConfig>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    info_span!("admin_auth").in_scope(|| {
+        match (
+            security_config.get_admin_apikey(),
+            security_config.get_apikey(),
+        ) {
+            (Some(admin_apikey), _) => match req
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|auth_header| auth_header.to_str().ok())
+                .and_then(presented_key)
+            {
+                Some(presented_key) if constant_time_eq(&admin_apikey, &presented_key) => Ok(()),
+                Some(_) => Err(StatusCode::FORBIDDEN),
+                None => Err(StatusCode::UNAUTHORIZED),
+            },
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(StatusCode::FORBIDDEN),
         }
-    } else {
-        Ok(next.run(req).await)
+    })?;
+    Ok(next.run(req).await)
+}
+
+// does not stop at the first differing byte, so the time taken does not reveal a prefix of a key
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// the bearer-token or the password of basic-credentials
+fn presented_key(auth_header: &str) -> Option<String> {
+    match auth_header.strip_prefix("Bearer ") {
+        Some(bearer_token) => Some(bearer_token.to_owned()),
+        None => basic_auth_password(auth_header),
     }
 }
+
+// browsers (e.g. showing the dashboard) send the api-key as password of basic-credentials ; the
+// user-name is ignored
+fn basic_auth_password(auth_header: &str) -> Option<String> {
+    let credentials = BASE64_STANDARD
+        .decode(auth_header.strip_prefix("Basic ")?)
+        .ok()?;
+    String::from_utf8(credentials)
+        .ok()?
+        .split_once(':')
+        .map(|(_, password)| password.to_owned())
+}
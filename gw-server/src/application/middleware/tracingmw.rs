@@ -0,0 +1,60 @@
+use crate::infrastructure::telemetry::{extract_trace_context, trace_id};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::{HeaderValue, header::CONTENT_TYPE},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use tracing::{Instrument, field::Empty, info_span, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// opens the root-span of a request (continuing a trace passed via 'traceparent') and returns
+/// its trace-id in the 'x-trace-id' response-header
+pub async fn trace_request(req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_else(|| req.uri().path().to_owned());
+    let span = info_span!(
+        "http_request",
+        otel.name = format!("{} {route}", req.method()),
+        otel.kind = "server",
+        http.request.method = %req.method(),
+        http.route = route,
+        http.response.status_code = Empty,
+    );
+    if let Err(e) = span.set_parent(extract_trace_context(req.headers())) {
+        warn!("could not continue propagated trace: {e}");
+    }
+
+    let mut res = next.run(req).instrument(span.clone()).await;
+
+    span.record("http.response.status_code", res.status().as_u16());
+    if let Some(trace_id) = trace_id(&span)
+        && let Ok(trace_id) = HeaderValue::from_str(&trace_id)
+    {
+        res.headers_mut().insert(TRACE_ID_HEADER, trace_id);
+    }
+
+    // streamed responses keep the span open until the last chunk was sent
+    let is_event_stream = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if is_event_stream {
+        let (parts, body) = res.into_parts();
+        let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let _ = &span;
+            chunk
+        }));
+        Response::from_parts(parts, body)
+    } else {
+        res
+    }
+}
@@ -2,8 +2,12 @@ use crate::model::{ApplicationConfig, SecurityConfig};
 use axum::routing::Router;
 use std::sync::Arc;
 
+mod dashboardrouter;
+mod healthrouter;
+mod metricsrouter;
 pub mod middleware;
 pub mod model;
+mod modelcatalogrouter;
 mod modelmanagerrouter;
 mod openairouter;
 
@@ -20,3 +24,28 @@ pub fn model_manager_router(
 ) -> Router {
     modelmanagerrouter::create_router(config, security_config)
 }
+
+pub fn model_catalog_router(
+    config: Arc<dyn ApplicationConfig>,
+    security_config: Arc<dyn SecurityConfig>,
+) -> Router {
+    modelcatalogrouter::create_router(config, security_config)
+}
+
+pub fn dashboard_router(
+    config: Arc<dyn ApplicationConfig>,
+    security_config: Arc<dyn SecurityConfig>,
+) -> Router {
+    dashboardrouter::create_router(config, security_config)
+}
+
+pub fn health_router(config: Arc<dyn ApplicationConfig>) -> Router {
+    healthrouter::create_router(config)
+}
+
+pub fn metrics_router(
+    config: Arc<dyn ApplicationConfig>,
+    security_config: Arc<dyn SecurityConfig>,
+) -> Router {
+    metricsrouter::create_router(config, security_config)
+}
@@ -1,14 +1,22 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, os::unix::process::ExitStatusExt, sync::Arc};
 
+use crate::domain::model::{
+    BackendActivityReport, BackendHealth, BackendProbe, BackendStatus, HealthReport, KeepAlive,
+    RecentRequest, RunOverrides,
+};
 use async_openai::types::{chat::CreateChatCompletionRequest, embeddings::CreateEmbeddingRequest};
 use axum::{
     extract::Request,
     http::{StatusCode, header},
 };
+use chrono::{DateTime, SecondsFormat, Utc};
 use http_body_util::BodyExt;
-use inference_backends::{ContextSize, LlamaCppConfigArgs, LlamaCppRunConfig, OnOffAutoValue};
+use inference_backends::{
+    ContextSize, ExitReason, LlamaCppConfigArgs, LlamaCppProcessState, LlamaCppRunConfig,
+    LlamaCppTransition, LogLine, LogStream, OnOffAutoValue, TransitionCause,
+};
 use serde::{Deserialize, Serialize};
-use tracing::{error, trace};
+use tracing::{error, trace, warn};
 
 const DEFAULT_PARALLEL: u8 = 1;
 
@@ -20,24 +28,366 @@ pub enum LlamaCppProcessStateResponse {
     Running(LlamaCppRunConfigDto),
     Starting(LlamaCppRunConfigDto),
     Stopping,
+    Failed(LlamaCppRunConfigDto, String),
 }
 
 impl From<inference_backends::LlamaCppProcessState> for LlamaCppProcessStateResponse {
     fn from(value: inference_backends::LlamaCppProcessState) -> Self {
         match value {
-            managed_process::ProcessState::Running(run_config) => {
+            managed_process::ProcessState::Running(run_config, _) => {
                 LlamaCppProcessStateResponse::Running(run_config.into())
             }
-            managed_process::ProcessState::Starting(run_config) => {
+            managed_process::ProcessState::Starting(run_config, _) => {
                 LlamaCppProcessStateResponse::Starting(run_config.into())
             }
             managed_process::ProcessState::Stopped => LlamaCppProcessStateResponse::Stopped,
-            managed_process::ProcessState::Stopping(_, None) => {
+            managed_process::ProcessState::Stopping(_, None, _) => {
                 LlamaCppProcessStateResponse::Stopping
             }
-            managed_process::ProcessState::Stopping(_, Some(run_config)) => {
+            managed_process::ProcessState::Stopping(_, Some(run_config), _) => {
                 LlamaCppProcessStateResponse::Starting(run_config.into())
             }
+            managed_process::ProcessState::Failed(run_config, reason) => {
+                LlamaCppProcessStateResponse::Failed(run_config.into(), reason)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProcessStateNameResponse {
+    Stopped,
+    Starting,
+    Running,
+    Stopping,
+    Failed,
+}
+
+impl From<&inference_backends::LlamaCppProcessState> for ProcessStateNameResponse {
+    fn from(value: &inference_backends::LlamaCppProcessState) -> Self {
+        match value {
+            managed_process::ProcessState::Stopped => ProcessStateNameResponse::Stopped,
+            managed_process::ProcessState::Starting(..) => ProcessStateNameResponse::Starting,
+            managed_process::ProcessState::Running(..) => ProcessStateNameResponse::Running,
+            managed_process::ProcessState::Stopping(..) => ProcessStateNameResponse::Stopping,
+            managed_process::ProcessState::Failed(_, _) => ProcessStateNameResponse::Failed,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendProbeResponse {
+    Healthy,
+    Loading,
+    Unhealthy(u16),
+    Unreachable(String),
+}
+
+impl From<BackendProbe> for BackendProbeResponse {
+    fn from(value: BackendProbe) -> Self {
+        match value {
+            BackendProbe::Healthy => BackendProbeResponse::Healthy,
+            BackendProbe::Loading => BackendProbeResponse::Loading,
+            BackendProbe::Unhealthy(status) => BackendProbeResponse::Unhealthy(status),
+            BackendProbe::Unreachable(reason) => BackendProbeResponse::Unreachable(reason),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendStatusResponse {
+    Idle,
+    Starting,
+    Loading,
+    Ready,
+    Degraded,
+    Stopping,
+    Failed,
+}
+
+impl From<BackendStatus> for BackendStatusResponse {
+    fn from(value: BackendStatus) -> Self {
+        match value {
+            BackendStatus::Idle => BackendStatusResponse::Idle,
+            BackendStatus::Starting => BackendStatusResponse::Starting,
+            BackendStatus::Loading => BackendStatusResponse::Loading,
+            BackendStatus::Ready => BackendStatusResponse::Ready,
+            BackendStatus::Degraded => BackendStatusResponse::Degraded,
+            BackendStatus::Stopping => BackendStatusResponse::Stopping,
+            BackendStatus::Failed => BackendStatusResponse::Failed,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct BackendHealthResponse {
+    pub backend: String,
+    pub process_state: ProcessStateNameResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe: Option<BackendProbeResponse>,
+    pub status: BackendStatusResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+impl From<BackendHealth> for BackendHealthResponse {
+    fn from(value: BackendHealth) -> Self {
+        BackendHealthResponse {
+            model: value.model_alias(),
+            failure: value.failure_reason(),
+            process_state: (&value.process_state).into(),
+            backend: value.backend,
+            probe: value.probe.map(Into::into),
+            status: value.status.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct HealthReportResponse {
+    pub ready: bool,
+    pub backends: Vec<BackendHealthResponse>,
+}
+
+impl From<HealthReport> for HealthReportResponse {
+    fn from(value: HealthReport) -> Self {
+        HealthReportResponse {
+            ready: value.is_ready(),
+            backends: value.backends.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// names the catalog-entry to create when cloning a model or saving a running config
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct NewAliasRequest {
+    pub alias: String,
+}
+
+/// optional run-params replacing the ones configured for the gateway when loading a model
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct LoadModelRequest {
+    pub parallel: Option<u8>,
+    pub threads: Option<i8>,
+    pub threads_batch: Option<i8>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+impl From<LoadModelRequest> for RunOverrides {
+    fn from(value: LoadModelRequest) -> Self {
+        RunOverrides {
+            parallel: value.parallel,
+            threads: value.threads,
+            threads_batch: value.threads_batch,
+            env: value.env,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct BackendActivityResponse {
+    pub backend: String,
+    // requests waiting for their model or being answered
+    pub in_flight: usize,
+    pub idle_secs: u64,
+    pub served_alias: Option<String>,
+}
+
+impl From<BackendActivityReport> for BackendActivityResponse {
+    fn from(value: BackendActivityReport) -> Self {
+        BackendActivityResponse {
+            backend: value.backend,
+            in_flight: value.in_flight,
+            idle_secs: value.idle_for.as_secs(),
+            served_alias: value.served_alias,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct RecentRequestResponse {
+    pub timestamp: String,
+    pub route: String,
+    pub model: Option<String>,
+    pub status: u16,
+    pub duration_ms: u128,
+}
+
+impl From<RecentRequest> for RecentRequestResponse {
+    fn from(value: RecentRequest) -> Self {
+        RecentRequestResponse {
+            timestamp: DateTime::<Utc>::from(value.timestamp)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            route: value.route,
+            model: value.model,
+            status: value.status,
+            duration_ms: value.duration.as_millis(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogStreamResponse {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogLineResponse {
+    pub generation: u64,
+    pub timestamp: String,
+    pub stream: LogStreamResponse,
+    pub line: String,
+}
+
+impl From<LogLine> for LogLineResponse {
+    fn from(value: LogLine) -> Self {
+        LogLineResponse {
+            generation: value.generation,
+            timestamp: DateTime::<Utc>::from(value.timestamp)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            stream: match value.stream {
+                LogStream::Stdout => LogStreamResponse::Stdout,
+                LogStream::Stderr => LogStreamResponse::Stderr,
+            },
+            line: value.line,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionCauseResponse {
+    Requested,
+    ProcessReady,
+    ProcessNotReady,
+    ProcessFinished,
+    Restart,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitReasonResponse {
+    Exited,
+    Stopped,
+    Killed,
+    Unknown,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct TransitionResponse {
+    pub timestamp: String,
+    pub generation: u64,
+    pub state: ProcessStateNameResponse,
+    // alias of the config the state refers to (the one being stopped while stopping)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    // alias of the config started once the stopping process finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_alias: Option<String>,
+    pub cause: TransitionCauseResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requested_by: Option<String>,
+    // why the process did not get ready or failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_reason: Option<ExitReasonResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_signal: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup_millis: Option<u64>,
+}
+
+impl From<LlamaCppTransition> for TransitionResponse {
+    fn from(value: LlamaCppTransition) -> Self {
+        let (alias, next_alias, failure) = match &value.state {
+            LlamaCppProcessState::Stopped => (None, None, None),
+            LlamaCppProcessState::Starting(config, _)
+            | LlamaCppProcessState::Running(config, _) => {
+                (Some(config.args_handle.alias.clone()), None, None)
+            }
+            LlamaCppProcessState::Stopping(config, next_config, _) => (
+                Some(config.args_handle.alias.clone()),
+                next_config
+                    .as_ref()
+                    .map(|next_config| next_config.args_handle.alias.clone()),
+                None,
+            ),
+            LlamaCppProcessState::Failed(config, reason) => (
+                Some(config.args_handle.alias.clone()),
+                None,
+                Some(reason.clone()),
+            ),
+        };
+        let (cause, requested_by, reason, exit_reason) = match value.cause {
+            TransitionCause::Requested(requested_by) => (
+                TransitionCauseResponse::Requested,
+                Some(requested_by),
+                failure,
+                None,
+            ),
+            TransitionCause::ProcessReady => {
+                (TransitionCauseResponse::ProcessReady, None, failure, None)
+            }
+            TransitionCause::ProcessNotReady(reason) => (
+                TransitionCauseResponse::ProcessNotReady,
+                None,
+                Some(reason),
+                None,
+            ),
+            TransitionCause::ProcessFinished(exit_reason) => (
+                TransitionCauseResponse::ProcessFinished,
+                None,
+                failure,
+                Some(exit_reason),
+            ),
+            TransitionCause::Restart => (TransitionCauseResponse::Restart, None, failure, None),
+        };
+        let exit_status = match exit_reason {
+            Some(ExitReason::Exited(exit_status) | ExitReason::Stopped(exit_status)) => {
+                Some(exit_status)
+            }
+            _ => None,
+        };
+        TransitionResponse {
+            timestamp: DateTime::<Utc>::from(value.timestamp)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            generation: value.generation,
+            state: (&value.state).into(),
+            alias,
+            next_alias,
+            cause,
+            requested_by,
+            reason,
+            exit_reason: exit_reason.map(|exit_reason| match exit_reason {
+                ExitReason::Exited(_) => ExitReasonResponse::Exited,
+                ExitReason::Stopped(_) => ExitReasonResponse::Stopped,
+                ExitReason::Killed => ExitReasonResponse::Killed,
+                ExitReason::Unknown => ExitReasonResponse::Unknown,
+            }),
+            exit_code: exit_status.and_then(|exit_status| exit_status.code()),
+            exit_signal: exit_status.and_then(|exit_status| exit_status.signal()),
+            startup_millis: value
+                .startup_duration
+                .map(|startup_duration| startup_duration.as_millis() as u64),
         }
     }
 }
@@ -160,6 +510,9 @@ pub struct LlamaCppRunConfigDto {
         default = "default_to_false"
     )]
     pub embeddings: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub backend_host: Option<String>,
 }
 
 impl LlamaCppRunConfigDto {
@@ -205,7 +558,9 @@ impl LlamaCppRunConfigDto {
                 reasoning: self.reasoning.clone(),
                 reasoning_budget: self.reasoning_budget,
                 no_cache_prompt: self.no_cache_prompt,
+                resource_limits: Default::default(),
                 embeddings: self.embeddings,
+                backend_host: self.backend_host,
             }),
         }
     }
@@ -253,14 +608,16 @@ impl From<LlamaCppRunConfig> for LlamaCppRunConfigDto {
             reasoning_budget: value.args_handle.reasoning_budget,
             no_cache_prompt: value.args_handle.no_cache_prompt,
             embeddings: value.args_handle.embeddings,
+            backend_host: value.args_handle.backend_host.clone(),
         }
     }
 }
 
+/// also returns the (ollama-style) 'keep_alive' a client may send along with its request
 pub async fn try_map_request_body_to_create_chat_completion_request(
     request: Request,
     model_alias: impl AsRef<str>,
-) -> Result<CreateChatCompletionRequest, StatusCode> {
+) -> Result<(CreateChatCompletionRequest, Option<KeepAlive>), StatusCode> {
     let sent_from_ui = {
         if let Some(referer) = request.headers().get(header::REFERER) {
             if let Ok(referer) = referer.to_str()
@@ -304,16 +661,18 @@ pub async fn try_map_request_body_to_create_chat_completion_request(
         .map(|mut create_chat_completions_request| {
             if sent_from_ui {
                 create_chat_completions_request.model = model_alias.as_ref().to_string();
-                create_chat_completions_request
-            } else {
-                create_chat_completions_request
             }
+            (
+                create_chat_completions_request,
+                extract_keep_alive(&request_body),
+            )
         })
 }
 
+/// also returns the (ollama-style) 'keep_alive' a client may send along with its request
 pub async fn try_map_request_body_to_create_embedding_request(
     request: Request,
-) -> Result<CreateEmbeddingRequest, StatusCode> {
+) -> Result<(CreateEmbeddingRequest, Option<KeepAlive>), StatusCode> {
     let request_body = request
         .into_body()
         .into_data_stream()
@@ -325,11 +684,69 @@ pub async fn try_map_request_body_to_create_embedding_request(
         })?
         .to_bytes();
 
-    serde_json::from_str::<CreateEmbeddingRequest>(
-        String::from_utf8_lossy(request_body.trim_ascii()).as_ref(),
-    )
-    .map_err(|e| {
-        error!("error deserializing payload (expected as CreateChatCompletionRequest): {e}");
-        StatusCode::UNPROCESSABLE_ENTITY
-    })
+    let request_body = String::from_utf8_lossy(request_body.trim_ascii());
+    serde_json::from_str::<CreateEmbeddingRequest>(request_body.as_ref())
+        .map_err(|e| {
+            error!("error deserializing payload (expected as CreateChatCompletionRequest): {e}");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })
+        .map(|create_embedding_request| {
+            (create_embedding_request, extract_keep_alive(&request_body))
+        })
+}
+
+#[derive(Deserialize)]
+struct KeepAliveField {
+    keep_alive: Option<serde_json::Value>,
+}
+
+/// 'keep_alive' is either a number of seconds or a duration like "90s", "10m" or "1h"; negative
+/// values keep the model loaded forever
+fn extract_keep_alive(request_body: &str) -> Option<KeepAlive> {
+    let keep_alive = serde_json::from_str::<KeepAliveField>(request_body)
+        .ok()?
+        .keep_alive?;
+    let secs = match &keep_alive {
+        serde_json::Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        serde_json::Value::String(s) => {
+            let s = s.trim();
+            let (value, unit_secs) = if let Some(value) = s.strip_suffix('h') {
+                (value, 3600)
+            } else if let Some(value) = s.strip_suffix('m') {
+                (value, 60)
+            } else if let Some(value) = s.strip_suffix('s') {
+                (value, 1)
+            } else {
+                (s, 1)
+            };
+            value.trim().parse::<i64>().ok().map(|v| v * unit_secs)
+        }
+        _ => None,
+    };
+    if secs.is_none() {
+        warn!("ignoring invalid keep_alive '{keep_alive}'");
+    }
+    secs.map(KeepAlive::from_secs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn keep_alive_is_read_from_seconds_and_durations() {
+        let keep_alive = extract_keep_alive;
+        assert_eq!(keep_alive(r#"{"model":"m"}"#), None);
+        assert_eq!(
+            keep_alive(r#"{"keep_alive":90}"#),
+            Some(KeepAlive::For(Duration::from_secs(90)))
+        );
+        assert_eq!(
+            keep_alive(r#"{"keep_alive":"10m"}"#),
+            Some(KeepAlive::For(Duration::from_secs(600)))
+        );
+        assert_eq!(keep_alive(r#"{"keep_alive":-1}"#), Some(KeepAlive::Forever));
+        assert_eq!(keep_alive(r#"{"keep_alive":"soon"}"#), None);
+    }
 }
@@ -0,0 +1,252 @@
+use crate::{
+    application::{
+        middleware::{check_admin_auth, check_auth},
+        model::{LlamaCppProcessStateResponse, LoadModelRequest, NewAliasRequest},
+    },
+    domain::model::{CatalogError, ModelLoadError},
+    model::{ApplicationConfig, SecurityConfig},
+};
+use axum::{
+    body::Bytes,
+    extract::{Json as JsonExtract, Path, Query, State},
+    handler::Handler,
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::Json as JsonBody,
+    routing::{Router, get, post, put},
+};
+use serde::Deserialize;
+use staticmodelconfig::{ModelConfiguration, ModelRoute, ModelRoutes};
+use std::sync::Arc;
+
+// the status-code along with the reason why the request failed
+type ErrorResponse = (StatusCode, String);
+
+pub fn create_router(
+    config: Arc<dyn ApplicationConfig>,
+    security_config: Arc<dyn SecurityConfig>,
+) -> Router {
+    // changes of the catalog are written to disk and require the admin-key
+    let admin = || from_fn_with_state(security_config.clone(), check_admin_auth);
+    Router::new()
+        .route(
+            "/admin/models",
+            get(list_model_configurations).post(create_model_configuration.layer(admin())),
+        )
+        .route(
+            "/admin/models/{alias}",
+            get(get_model_configuration)
+                .put(update_model_configuration.layer(admin()))
+                .delete(delete_model_configuration.layer(admin())),
+        )
+        .route(
+            "/admin/models/{alias}/clone",
+            post(clone_model_configuration.layer(admin())),
+        )
+        .route("/admin/models/{alias}/load", post(load_model))
+        .route("/admin/models/{alias}/unload", post(unload_model))
+        .route(
+            "/admin/backends/{name}/save",
+            post(save_running_configuration.layer(admin())),
+        )
+        .route(
+            "/admin/routes",
+            get(get_model_routes).put(replace_model_routes.layer(admin())),
+        )
+        .route(
+            "/admin/routes/{name}",
+            put(set_model_route.layer(admin())).delete(delete_model_route.layer(admin())),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            security_config,
+            check_auth,
+        ))
+        .with_state(config)
+}
+
+fn catalog_error_response(catalog_error: CatalogError) -> ErrorResponse {
+    let status_code = match catalog_error {
+        CatalogError::UnknownAlias(_) | CatalogError::UnknownBackend(_) => StatusCode::NOT_FOUND,
+        CatalogError::AliasTaken(_) | CatalogError::NotRunning(_) => StatusCode::CONFLICT,
+        CatalogError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        CatalogError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status_code, catalog_error.to_string())
+}
+
+fn model_load_error_response(model_load_error: ModelLoadError) -> ErrorResponse {
+    let status_code = match model_load_error {
+        ModelLoadError::UnknownModel(_) => StatusCode::NOT_FOUND,
+        ModelLoadError::NotServed(_) => StatusCode::GATEWAY_TIMEOUT,
+        ModelLoadError::Failed(..) => StatusCode::SERVICE_UNAVAILABLE,
+        ModelLoadError::NotLoaded(_) => StatusCode::CONFLICT,
+        ModelLoadError::ContextSizeExceeded(..) => StatusCode::BAD_REQUEST,
+    };
+    (status_code, model_load_error.to_string())
+}
+
+async fn list_model_configurations(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+) -> JsonBody<Vec<ModelConfiguration>> {
+    JsonBody::from(config.model_catalog_service().list_model_configurations())
+}
+
+async fn get_model_configuration(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(alias): Path<String>,
+) -> Result<JsonBody<ModelConfiguration>, ErrorResponse> {
+    config
+        .model_catalog_service()
+        .get_model_configuration(&alias)
+        .map(JsonBody::from)
+        .map_err(catalog_error_response)
+}
+
+async fn create_model_configuration(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    JsonBody(model_configuration): JsonExtract<ModelConfiguration>,
+) -> Result<(StatusCode, JsonBody<ModelConfiguration>), ErrorResponse> {
+    config
+        .model_catalog_service()
+        .create_model_configuration(model_configuration.clone())
+        .map_err(catalog_error_response)?;
+    Ok((StatusCode::CREATED, JsonBody::from(model_configuration)))
+}
+
+async fn update_model_configuration(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(alias): Path<String>,
+    JsonBody(model_configuration): JsonExtract<ModelConfiguration>,
+) -> Result<JsonBody<ModelConfiguration>, ErrorResponse> {
+    config
+        .model_catalog_service()
+        .update_model_configuration(&alias, model_configuration.clone())
+        .map_err(catalog_error_response)?;
+    Ok(JsonBody::from(model_configuration))
+}
+
+async fn delete_model_configuration(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(alias): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    config
+        .model_catalog_service()
+        .delete_model_configuration(&alias)
+        .map_err(catalog_error_response)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn clone_model_configuration(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(alias): Path<String>,
+    JsonBody(new_alias_request): JsonExtract<NewAliasRequest>,
+) -> Result<(StatusCode, JsonBody<ModelConfiguration>), ErrorResponse> {
+    config
+        .model_catalog_service()
+        .clone_model_configuration(&alias, &new_alias_request.alias)
+        .map(|model_configuration| (StatusCode::CREATED, JsonBody::from(model_configuration)))
+        .map_err(catalog_error_response)
+}
+
+async fn save_running_configuration(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(name): Path<String>,
+    JsonBody(new_alias_request): JsonExtract<NewAliasRequest>,
+) -> Result<(StatusCode, JsonBody<ModelConfiguration>), ErrorResponse> {
+    config
+        .model_catalog_service()
+        .save_running_configuration(&name, &new_alias_request.alias)
+        .await
+        .map(|model_configuration| (StatusCode::CREATED, JsonBody::from(model_configuration)))
+        .map_err(catalog_error_response)
+}
+
+#[derive(Deserialize)]
+struct WaitQuery {
+    #[serde(default)]
+    wait: bool,
+}
+
+// the body (the run-params to override) is optional ; with 'wait' the response is sent once the
+// model is served
+async fn load_model(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(alias): Path<String>,
+    Query(query): Query<WaitQuery>,
+    body: Bytes,
+) -> Result<JsonBody<LlamaCppProcessStateResponse>, ErrorResponse> {
+    let load_model_request: LoadModelRequest = if body.is_empty() {
+        LoadModelRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?
+    };
+    config
+        .models_service()
+        .load_model(&alias, load_model_request.into(), query.wait)
+        .await
+        .map(|llamacpp_process_state| {
+            JsonBody(LlamaCppProcessStateResponse::from(llamacpp_process_state))
+        })
+        .map_err(model_load_error_response)
+}
+
+async fn unload_model(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(alias): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    config
+        .models_service()
+        .unload_model(&alias)
+        .await
+        .map_err(model_load_error_response)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_model_routes(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+) -> JsonBody<ModelRoutes> {
+    JsonBody::from(config.model_catalog_service().get_model_routes())
+}
+
+async fn replace_model_routes(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    JsonBody(model_routes): JsonExtract<ModelRoutes>,
+) -> Result<JsonBody<ModelRoutes>, ErrorResponse> {
+    config
+        .model_catalog_service()
+        .replace_model_routes(model_routes.clone())
+        .map_err(catalog_error_response)?;
+    Ok(JsonBody::from(model_routes))
+}
+
+#[derive(Deserialize)]
+struct KeyQuery {
+    // the key-name of the api-key the route applies to (all if not given)
+    key: Option<String>,
+}
+
+async fn set_model_route(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(name): Path<String>,
+    Query(query): Query<KeyQuery>,
+    JsonBody(model_route): JsonExtract<ModelRoute>,
+) -> Result<JsonBody<ModelRoute>, ErrorResponse> {
+    config
+        .model_catalog_service()
+        .set_model_route(&name, query.key.as_deref(), model_route.clone())
+        .map_err(catalog_error_response)?;
+    Ok(JsonBody::from(model_route))
+}
+
+async fn delete_model_route(
+    State(config): State<Arc<dyn ApplicationConfig>>,
+    Path(name): Path<String>,
+    Query(query): Query<KeyQuery>,
+) -> Result<StatusCode, ErrorResponse> {
+    config
+        .model_catalog_service()
+        .delete_model_route(&name, query.key.as_deref())
+        .map_err(catalog_error_response)?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,18 +1,29 @@
 use crate::{
     application::{
         middleware::check_auth,
-        model::{LlamaCppProcessStateResponse, LlamaCppRunConfigDto},
+        model::{
+            LlamaCppProcessStateResponse, LlamaCppRunConfigDto, LogLineResponse, TransitionResponse,
+        },
+    },
+    domain::{
+        model::{EMBEDDINGMODEL_BACKEND, LANGUAGEMODEL_BACKEND, REQUESTED_BY_ADMIN_API},
+        ports::ModelManagerServiceInPort,
     },
     model::ApplicationConfig,
     model::SecurityConfig,
 };
 use axum::{
-    extract::{Json as JsonExtract, State},
+    extract::{Json as JsonExtract, Path, Query, State},
     http::StatusCode,
-    response::Json as JsonBody,
-    routing::{Router, get},
+    response::{
+        IntoResponse, Json as JsonBody, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{Router, delete, get},
 };
-use std::sync::Arc;
+use serde::Deserialize;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::broadcast::error::RecvError;
 
 #[derive(Clone)]
 struct CombinedState {
@@ -36,12 +47,22 @@ pub fn create_router(
                 .put(start_llama_cpp_languagemodel_process)
                 .delete(stop_llamacpp_languagemodel),
         )
+        .route(
+            "/admin/llamacpp/llm/failures",
+            delete(reset_llamacpp_languagemodel_failures),
+        )
         .route(
             "/admin/llamacpp/embedding",
             get(get_llama_cpp_embeddingmodel_state)
                 .put(start_llama_cpp_embeddingmodel_process)
                 .delete(stop_llamacpp_embeddingmodel),
         )
+        .route(
+            "/admin/llamacpp/embedding/failures",
+            delete(reset_llamacpp_embeddingmodel_failures),
+        )
+        .route("/admin/backends/{name}/logs", get(get_backend_logs))
+        .route("/admin/backends/{name}/history", get(get_backend_history))
         .layer(axum::middleware::from_fn_with_state(
             security_config,
             check_auth,
@@ -82,7 +103,7 @@ async fn start_llama_cpp_languagemodel_process(
     let llamacpp_process_state: LlamaCppProcessStateResponse = combined_state
         .config
         .languagemodelmanager_service()
-        .start_llamacpp_process(llama_cpp_run_config)
+        .start_llamacpp_process(llama_cpp_run_config, REQUESTED_BY_ADMIN_API)
         .await
         .into();
     Ok(JsonBody::from(llamacpp_process_state))
@@ -97,7 +118,7 @@ async fn start_llama_cpp_embeddingmodel_process(
     let llamacpp_process_state: LlamaCppProcessStateResponse = combined_state
         .config
         .embeddingmodelmanager_service()
-        .start_llamacpp_process(llama_cpp_run_config)
+        .start_llamacpp_process(llama_cpp_run_config, REQUESTED_BY_ADMIN_API)
         .await
         .into();
     Ok(JsonBody::from(llamacpp_process_state))
@@ -107,7 +128,7 @@ async fn stop_llamacpp_languagemodel(State(combined_state): State<CombinedState>
     combined_state
         .config
         .languagemodelmanager_service()
-        .stop_llamacpp_process()
+        .stop_llamacpp_process(REQUESTED_BY_ADMIN_API)
         .await;
     StatusCode::NO_CONTENT
 }
@@ -116,7 +137,122 @@ async fn stop_llamacpp_embeddingmodel(State(combined_state): State<CombinedState
     combined_state
         .config
         .embeddingmodelmanager_service()
-        .stop_llamacpp_process()
+        .stop_llamacpp_process(REQUESTED_BY_ADMIN_API)
+        .await;
+    StatusCode::NO_CONTENT
+}
+
+async fn reset_llamacpp_languagemodel_failures(
+    State(combined_state): State<CombinedState>,
+) -> StatusCode {
+    combined_state
+        .config
+        .languagemodelmanager_service()
+        .reset_failures(REQUESTED_BY_ADMIN_API)
+        .await;
+    StatusCode::NO_CONTENT
+}
+
+async fn reset_llamacpp_embeddingmodel_failures(
+    State(combined_state): State<CombinedState>,
+) -> StatusCode {
+    combined_state
+        .config
+        .embeddingmodelmanager_service()
+        .reset_failures(REQUESTED_BY_ADMIN_API)
         .await;
     StatusCode::NO_CONTENT
 }
+
+#[derive(Deserialize)]
+struct FollowQuery {
+    #[serde(default)]
+    follow: bool,
+}
+
+// sends the buffered output of the backend as server-sent events ; with 'follow' new lines are
+// sent as they are written until the client disconnects
+async fn get_backend_logs(
+    State(combined_state): State<CombinedState>,
+    Path(name): Path<String>,
+    Query(query): Query<FollowQuery>,
+) -> Result<Response, StatusCode> {
+    let model_manager_service = model_manager_service_of(&combined_state, &name)?;
+    let (buffered_lines, mut receiver) = model_manager_service.process_log().subscribe();
+
+    let follow = query.follow;
+    let stream = async_stream::stream! {
+        for log_line in buffered_lines {
+            yield log_line_event(log_line.into());
+        }
+        if follow {
+            loop {
+                match receiver.recv().await {
+                    Ok(log_line) => yield log_line_event(log_line.into()),
+                    Err(RecvError::Lagged(skipped)) => {
+                        yield Ok(Event::default().event("lagged").data(skipped.to_string()))
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+    };
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+fn log_line_event(log_line: LogLineResponse) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .json_data(log_line)
+        .unwrap_or_else(|_| Event::default().comment("unserializable log-line")))
+}
+
+// sends the recorded state-transitions of the backend as server-sent events ; with 'follow' new
+// transitions are sent as they happen until the client disconnects
+async fn get_backend_history(
+    State(combined_state): State<CombinedState>,
+    Path(name): Path<String>,
+    Query(query): Query<FollowQuery>,
+) -> Result<Response, StatusCode> {
+    let model_manager_service = model_manager_service_of(&combined_state, &name)?;
+    let (buffered_transitions, mut receiver) = model_manager_service.process_history().subscribe();
+
+    let follow = query.follow;
+    let stream = async_stream::stream! {
+        for transition in buffered_transitions {
+            yield transition_event(transition.into());
+        }
+        if follow {
+            loop {
+                match receiver.recv().await {
+                    Ok(transition) => yield transition_event(transition.into()),
+                    Err(RecvError::Lagged(skipped)) => {
+                        yield Ok(Event::default().event("lagged").data(skipped.to_string()))
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+    };
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+fn transition_event(transition: TransitionResponse) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .json_data(transition)
+        .unwrap_or_else(|_| Event::default().comment("unserializable transition")))
+}
+
+fn model_manager_service_of(
+    combined_state: &CombinedState,
+    name: &str,
+) -> Result<Arc<dyn ModelManagerServiceInPort>, StatusCode> {
+    match name {
+        LANGUAGEMODEL_BACKEND => Ok(combined_state.config.languagemodelmanager_service()),
+        EMBEDDINGMODEL_BACKEND => Ok(combined_state.config.embeddingmodelmanager_service()),
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
@@ -1,11 +1,15 @@
 use crate::{
     application::{
-        middleware::check_auth,
+        middleware::{RequestContext, check_auth},
         model::{
             try_map_request_body_to_create_chat_completion_request,
             try_map_request_body_to_create_embedding_request,
         },
     },
+    domain::model::{
+        ActivityGuard, EMBEDDINGMODEL_BACKEND, LANGUAGEMODEL_BACKEND, ModelLoadError,
+        REQUESTED_BY_CHAT_COMMAND,
+    },
     model::ApplicationConfig,
     model::SecurityConfig,
 };
@@ -17,14 +21,29 @@ use axum::{
     Json,
     body::Body,
     extract::{Path, Query, Request, State},
-    http::{Response, StatusCode},
+    http::{HeaderValue, Response, StatusCode},
     response::IntoResponse,
     routing::{Router, any, get, head, post},
 };
-use futures_util::stream;
+use futures_util::{StreamExt, stream};
 use staticmodelconfig::ModelList;
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tracing::{error, info, trace, warn};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{Instrument, error, field::Empty, info, info_span, trace, warn};
+
+// names the concrete model which served the request (virtual model-names are resolved)
+const MODEL_HEADER: &str = "x-model";
+// the token-count of a prompt is estimated (the tokenizer of the model is not at hand)
+const CHARS_PER_TOKEN: u64 = 3;
+// the tokens of the chat-template framing a message
+const TOKENS_PER_MESSAGE: u64 = 8;
+// the tokens an image or audio-clip is embedded with
+const TOKENS_PER_MEDIA_PART: u64 = 1024;
+// the completion to make room for if the request does not limit it
+const DEFAULT_COMPLETION_TOKENS: u64 = 4096;
 
 pub fn create_router(
     config: Arc<dyn ApplicationConfig>,
@@ -144,21 +163,25 @@ async fn post_completions(
 async fn post_chat_completions_impl(
     application_config: Arc<dyn ApplicationConfig>,
     optional_parallel_backend_requests_to_set: Option<u8>,
-    request: Request,
+    mut request: Request,
 ) -> Result<Response<Body>, StatusCode> {
-    let mut chat_completions_request = try_map_request_body_to_create_chat_completion_request(
-        request,
-        application_config
-            .models_service()
-            .get_running_languagemodel_alias()
-            .await
-            .unwrap_or(
-                application_config
-                    .models_service()
-                    .get_default_languagemodel_alias(),
-            ),
-    )
-    .await?;
+    let request_context = RequestContext::of(&mut request);
+    let resolve_model_span = info_span!("resolve_model", model = Empty);
+    let (mut chat_completions_request, keep_alive_override) =
+        try_map_request_body_to_create_chat_completion_request(
+            request,
+            application_config
+                .models_service()
+                .get_running_languagemodel_alias()
+                .await
+                .unwrap_or(
+                    application_config
+                        .models_service()
+                        .get_default_languagemodel_alias(),
+                ),
+        )
+        .instrument(resolve_model_span.clone())
+        .await?;
 
     trace!("request: {:#?}", chat_completions_request);
 
@@ -171,7 +194,7 @@ async fn post_chat_completions_impl(
         use std::convert::Infallible;
         application_config
             .languagemodelmanager_service()
-            .stop_llamacpp_process()
+            .stop_llamacpp_process(REQUESTED_BY_CHAT_COMMAND)
             .await;
         let response_json = serde_json::to_string(&CreateChatCompletionStreamResponse {
             id: "none".into(),
@@ -206,10 +229,33 @@ async fn post_chat_completions_impl(
         return Ok(Sse::new(stream).into_response());
     }
 
-    let requested_model = extract_model_override_from_last_user_text_in_chat_completions_request(
-        &mut chat_completions_request,
-    )
-    .unwrap_or(chat_completions_request.model.clone());
+    let requested_model = resolve_model_span.in_scope(|| {
+        let requested_model =
+            extract_model_override_from_last_user_text_in_chat_completions_request(
+                &mut chat_completions_request,
+            )
+            .unwrap_or(chat_completions_request.model.clone());
+        application_config
+            .models_service()
+            .resolve_model_name(
+                &requested_model,
+                request_context.snapshot().key_name.as_deref(),
+            )
+            .map_err(unresolved_model)
+    })?;
+    let requested_model = application_config
+        .models_service()
+        .select_context_size(
+            &requested_model,
+            estimate_required_tokens(&chat_completions_request),
+        )
+        .instrument(resolve_model_span.clone())
+        .await;
+    // the backend reports the model it was asked for, so responses name the concrete model
+    chat_completions_request.model = requested_model.clone();
+    resolve_model_span.record("model", &requested_model);
+    drop(resolve_model_span);
+    request_context.set_model(&requested_model);
 
     if let Some(parallel_backend_requests_to_set) = optional_parallel_backend_requests_to_set {
         application_config
@@ -217,19 +263,30 @@ async fn post_chat_completions_impl(
             .set_parallel_backend_requests(parallel_backend_requests_to_set);
     }
 
+    // tracked before ensuring the model is served so it is not considered idle meanwhile
+    let activity = application_config
+        .idle_unload_service()
+        .track_activity(LANGUAGEMODEL_BACKEND, keep_alive_override);
+
+    let queue_start = Instant::now();
     application_config
         .models_service()
         .ensure_requested_languagemodel_is_served(&requested_model, Duration::from_mins(3))
+        .instrument(info_span!("ensure_model_served", model = requested_model))
         .await
-        .map_err(|_| {
-            error!("error serving requested model");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .map_err(unserved_model)?;
+    request_context.set_queue_time(queue_start.elapsed());
 
     application_config
         .openai_chat_completions_service()
         .process_chat_completions_request(chat_completions_request)
         .await
+        .map(|response| {
+            with_model_header(
+                busy_until_body_is_done(response, activity),
+                &requested_model,
+            )
+        })
 }
 
 // EMBEDDINGS
@@ -251,11 +308,23 @@ async fn post_embeddings(
 async fn post_embeddings_impl(
     application_config: Arc<dyn ApplicationConfig>,
     optional_parallel_backend_requests_to_set: Option<u8>,
-    request: Request,
+    mut request: Request,
 ) -> Result<Response<Body>, StatusCode> {
-    let embedding_request = try_map_request_body_to_create_embedding_request(request).await?;
+    let request_context = RequestContext::of(&mut request);
+    let (mut embedding_request, keep_alive_override) =
+        try_map_request_body_to_create_embedding_request(request)
+            .instrument(info_span!("resolve_model"))
+            .await?;
 
-    let requested_model = embedding_request.model.clone();
+    let requested_model = application_config
+        .models_service()
+        .resolve_model_name(
+            &embedding_request.model,
+            request_context.snapshot().key_name.as_deref(),
+        )
+        .map_err(unresolved_model)?;
+    embedding_request.model = requested_model.clone();
+    request_context.set_model(&requested_model);
 
     if let Some(parallel_backend_requests_to_set) = optional_parallel_backend_requests_to_set {
         application_config
@@ -263,19 +332,29 @@ async fn post_embeddings_impl(
             .set_parallel_backend_requests(parallel_backend_requests_to_set);
     }
 
+    let activity = application_config
+        .idle_unload_service()
+        .track_activity(EMBEDDINGMODEL_BACKEND, keep_alive_override);
+
+    let queue_start = Instant::now();
     application_config
         .models_service()
         .ensure_requested_embeddingmodel_is_served(&requested_model, Duration::from_mins(3))
+        .instrument(info_span!("ensure_model_served", model = requested_model))
         .await
-        .map_err(|_| {
-            error!("error serving requested embedding model");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .map_err(unserved_model)?;
+    request_context.set_queue_time(queue_start.elapsed());
 
     application_config
         .openai_embeddings_service()
         .process_embedding_request(embedding_request)
         .await
+        .map(|response| {
+            with_model_header(
+                busy_until_body_is_done(response, activity),
+                &requested_model,
+            )
+        })
 }
 
 // FALLBACK
@@ -310,10 +389,50 @@ async fn api_fallback_impl(
             .set_parallel_backend_requests(parallel_backend_requests_to_set);
     }
 
+    let activity = application_config
+        .idle_unload_service()
+        .track_activity(LANGUAGEMODEL_BACKEND, None);
+
     application_config
         .openai_chat_completions_service()
         .forward_api_request(request)
         .await
+        .map(|response| busy_until_body_is_done(response, activity))
+}
+
+// the backend is busy until the (possibly streamed) response-body is sent completely
+fn busy_until_body_is_done(response: Response<Body>, activity: ActivityGuard) -> Response<Body> {
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &activity;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+// starting a backend is not worth it for a model which is not in the catalog
+fn unresolved_model(model_load_error: ModelLoadError) -> StatusCode {
+    warn!("{model_load_error}");
+    match model_load_error {
+        ModelLoadError::ContextSizeExceeded(..) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::NOT_FOUND,
+    }
+}
+
+// a model which failed (or is refused as it crashed repeatedly) is answered right away
+fn unserved_model(model_load_error: ModelLoadError) -> StatusCode {
+    error!("error serving requested model: {model_load_error}");
+    match model_load_error {
+        ModelLoadError::Failed(..) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn with_model_header(mut response: Response<Body>, model: &str) -> Response<Body> {
+    if let Ok(model) = HeaderValue::from_str(model) {
+        response.headers_mut().insert(MODEL_HEADER, model);
+    }
+    response
 }
 
 async fn chat_handler(
@@ -326,7 +445,7 @@ async fn chat_handler(
         .models_service()
         .ensure_any_languagemodel_is_served(&default_model_alias, Duration::from_mins(3))
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(unserved_model)?;
     application_config
         .openai_chat_completions_service()
         .get_chat()
@@ -343,6 +462,49 @@ async fn chat_handler_assets(
         .await
 }
 
+// the tokens of the messages and tools plus the ones of the completion (rather too many than too
+// few)
+fn estimate_required_tokens(chat_completions_request: &CreateChatCompletionRequest) -> u64 {
+    // the chars of the texts and the count of media-parts
+    fn count(value: &serde_json::Value, chars: &mut u64, media_parts: &mut u64) {
+        match value {
+            serde_json::Value::String(text) => *chars += text.chars().count() as u64,
+            serde_json::Value::Array(values) => values
+                .iter()
+                .for_each(|value| count(value, chars, media_parts)),
+            serde_json::Value::Object(map)
+                if map.contains_key("image_url") || map.contains_key("input_audio") =>
+            {
+                *media_parts += 1
+            }
+            serde_json::Value::Object(map) => map
+                .values()
+                .for_each(|value| count(value, chars, media_parts)),
+            _ => {}
+        }
+    }
+    let (mut chars, mut media_parts) = (0, 0);
+    count(
+        &serde_json::to_value((
+            &chat_completions_request.messages,
+            &chat_completions_request.tools,
+        ))
+        .unwrap_or_default(),
+        &mut chars,
+        &mut media_parts,
+    );
+    #[allow(deprecated)]
+    let completion_tokens = chat_completions_request
+        .max_completion_tokens
+        .or(chat_completions_request.max_tokens)
+        .map(u64::from)
+        .unwrap_or(DEFAULT_COMPLETION_TOKENS);
+    chars.div_ceil(CHARS_PER_TOKEN)
+        + chat_completions_request.messages.len() as u64 * TOKENS_PER_MESSAGE
+        + media_parts * TOKENS_PER_MEDIA_PART
+        + completion_tokens
+}
+
 fn process_last_user_prompt_in_chat_completions(
     chat_completions_request: &mut CreateChatCompletionRequest,
     proc: impl Fn(&mut String) -> Option<String>,
@@ -412,3 +574,133 @@ fn check_user_prompt_for_stop_llamacpp(
         false
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::{
+        ports::{
+            AuditServiceInPort, HealthServiceInPort, IdleUnloadServiceInPort, MetricsServiceInPort,
+            ModelCatalogServiceInPort, ModelManagerServiceInPort, ModelsServiceInPort,
+            OpenAiRequestForwardPServiceInPort,
+        },
+        service::fakes::{FakeModelLoader, models_service_with},
+    };
+
+    // the requests under test are answered before any other service is needed
+    struct ModelsOnlyConfig(Arc<dyn ModelsServiceInPort>);
+
+    impl ApplicationConfig for ModelsOnlyConfig {
+        fn openai_chat_completions_service(&self) -> Arc<dyn OpenAiRequestForwardPServiceInPort> {
+            unimplemented!()
+        }
+        fn openai_embeddings_service(&self) -> Arc<dyn OpenAiRequestForwardPServiceInPort> {
+            unimplemented!()
+        }
+        fn languagemodelmanager_service(&self) -> Arc<dyn ModelManagerServiceInPort> {
+            unimplemented!()
+        }
+        fn embeddingmodelmanager_service(&self) -> Arc<dyn ModelManagerServiceInPort> {
+            unimplemented!()
+        }
+        fn models_service(&self) -> Arc<dyn ModelsServiceInPort> {
+            self.0.clone()
+        }
+        fn model_catalog_service(&self) -> Arc<dyn ModelCatalogServiceInPort> {
+            unimplemented!()
+        }
+        fn health_service(&self) -> Arc<dyn HealthServiceInPort> {
+            unimplemented!()
+        }
+        fn metrics_service(&self) -> Arc<dyn MetricsServiceInPort> {
+            unimplemented!()
+        }
+        fn idle_unload_service(&self) -> Arc<dyn IdleUnloadServiceInPort> {
+            unimplemented!()
+        }
+        fn audit_service(&self) -> Option<Arc<dyn AuditServiceInPort>> {
+            None
+        }
+    }
+
+    fn request(request: serde_json::Value) -> CreateChatCompletionRequest {
+        serde_json::from_value(request).unwrap()
+    }
+
+    #[test]
+    fn required_tokens_are_estimated_from_the_texts_of_the_request() {
+        // 300 chars of content and 4 of the role
+        let text_only = request(serde_json::json!({
+            "model": "model@auto",
+            "messages": [{"role": "user", "content": "x".repeat(300)}]
+        }));
+        assert_eq!(
+            estimate_required_tokens(&text_only),
+            102 + TOKENS_PER_MESSAGE + DEFAULT_COMPLETION_TOKENS
+        );
+
+        // media-parts count as a whole regardless of their (base64-encoded) size
+        let multimodal = request(serde_json::json!({
+            "model": "model@auto",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "describe"},
+                {"type": "image_url", "image_url": {"url": format!("data:image/png;base64,{}", "A".repeat(100_000))}}
+            ]}]
+        }));
+        assert_eq!(
+            estimate_required_tokens(&multimodal),
+            6 + TOKENS_PER_MESSAGE + TOKENS_PER_MEDIA_PART + DEFAULT_COMPLETION_TOKENS
+        );
+
+        // the completion is limited by the request
+        let limited = request(serde_json::json!({
+            "model": "model@auto",
+            "messages": [
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": "hi"}
+            ],
+            "max_tokens": 100
+        }));
+        assert_eq!(
+            estimate_required_tokens(&limited),
+            7 + 2 * TOKENS_PER_MESSAGE + 100
+        );
+        let limited = request(serde_json::json!({
+            "model": "model@auto",
+            "messages": [{"role": "user", "content": "hi"}],
+            "max_tokens": 100,
+            "max_completion_tokens": 200
+        }));
+        assert_eq!(
+            estimate_required_tokens(&limited),
+            2 + TOKENS_PER_MESSAGE + 200
+        );
+    }
+
+    #[tokio::test]
+    async fn context_sizes_beyond_the_max_ctx_size_are_rejected_as_bad_requests() {
+        let (_, models_service) =
+            models_service_with(FakeModelLoader::with_models(&["model-a"]), 1);
+        let config: Arc<dyn ApplicationConfig> = Arc::new(ModelsOnlyConfig(models_service));
+        let post = async |model: &str| {
+            let request = Request::builder()
+                .uri("/v1/chat/completions")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": model,
+                        "messages": [{"role": "user", "content": "hi"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap();
+            post_completions(State(config.clone()), request)
+                .await
+                .map(|response| response.status())
+        };
+
+        // the max-ctx-size of the model is 131072
+        assert_eq!(post("model-a@999999").await, Err(StatusCode::BAD_REQUEST));
+        assert_eq!(post("model-a@256k").await, Err(StatusCode::BAD_REQUEST));
+        assert_eq!(post("unknown@32k").await, Err(StatusCode::NOT_FOUND));
+    }
+}
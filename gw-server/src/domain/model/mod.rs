@@ -0,0 +1,444 @@
+use crate::domain::ports::{LlamaCppControllerOutPort, OpenAiClientOutPort};
+use inference_backends::LlamaCppProcessState;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// name of the backend serving language-models (used for health-reports and metrics)
+pub const LANGUAGEMODEL_BACKEND: &str = "llm";
+/// name of the backend serving embedding-models (used for health-reports and metrics)
+pub const EMBEDDINGMODEL_BACKEND: &str = "embedding";
+
+/// who requested a start or stop of a backend (recorded in its history)
+pub const REQUESTED_BY_ADMIN_API: &str = "admin-api";
+pub const REQUESTED_BY_CHAT_COMMAND: &str = "chat-command";
+pub const REQUESTED_BY_MODEL_REQUEST: &str = "model-request";
+pub const REQUESTED_BY_IDLE_UNLOAD: &str = "idle-unload";
+pub const REQUESTED_BY_SHUTDOWN: &str = "shutdown";
+
+/// a managed backend together with the client used to talk to it
+#[derive(Clone)]
+pub struct MonitoredBackend {
+    pub name: String,
+    pub controller: Arc<dyn LlamaCppControllerOutPort>,
+    pub client: Arc<dyn OpenAiClientOutPort>,
+}
+
+/// result of polling the health-endpoint of a backend
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendProbe {
+    // the backend answered that it is ready to serve requests
+    Healthy,
+
+    // the backend is reachable but still loading its model
+    Loading,
+
+    // the backend answered with an unexpected status-code
+    Unhealthy(u16),
+
+    // the backend could not be reached at all (provides the reason)
+    Unreachable(String),
+}
+
+/// health of a backend as derived from its process-state and the result of probing it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackendStatus {
+    // no process is running (models are started on demand)
+    Idle,
+
+    // the process was started but does not answer yet
+    Starting,
+
+    // the process answers but is still loading its model
+    Loading,
+
+    // the process is running and answers its health-endpoint
+    Ready,
+
+    // the process is alive but its health-endpoint fails
+    Degraded,
+
+    // the process is being stopped
+    Stopping,
+
+    // the process crashed and is not restarted (anymore)
+    Failed,
+}
+
+/// short name of a process-state as used in reports and metrics
+pub fn process_state_name(process_state: &LlamaCppProcessState) -> &'static str {
+    match process_state {
+        LlamaCppProcessState::Stopped => "stopped",
+        LlamaCppProcessState::Starting(..) => "starting",
+        LlamaCppProcessState::Running(..) => "running",
+        LlamaCppProcessState::Stopping(..) => "stopping",
+        LlamaCppProcessState::Failed(_, _) => "failed",
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackendHealth {
+    pub backend: String,
+    pub process_state: LlamaCppProcessState,
+    pub probe: Option<BackendProbe>,
+    pub status: BackendStatus,
+}
+
+impl BackendHealth {
+    pub fn from_process_state(
+        backend: impl Into<String>,
+        process_state: LlamaCppProcessState,
+    ) -> Self {
+        let status = match process_state {
+            LlamaCppProcessState::Stopped => BackendStatus::Idle,
+            LlamaCppProcessState::Starting(..) => BackendStatus::Starting,
+            LlamaCppProcessState::Running(..) => BackendStatus::Ready,
+            LlamaCppProcessState::Stopping(..) => BackendStatus::Stopping,
+            LlamaCppProcessState::Failed(_, _) => BackendStatus::Failed,
+        };
+        Self {
+            backend: backend.into(),
+            process_state,
+            probe: None,
+            status,
+        }
+    }
+
+    /// combines the process-state with the result of probing the backend
+    pub fn with_probe(mut self, probe: BackendProbe) -> Self {
+        self.status = match (&self.process_state, &probe) {
+            (LlamaCppProcessState::Starting(..), BackendProbe::Healthy) => BackendStatus::Starting,
+            (LlamaCppProcessState::Starting(..), BackendProbe::Loading) => BackendStatus::Loading,
+            (LlamaCppProcessState::Starting(..), _) => BackendStatus::Starting,
+            (LlamaCppProcessState::Running(..), BackendProbe::Healthy) => BackendStatus::Ready,
+            (LlamaCppProcessState::Running(..), BackendProbe::Loading) => BackendStatus::Loading,
+            (LlamaCppProcessState::Running(..), _) => BackendStatus::Degraded,
+            _ => self.status,
+        };
+        self.probe = Some(probe);
+        self
+    }
+
+    /// the alias of the model the backend serves (or is about to serve)
+    pub fn model_alias(&self) -> Option<String> {
+        match &self.process_state {
+            LlamaCppProcessState::Stopped => None,
+            LlamaCppProcessState::Starting(config, _)
+            | LlamaCppProcessState::Running(config, _)
+            | LlamaCppProcessState::Stopping(config, None, _)
+            | LlamaCppProcessState::Failed(config, _) => Some(config.args_handle.alias.clone()),
+            LlamaCppProcessState::Stopping(_, Some(next_config), _) => {
+                Some(next_config.args_handle.alias.clone())
+            }
+        }
+    }
+
+    /// why the backend failed (if it did)
+    pub fn failure_reason(&self) -> Option<String> {
+        if let LlamaCppProcessState::Failed(_, reason) = &self.process_state {
+            Some(reason.clone())
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    pub backends: Vec<BackendHealth>,
+}
+
+impl HealthReport {
+    /// the gateway is ready unless a backend failed: models are loaded on demand, so a backend
+    /// starting, loading or stopping (e.g. while switching models) still accepts requests
+    pub fn is_ready(&self) -> bool {
+        self.backends
+            .iter()
+            .all(|b| b.status != BackendStatus::Failed)
+    }
+}
+
+/// one entry of the audit-log, describing a single handled api-request
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    pub timestamp: SystemTime,
+    pub key_name: Option<String>,
+    pub method: String,
+    pub route: String,
+    pub model: Option<String>,
+    pub status: u16,
+    pub duration: Duration,
+    // time spent waiting for the requested model to be served
+    pub queue_time: Option<Duration>,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    pub trace_id: Option<String>,
+    pub request_body: Option<serde_json::Value>,
+    pub response_body: Option<serde_json::Value>,
+}
+
+/// what the audit-log captures and which parts of captured bodies are redacted
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    pub capture_request_bodies: bool,
+    pub capture_response_bodies: bool,
+    // captured bodies exceeding this size (after redaction) are truncated
+    pub max_captured_body_bytes: usize,
+    // values of json-fields with these names (case-insensitive) are replaced
+    pub redacted_keys: Vec<String>,
+    // replaces base64-encoded data-urls and 'b64_json'-fields (images, audio)
+    pub redact_images: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            capture_request_bodies: false,
+            capture_response_bodies: false,
+            max_captured_body_bytes: 64 * 1024,
+            redacted_keys: [
+                "api_key",
+                "apikey",
+                "api-key",
+                "authorization",
+                "password",
+                "secret",
+                "client_secret",
+                "token",
+                "access_token",
+                "refresh_token",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            redact_images: true,
+        }
+    }
+}
+
+/// how long a model stays loaded after the last request it served
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeepAlive {
+    Forever,
+    For(Duration),
+}
+
+impl KeepAlive {
+    /// negative values keep the model loaded forever
+    pub fn from_secs(secs: i64) -> Self {
+        if secs < 0 {
+            KeepAlive::Forever
+        } else {
+            KeepAlive::For(Duration::from_secs(secs as u64))
+        }
+    }
+}
+
+/// what is known about the requests a backend served recently
+#[derive(Debug)]
+pub struct BackendActivity {
+    pub in_flight: usize,
+    pub last_activity: Instant,
+    // keep-alive sent along with the latest request (replaces the one of the catalog)
+    pub keep_alive_override: Option<KeepAlive>,
+    // alias of the model the activity refers to (a newly loaded model starts out fresh)
+    pub served_alias: Option<String>,
+}
+
+impl Default for BackendActivity {
+    fn default() -> Self {
+        Self {
+            in_flight: 0,
+            last_activity: Instant::now(),
+            keep_alive_override: None,
+            served_alias: None,
+        }
+    }
+}
+
+/// marks a backend as busy as long as it is alive
+pub struct ActivityGuard {
+    activity: Arc<Mutex<BackendActivity>>,
+}
+
+impl ActivityGuard {
+    pub fn new(
+        activity: Arc<Mutex<BackendActivity>>,
+        keep_alive_override: Option<KeepAlive>,
+    ) -> Self {
+        {
+            let mut activity = activity
+                .lock()
+                .expect("locking backend-activity must not fail");
+            activity.in_flight += 1;
+            activity.last_activity = Instant::now();
+            if keep_alive_override.is_some() {
+                activity.keep_alive_override = keep_alive_override;
+            }
+        }
+        Self { activity }
+    }
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        let mut activity = self
+            .activity
+            .lock()
+            .expect("locking backend-activity must not fail");
+        activity.in_flight -= 1;
+        activity.last_activity = Instant::now();
+    }
+}
+
+/// why a request on the model-catalog failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogError {
+    UnknownAlias(String),
+    AliasTaken(String),
+    // the configuration was refused ; provides the reason
+    Invalid(String),
+    UnknownBackend(String),
+    // the backend does not run any model
+    NotRunning(String),
+    // the catalog could not be read or written
+    Io(String),
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownAlias(alias) => write!(f, "no model with alias '{alias}'"),
+            Self::AliasTaken(alias) => write!(f, "a model with alias '{alias}' exists already"),
+            Self::Invalid(reason) => write!(f, "{reason}"),
+            Self::UnknownBackend(name) => write!(f, "no backend named '{name}'"),
+            Self::NotRunning(name) => write!(f, "backend '{name}' does not run any model"),
+            Self::Io(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+/// run-params replacing the ones configured for the gateway when a model is loaded via the
+/// admin-api ; they apply to that model only and are dropped once another model is started on
+/// its backend
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunOverrides {
+    pub parallel: Option<u8>,
+    pub threads: Option<i8>,
+    pub threads_batch: Option<i8>,
+    // merged into the environment configured for the gateway
+    pub env: HashMap<String, String>,
+}
+
+/// why loading (or unloading) a model failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelLoadError {
+    UnknownModel(String),
+    // the model did not get served in time
+    NotServed(String),
+    // the process of the model failed (or is refused as it crashed repeatedly)
+    Failed(String, String),
+    // the model is not loaded by any backend
+    NotLoaded(String),
+    // the context-size of the alias exceeds the max-ctx-size of its model
+    ContextSizeExceeded(String, u64),
+}
+
+impl std::fmt::Display for ModelLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownModel(alias) => write!(f, "no model with alias '{alias}'"),
+            Self::NotServed(alias) => write!(f, "model '{alias}' did not get served"),
+            Self::Failed(alias, reason) => write!(f, "model '{alias}' failed: {reason}"),
+            Self::NotLoaded(alias) => write!(f, "model '{alias}' is not loaded"),
+            Self::ContextSizeExceeded(alias, max_ctx_size) => write!(
+                f,
+                "context-size of '{alias}' exceeds the max-ctx-size {max_ctx_size}"
+            ),
+        }
+    }
+}
+
+/// the requests a backend is busy with (waiting for their model or being answered)
+#[derive(Debug, Clone)]
+pub struct BackendActivityReport {
+    pub backend: String,
+    pub in_flight: usize,
+    pub idle_for: Duration,
+    pub served_alias: Option<String>,
+}
+
+/// a request handled recently (kept to be shown on the dashboard)
+#[derive(Debug, Clone)]
+pub struct RecentRequest {
+    pub timestamp: SystemTime,
+    pub route: String,
+    pub model: Option<String>,
+    pub status: u16,
+    pub duration: Duration,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use inference_backends::{LlamaCppConfigArgs, LlamaCppRunConfig};
+
+    #[test]
+    fn the_gateway_is_ready_unless_a_backend_failed() {
+        let config = LlamaCppRunConfig {
+            args_handle: Arc::new(LlamaCppConfigArgs::default()),
+            env_handle: Default::default(),
+            parallel: 1,
+            threads: 1,
+            threads_batch: 1,
+        };
+        let report = |backends: Vec<BackendHealth>| HealthReport { backends }.is_ready();
+        let idle = BackendHealth::from_process_state("llm", LlamaCppProcessState::Stopped);
+        let running = || {
+            BackendHealth::from_process_state(
+                "llm",
+                LlamaCppProcessState::Running(config.clone(), None),
+            )
+        };
+        let starting = || {
+            BackendHealth::from_process_state(
+                "llm",
+                LlamaCppProcessState::Starting(config.clone(), None),
+            )
+        };
+
+        assert!(report(vec![
+            idle.clone(),
+            running().with_probe(BackendProbe::Healthy)
+        ]));
+        // backends switching models (starting, loading, stopping) keep the gateway ready
+        assert!(report(vec![
+            idle.clone(),
+            running().with_probe(BackendProbe::Loading)
+        ]));
+        assert!(report(vec![starting().with_probe(BackendProbe::Healthy)]));
+        assert!(report(vec![starting().with_probe(BackendProbe::Loading)]));
+        assert!(report(vec![BackendHealth::from_process_state(
+            "llm",
+            LlamaCppProcessState::Stopping(config.clone(), None, std::time::Instant::now()),
+        )]));
+        // a running backend failing its probe is degraded but still accepts requests
+        assert!(report(vec![
+            running().with_probe(BackendProbe::Unhealthy(500))
+        ]));
+        assert!(report(vec![
+            running().with_probe(BackendProbe::Unreachable("refused".into()))
+        ]));
+        let failed = BackendHealth::from_process_state(
+            "llm",
+            LlamaCppProcessState::Failed(config.clone(), "crashed".into()),
+        );
+        assert_eq!(failed.failure_reason().as_deref(), Some("crashed"));
+        assert!(!report(vec![
+            idle,
+            failed.with_probe(BackendProbe::Healthy)
+        ]));
+    }
+}
@@ -1,9 +1,15 @@
+use crate::domain::model::{
+    ActivityGuard, AuditRecord, BackendActivityReport, BackendProbe, CatalogError, HealthReport,
+    KeepAlive, ModelLoadError, RecentRequest, RunOverrides,
+};
 use async_openai::types::{chat::CreateChatCompletionRequest, embeddings::CreateEmbeddingRequest};
 use async_trait::async_trait;
 use axum::{extract::Request, http::StatusCode, response::Response};
-use inference_backends::{LlamaCppConfigArgs, LlamaCppProcessState, LlamaCppRunConfig};
-use staticmodelconfig::ModelConfiguration;
+use inference_backends::{
+    LlamaCppConfigArgs, LlamaCppProcessHistory, LlamaCppProcessState, LlamaCppRunConfig, ProcessLog,
+};
 use staticmodelconfig::ModelList;
+use staticmodelconfig::{ModelConfiguration, ModelRoute, ModelRoutes};
 use std::{sync::Arc, time::Duration};
 
 /// IN-PORTS
@@ -30,11 +36,21 @@ pub trait OpenAiRequestForwardPServiceInPort: Send + Sync + 'static {
 #[async_trait]
 pub trait ModelManagerServiceInPort: Send + Sync + 'static {
     async fn get_llamacpp_state(&self) -> LlamaCppProcessState;
-    async fn stop_llamacpp_process(&self);
+    async fn stop_llamacpp_process(&self, requested_by: &str);
     async fn start_llamacpp_process(
         &self,
         llamacpp_run_config: LlamaCppRunConfig,
+        requested_by: &str,
     ) -> LlamaCppProcessState;
+
+    /// allows configs that crashed repeatedly to be started again
+    async fn reset_failures(&self, requested_by: &str);
+
+    /// the recent output of the backend-processes
+    fn process_log(&self) -> ProcessLog;
+
+    /// the recent state-transitions of the backend
+    fn process_history(&self) -> LlamaCppProcessHistory;
 }
 
 #[async_trait]
@@ -43,17 +59,17 @@ pub trait ModelsServiceInPort: Send + Sync + 'static {
         &self,
         default_model_alias: &str,
         timeout: Duration,
-    ) -> Result<(), ()>;
+    ) -> Result<(), ModelLoadError>;
     async fn ensure_requested_languagemodel_is_served(
         &self,
         requested_model_variant: &str,
         timeout: Duration,
-    ) -> Result<(), ()>;
+    ) -> Result<(), ModelLoadError>;
     async fn ensure_requested_embeddingmodel_is_served(
         &self,
         requested_model_variant: &str,
         timeout: Duration,
-    ) -> Result<(), ()>;
+    ) -> Result<(), ModelLoadError>;
 
     /// returns the ModelList to return on the models-endpoint
     fn get_models(&self) -> Arc<ModelList>;
@@ -63,11 +79,127 @@ pub trait ModelsServiceInPort: Send + Sync + 'static {
 
     fn get_default_languagemodel_alias(&self) -> String;
 
+    /// the model the (possibly virtual) model-name is routed to for the api-key with the
+    /// key-name ; names which are not virtual are returned as they are ; fails for models which
+    /// are not in the catalog (e.g. the target of a route was deleted)
+    fn resolve_model_name(
+        &self,
+        requested_model: &str,
+        key_name: Option<&str>,
+    ) -> Result<String, ModelLoadError>;
+
+    /// the context-size-aware alias of the smallest context-size holding the required tokens
+    /// (per parallel request) if the model is requested with the 'auto' context-size-hint ; the
+    /// running instance of the model is kept if its context-size suffices already ; other
+    /// model-names are returned as they are
+    async fn select_context_size(&self, requested_model: &str, required_tokens: u64) -> String;
+
     fn get_default_embeddingmodel_alias(&self) -> String;
 
+    /// the alias of the catalog the (context-size-aware) model-name refers to
+    fn catalog_alias_of(&self, model: &str) -> Option<String>;
+
     async fn get_running_languagemodel_alias(&self) -> Option<String>;
 
     fn set_parallel_backend_requests(&self, parallel_backend_requests: u8);
+
+    /// starts the model on the backend serving its kind (language- or embedding-model) ; with
+    /// 'wait' returns once the model is served instead of right after issuing the start
+    async fn load_model(
+        &self,
+        alias: &str,
+        overrides: RunOverrides,
+        wait: bool,
+    ) -> Result<LlamaCppProcessState, ModelLoadError>;
+
+    /// stops the backend serving the model
+    async fn unload_model(&self, alias: &str) -> Result<(), ModelLoadError>;
+}
+
+#[async_trait]
+pub trait HealthServiceInPort: Send + Sync + 'static {
+    /// reports the process-states of all managed backends without probing them
+    async fn check_liveness(&self) -> HealthReport;
+
+    /// reports the process-states of all managed backends combined with the result of probing them
+    async fn check_readiness(&self) -> HealthReport;
+}
+
+#[async_trait]
+pub trait MetricsServiceInPort: Send + Sync + 'static {
+    fn observe_request(&self, route: &str, model: Option<&str>, status: u16, duration: Duration);
+
+    /// renders all metrics in the prometheus text-format
+    async fn render_metrics(&self) -> String;
+
+    /// the latest requests to the inference-api (newest last)
+    fn recent_requests(&self) -> Vec<RecentRequest>;
+}
+
+pub trait AuditServiceInPort: Send + Sync + 'static {
+    fn captures_request_bodies(&self) -> bool;
+    fn captures_response_bodies(&self) -> bool;
+
+    /// redacts the captured bodies (if any) and appends the record to the audit-log
+    fn record(&self, record: AuditRecord);
+}
+
+pub trait IdleUnloadServiceInPort: Send + Sync + 'static {
+    /// marks the backend as busy until the returned guard is dropped; once it was idle for the
+    /// keep-alive of its model (or the one sent along with the latest request) it is stopped
+    fn track_activity(
+        &self,
+        backend: &str,
+        keep_alive_override: Option<KeepAlive>,
+    ) -> ActivityGuard;
+
+    /// the current activity of every backend
+    fn activity_report(&self) -> Vec<BackendActivityReport>;
+}
+
+#[async_trait]
+pub trait ModelCatalogServiceInPort: Send + Sync + 'static {
+    /// all configurations of the catalog ordered by alias
+    fn list_model_configurations(&self) -> Vec<ModelConfiguration>;
+    fn get_model_configuration(&self, alias: &str) -> Result<ModelConfiguration, CatalogError>;
+    fn create_model_configuration(
+        &self,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError>;
+    /// the configuration may come with another alias (it is renamed then)
+    fn update_model_configuration(
+        &self,
+        alias: &str,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError>;
+    fn clone_model_configuration(
+        &self,
+        alias: &str,
+        new_alias: &str,
+    ) -> Result<ModelConfiguration, CatalogError>;
+    fn delete_model_configuration(&self, alias: &str) -> Result<ModelConfiguration, CatalogError>;
+
+    fn get_model_routes(&self) -> ModelRoutes;
+    fn replace_model_routes(&self, model_routes: ModelRoutes) -> Result<(), CatalogError>;
+    /// sets the route of the api-key with the key-name (the general one if none is given)
+    fn set_model_route(
+        &self,
+        name: &str,
+        key_name: Option<&str>,
+        model_route: ModelRoute,
+    ) -> Result<(), CatalogError>;
+    fn delete_model_route(
+        &self,
+        name: &str,
+        key_name: Option<&str>,
+    ) -> Result<ModelRoute, CatalogError>;
+
+    /// adds the configuration the backend currently runs with to the catalog
+    async fn save_running_configuration(
+        &self,
+        backend: &str,
+        new_alias: &str,
+    ) -> Result<ModelConfiguration, CatalogError>;
 }
 
 /// OUT-PORTS
@@ -83,6 +215,10 @@ pub trait OpenAiClientOutPort: Send + Sync + 'static {
     async fn forward_api_request(&self, request: Request) -> Result<Response, StatusCode>;
     async fn forward_ui_request(&self, request: Request) -> Result<Response, StatusCode>;
     async fn request_chat(&self) -> Result<Response, StatusCode>;
+    async fn probe_health(&self) -> BackendProbe;
+
+    /// returns the prometheus-metrics of the backend itself (if the backend exposes any)
+    async fn fetch_backend_metrics(&self) -> Option<String>;
 }
 
 #[async_trait]
@@ -91,12 +227,59 @@ pub trait LlamaCppControllerOutPort: Send + Sync + 'static {
     async fn start_llamacpp_process(
         &self,
         llamacpp_config: LlamaCppRunConfig,
+        requested_by: &str,
     ) -> LlamaCppProcessState;
-    async fn stop_llamacpp_process(&self);
+    async fn stop_llamacpp_process(&self, requested_by: &str);
+    async fn reset_failures(&self, requested_by: &str);
+    fn process_log(&self) -> ProcessLog;
+    fn process_history(&self) -> LlamaCppProcessHistory;
 }
 
 #[async_trait]
 pub trait ModelLoaderOutPort: Send + Sync + 'static {
     fn get_static_model_configurations(&self) -> Vec<ModelConfiguration>;
+    fn get_static_model_configuration(&self, alias: &str) -> Option<ModelConfiguration>;
+
+    /// changes with every change of the catalog (allows to cache derived data)
+    fn catalog_revision(&self) -> u64;
+    fn create_model_configuration(
+        &self,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError>;
+    fn update_model_configuration(
+        &self,
+        alias: &str,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError>;
+    fn delete_model_configuration(&self, alias: &str) -> Result<ModelConfiguration, CatalogError>;
+
+    /// the virtual model-names ; changing them changes the catalog-revision as well
+    fn get_model_routes(&self) -> ModelRoutes;
+    fn set_model_routes(&self, model_routes: ModelRoutes) -> Result<(), CatalogError>;
+
     async fn get_model_configuration(&self, alias: &str) -> Result<Arc<LlamaCppConfigArgs>, ()>;
 }
+
+pub trait MetricsOutPort: Send + Sync + 'static {
+    fn observe_request(&self, route: &str, model: &str, status: u16, duration: Duration);
+    fn observe_time_to_first_token(&self, backend: &str, model: &str, duration: Duration);
+    /// the speed is none if it could not be measured
+    fn observe_generation(
+        &self,
+        backend: &str,
+        model: &str,
+        completion_tokens: u64,
+        tokens_per_second: Option<f64>,
+    );
+    fn observe_model_load(&self, backend: &str, model: &str, duration: Duration);
+    fn count_model_swap(&self, backend: &str, model: &str);
+    fn count_heartbeat(&self, backend: &str);
+    fn set_process_state(&self, backend: &str, state: &str);
+
+    /// encodes the gathered metrics merged with the metrics provided by the backends themselves
+    fn encode(&self, backend_metrics: Vec<(String, String)>) -> String;
+}
+
+pub trait AuditLogOutPort: Send + Sync + 'static {
+    fn append(&self, record: AuditRecord);
+}
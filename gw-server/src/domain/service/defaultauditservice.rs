@@ -0,0 +1,179 @@
+use crate::domain::{
+    model::{AuditConfig, AuditRecord},
+    ports::{AuditLogOutPort, AuditServiceInPort},
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+const REDACTED: &str = "[redacted]";
+const DATA_URL_PREFIX: &str = "data:";
+const BASE64_MARKER: &str = ";base64,";
+const B64_JSON_KEY: &str = "b64_json";
+
+pub struct DefaultAuditService {
+    config: AuditConfig,
+    audit_log: Arc<dyn AuditLogOutPort>,
+}
+
+impl DefaultAuditService {
+    pub fn create_service(
+        mut config: AuditConfig,
+        audit_log: Arc<dyn AuditLogOutPort>,
+    ) -> Arc<dyn AuditServiceInPort> {
+        config
+            .redacted_keys
+            .iter_mut()
+            .for_each(|key| *key = key.to_lowercase());
+        Arc::new(Self { config, audit_log })
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    let key = key.to_lowercase();
+                    if self.config.redacted_keys.contains(&key) {
+                        *value = Value::String(REDACTED.into());
+                    } else if self.config.redact_images
+                        && key == B64_JSON_KEY
+                        && let Value::String(data) = value
+                    {
+                        *value = Value::String(format!("[redacted base64: {} bytes]", data.len()));
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.redact(value)),
+            Value::String(text) if self.config.redact_images => {
+                if let Some(data_url) = text.strip_prefix(DATA_URL_PREFIX)
+                    && let Some((media_type, data)) = data_url.split_once(BASE64_MARKER)
+                {
+                    *text = format!("[redacted {media_type}: {} bytes]", data.len());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn prepare_body(&self, mut body: Value) -> Value {
+        self.redact(&mut body);
+        let serialized = body.to_string();
+        if serialized.len() > self.config.max_captured_body_bytes {
+            let mut cut = self.config.max_captured_body_bytes;
+            while !serialized.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            Value::String(format!(
+                "{}...[truncated {} bytes]",
+                &serialized[..cut],
+                serialized.len() - cut
+            ))
+        } else {
+            body
+        }
+    }
+}
+
+impl AuditServiceInPort for DefaultAuditService {
+    fn captures_request_bodies(&self) -> bool {
+        self.config.capture_request_bodies
+    }
+
+    fn captures_response_bodies(&self) -> bool {
+        self.config.capture_response_bodies
+    }
+
+    fn record(&self, mut record: AuditRecord) {
+        record.request_body = record.request_body.map(|body| self.prepare_body(body));
+        record.response_body = record.response_body.map(|body| self.prepare_body(body));
+        self.audit_log.append(record);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    struct RecordingAuditLog(Arc<Mutex<Vec<AuditRecord>>>);
+
+    impl AuditLogOutPort for RecordingAuditLog {
+        fn append(&self, record: AuditRecord) {
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    fn record_with_request_body(config: AuditConfig, body: Value) -> Value {
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let service = DefaultAuditService::create_service(
+            config,
+            Arc::new(RecordingAuditLog(recorded.clone())),
+        );
+        service.record(AuditRecord {
+            timestamp: std::time::SystemTime::now(),
+            key_name: None,
+            method: "POST".into(),
+            route: "/api/v1/chat/completions".into(),
+            model: None,
+            status: 200,
+            duration: std::time::Duration::ZERO,
+            queue_time: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            trace_id: None,
+            request_body: Some(body),
+            response_body: None,
+        });
+        let record = recorded.lock().unwrap().pop().unwrap();
+        record.request_body.unwrap()
+    }
+
+    #[test]
+    fn secrets_and_images_are_redacted() {
+        let body = json!({
+            "model": "gpt-oss-120b",
+            "max_tokens": 100,
+            "api_key": "sk-secret",
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "what is on data:image/png;base64,this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+                ]
+            }],
+            "extra": {"Authorization": "Bearer abc"}
+        });
+
+        assert_eq!(
+            record_with_request_body(AuditConfig::default(), body),
+            json!({
+                "model": "gpt-oss-120b",
+                "max_tokens": 100,
+                "api_key": "[redacted]",
+                "messages": [{
+                    "role": "user",
+                    "content": [
+                        {"type": "text", "text": "what is on data:image/png;base64,this?"},
+                        {"type": "image_url", "image_url": {"url": "[redacted image/png: 12 bytes]"}}
+                    ]
+                }],
+                "extra": {"Authorization": "[redacted]"}
+            })
+        );
+    }
+
+    // the cut must not split the (two-byte) 'ä'
+    #[test]
+    fn oversized_bodies_are_truncated() {
+        let config = AuditConfig {
+            max_captured_body_bytes: 11,
+            ..Default::default()
+        };
+        assert_eq!(
+            record_with_request_body(config, json!({"input": "äääää"})),
+            json!("{\"input\":\"...[truncated 12 bytes]")
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use crate::domain::{
+    model::{BackendHealth, BackendStatus, HealthReport, MonitoredBackend},
+    ports::HealthServiceInPort,
+};
+use async_trait::async_trait;
+use futures::future::join_all;
+use std::sync::Arc;
+use tracing::warn;
+
+pub struct DefaultHealthService {
+    backends: Vec<MonitoredBackend>,
+}
+
+impl DefaultHealthService {
+    pub fn create_service(backends: Vec<MonitoredBackend>) -> Arc<dyn HealthServiceInPort> {
+        Arc::new(Self { backends })
+    }
+
+    async fn check(backend: &MonitoredBackend, probe: bool) -> BackendHealth {
+        let process_state = backend.controller.get_llamacpp_state().await;
+        let health = BackendHealth::from_process_state(&backend.name, process_state);
+
+        // probing only makes sense while a process is starting or running
+        if !probe
+            || !matches!(
+                health.status,
+                BackendStatus::Starting | BackendStatus::Ready
+            )
+        {
+            return health;
+        }
+
+        let health = health.with_probe(backend.client.probe_health().await);
+        if health.status == BackendStatus::Degraded {
+            warn!(
+                "backend '{}' is running but its health-probe failed: {:?}",
+                backend.name, health.probe
+            );
+        }
+        health
+    }
+}
+
+#[async_trait]
+impl HealthServiceInPort for DefaultHealthService {
+    async fn check_liveness(&self) -> HealthReport {
+        HealthReport {
+            backends: join_all(self.backends.iter().map(|b| Self::check(b, false))).await,
+        }
+    }
+
+    async fn check_readiness(&self) -> HealthReport {
+        HealthReport {
+            backends: join_all(self.backends.iter().map(|b| Self::check(b, true))).await,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::domain::{
+    model::{
+        ActivityGuard, BackendActivity, BackendActivityReport, KeepAlive, MonitoredBackend,
+        REQUESTED_BY_IDLE_UNLOAD,
+    },
+    ports::{IdleUnloadServiceInPort, ModelLoaderOutPort},
+};
+use inference_backends::LlamaCppProcessState;
+use staticmodelconfig::ContextSizeAwareAlias;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{info, trace};
+
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct DefaultIdleUnloadService {
+    backends: Vec<(MonitoredBackend, Arc<Mutex<BackendActivity>>)>,
+    // provides the keep-alive per model (by alias without context-size) as configured in the
+    // catalog
+    model_loader: Arc<dyn ModelLoaderOutPort>,
+    default_keep_alive: KeepAlive,
+}
+
+impl DefaultIdleUnloadService {
+    /// spawns the background-task which stops idle backends
+    pub fn create_service(
+        backends: Vec<MonitoredBackend>,
+        model_loader: Arc<dyn ModelLoaderOutPort>,
+        default_keep_alive: KeepAlive,
+    ) -> Arc<dyn IdleUnloadServiceInPort> {
+        let service = Arc::new(Self::new(backends, model_loader, default_keep_alive));
+
+        let unloader = service.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                unloader.unload_idle_backends().await;
+            }
+        });
+
+        service
+    }
+
+    fn new(
+        backends: Vec<MonitoredBackend>,
+        model_loader: Arc<dyn ModelLoaderOutPort>,
+        default_keep_alive: KeepAlive,
+    ) -> Self {
+        Self {
+            backends: backends
+                .into_iter()
+                .map(|backend| (backend, Arc::default()))
+                .collect(),
+            model_loader,
+            default_keep_alive,
+        }
+    }
+
+    fn keep_alive_of(&self, alias: &str) -> KeepAlive {
+        let model = ContextSizeAwareAlias::resolve(alias, |model| {
+            self.model_loader
+                .get_static_model_configuration(model)
+                .is_some()
+        })
+        .map(|(model, _)| model)
+        .unwrap_or_else(|| alias.to_owned());
+        self.model_loader
+            .get_static_model_configuration(&model)
+            .and_then(|config| config.keep_alive)
+            .map(KeepAlive::from_secs)
+            .unwrap_or(self.default_keep_alive)
+    }
+
+    async fn unload_idle_backends(&self) {
+        for (backend, activity) in &self.backends {
+            let LlamaCppProcessState::Running(running_config, _) =
+                backend.controller.get_llamacpp_state().await
+            else {
+                continue;
+            };
+            let alias = &running_config.args_handle.alias;
+
+            let idle_for = {
+                let mut activity = activity
+                    .lock()
+                    .expect("locking backend-activity must not fail");
+                // the idle-period of a model starts once it is loaded ; the keep-alive of the
+                // model loaded before does not apply to it
+                if activity.served_alias.as_ref() != Some(alias) {
+                    activity.served_alias = Some(alias.clone());
+                    activity.last_activity = Instant::now();
+                    activity.keep_alive_override = None;
+                    continue;
+                }
+                if activity.in_flight > 0 {
+                    continue;
+                }
+                let keep_alive = activity
+                    .keep_alive_override
+                    .unwrap_or_else(|| self.keep_alive_of(alias));
+                match keep_alive {
+                    KeepAlive::For(keep_alive)
+                        if activity.last_activity.elapsed() >= keep_alive =>
+                    {
+                        activity.served_alias = None;
+                        activity.keep_alive_override = None;
+                        activity.last_activity.elapsed()
+                    }
+                    _ => continue,
+                }
+            };
+
+            info!(
+                "stopping backend '{}' serving '{alias}' after being idle for {}s",
+                backend.name,
+                idle_for.as_secs()
+            );
+            backend
+                .controller
+                .stop_llamacpp_process(REQUESTED_BY_IDLE_UNLOAD)
+                .await;
+        }
+    }
+}
+
+impl IdleUnloadServiceInPort for DefaultIdleUnloadService {
+    fn track_activity(
+        &self,
+        backend: &str,
+        keep_alive_override: Option<KeepAlive>,
+    ) -> ActivityGuard {
+        let activity = self
+            .backends
+            .iter()
+            .find(|(monitored_backend, _)| monitored_backend.name == backend)
+            .map(|(_, activity)| activity.clone())
+            .unwrap_or_else(|| {
+                trace!("activity of unknown backend '{backend}' is not tracked");
+                Arc::default()
+            });
+        ActivityGuard::new(activity, keep_alive_override)
+    }
+
+    fn activity_report(&self) -> Vec<BackendActivityReport> {
+        self.backends
+            .iter()
+            .map(|(monitored_backend, activity)| {
+                let activity = activity
+                    .lock()
+                    .expect("locking backend-activity must not fail");
+                BackendActivityReport {
+                    backend: monitored_backend.name.clone(),
+                    in_flight: activity.in_flight,
+                    idle_for: activity.last_activity.elapsed(),
+                    served_alias: activity.served_alias.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::ports::LlamaCppControllerOutPort;
+    use crate::domain::service::fakes::{FakeController, FakeModelLoader, NoClient};
+    use inference_backends::{LlamaCppConfigArgs, LlamaCppRunConfig};
+
+    async fn start(controller: &FakeController, alias: &str) {
+        controller
+            .start_llamacpp_process(
+                LlamaCppRunConfig {
+                    args_handle: Arc::new(LlamaCppConfigArgs {
+                        alias: alias.into(),
+                        ..Default::default()
+                    }),
+                    env_handle: Default::default(),
+                    parallel: 1,
+                    threads: 1,
+                    threads_batch: 1,
+                },
+                "test",
+            )
+            .await;
+    }
+
+    fn service_with(
+        controller: Arc<FakeController>,
+        model_loader: FakeModelLoader,
+    ) -> DefaultIdleUnloadService {
+        DefaultIdleUnloadService::new(
+            vec![MonitoredBackend {
+                name: "llm".into(),
+                controller,
+                client: Arc::new(NoClient),
+            }],
+            Arc::new(model_loader),
+            KeepAlive::Forever,
+        )
+    }
+
+    async fn is_running(controller: &FakeController) -> bool {
+        matches!(
+            controller.get_llamacpp_state().await,
+            LlamaCppProcessState::Running(..)
+        )
+    }
+
+    #[tokio::test]
+    async fn idle_backends_are_stopped_after_their_keep_alive() {
+        let controller = Arc::new(FakeController::stopped());
+        start(&controller, "some-model").await;
+        let service = service_with(controller.clone(), FakeModelLoader::with_models(&[]));
+        let is_running = async || is_running(&controller).await;
+
+        // the first check only notices the loaded model
+        service.unload_idle_backends().await;
+        service.unload_idle_backends().await;
+        assert!(is_running().await);
+
+        // busy backends are not stopped even if their keep-alive is over
+        let guard = service.track_activity("llm", Some(KeepAlive::For(Duration::ZERO)));
+        service.unload_idle_backends().await;
+        assert!(is_running().await);
+
+        drop(guard);
+        service.unload_idle_backends().await;
+        assert!(!is_running().await);
+    }
+
+    #[tokio::test]
+    async fn the_keep_alive_of_a_request_does_not_apply_to_the_next_model() {
+        let mut model_loader = FakeModelLoader::with_models(&["model-a", "model-b"]);
+        model_loader.model_configurations[1].keep_alive = Some(0);
+        let controller = Arc::new(FakeController::stopped());
+        start(&controller, "model-a").await;
+        let service = service_with(controller.clone(), model_loader);
+
+        service.unload_idle_backends().await;
+        drop(service.track_activity("llm", Some(KeepAlive::Forever)));
+        service.unload_idle_backends().await;
+        assert!(is_running(&controller).await);
+
+        // model-b is stopped after its keep-alive of the catalog
+        start(&controller, "model-b").await;
+        service.unload_idle_backends().await;
+        assert!(is_running(&controller).await);
+        service.unload_idle_backends().await;
+        assert!(!is_running(&controller).await);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::domain::{
+    model::{MonitoredBackend, RecentRequest, process_state_name},
+    ports::{MetricsOutPort, MetricsServiceInPort},
+};
+use async_trait::async_trait;
+use futures::future::join_all;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+const RECENT_REQUESTS_CAPACITY: usize = 100;
+// only requests to the inference-api are kept as recent requests (not the admin-requests of the
+// dashboard itself)
+const RECENT_REQUESTS_ROUTE_PREFIX: &str = "/api/";
+
+pub struct DefaultMetricsService {
+    metrics: Arc<dyn MetricsOutPort>,
+    backends: Vec<MonitoredBackend>,
+    aggregate_backend_metrics: bool,
+    recent_requests: Mutex<VecDeque<RecentRequest>>,
+}
+
+impl DefaultMetricsService {
+    pub fn create_service(
+        metrics: Arc<dyn MetricsOutPort>,
+        backends: Vec<MonitoredBackend>,
+        aggregate_backend_metrics: bool,
+    ) -> Arc<dyn MetricsServiceInPort> {
+        Arc::new(Self {
+            metrics,
+            backends,
+            aggregate_backend_metrics,
+            recent_requests: Mutex::new(VecDeque::with_capacity(RECENT_REQUESTS_CAPACITY)),
+        })
+    }
+}
+
+#[async_trait]
+impl MetricsServiceInPort for DefaultMetricsService {
+    fn observe_request(&self, route: &str, model: Option<&str>, status: u16, duration: Duration) {
+        self.metrics
+            .observe_request(route, model.unwrap_or_default(), status, duration);
+
+        if route.starts_with(RECENT_REQUESTS_ROUTE_PREFIX) {
+            let mut recent_requests = self
+                .recent_requests
+                .lock()
+                .expect("locking recent requests must not fail");
+            if recent_requests.len() == RECENT_REQUESTS_CAPACITY {
+                recent_requests.pop_front();
+            }
+            recent_requests.push_back(RecentRequest {
+                timestamp: SystemTime::now(),
+                route: route.to_owned(),
+                model: model.map(ToOwned::to_owned),
+                status,
+                duration,
+            });
+        }
+    }
+
+    fn recent_requests(&self) -> Vec<RecentRequest> {
+        self.recent_requests
+            .lock()
+            .expect("locking recent requests must not fail")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    async fn render_metrics(&self) -> String {
+        // process-states are gauges which are refreshed on every scrape
+        for backend in self.backends.iter() {
+            let process_state = backend.controller.get_llamacpp_state().await;
+            self.metrics
+                .set_process_state(&backend.name, process_state_name(&process_state));
+        }
+
+        let backend_metrics = if self.aggregate_backend_metrics {
+            join_all(self.backends.iter().map(|backend| async {
+                backend
+                    .client
+                    .fetch_backend_metrics()
+                    .await
+                    .map(|metrics| (backend.name.clone(), metrics))
+            }))
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+        } else {
+            Vec::new()
+        };
+
+        self.metrics.encode(backend_metrics)
+    }
+}
@@ -0,0 +1,200 @@
+use crate::domain::{
+    model::{CatalogError, MonitoredBackend},
+    ports::{ModelCatalogServiceInPort, ModelLoaderOutPort},
+};
+use async_trait::async_trait;
+use inference_backends::{LlamaCppProcessState, ResourceLimits};
+use staticmodelconfig::{ContextSizeAwareAlias, ModelConfiguration, ModelRoute, ModelRoutes};
+use std::sync::Arc;
+
+pub struct DefaultModelCatalogService {
+    model_loader: Arc<dyn ModelLoaderOutPort>,
+    backends: Vec<MonitoredBackend>,
+}
+
+impl DefaultModelCatalogService {
+    pub fn create_service(
+        model_loader: Arc<dyn ModelLoaderOutPort>,
+        backends: Vec<MonitoredBackend>,
+    ) -> Arc<dyn ModelCatalogServiceInPort> {
+        Arc::new(Self {
+            model_loader,
+            backends,
+        })
+    }
+}
+
+#[async_trait]
+impl ModelCatalogServiceInPort for DefaultModelCatalogService {
+    fn list_model_configurations(&self) -> Vec<ModelConfiguration> {
+        self.model_loader.get_static_model_configurations()
+    }
+
+    fn get_model_configuration(&self, alias: &str) -> Result<ModelConfiguration, CatalogError> {
+        self.model_loader
+            .get_static_model_configuration(alias)
+            .ok_or_else(|| CatalogError::UnknownAlias(alias.to_owned()))
+    }
+
+    fn create_model_configuration(
+        &self,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError> {
+        self.model_loader
+            .create_model_configuration(model_configuration)
+    }
+
+    fn update_model_configuration(
+        &self,
+        alias: &str,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError> {
+        self.model_loader
+            .update_model_configuration(alias, model_configuration)
+    }
+
+    fn clone_model_configuration(
+        &self,
+        alias: &str,
+        new_alias: &str,
+    ) -> Result<ModelConfiguration, CatalogError> {
+        let mut model_configuration = self.get_model_configuration(alias)?;
+        model_configuration.alias = new_alias.to_owned();
+        self.model_loader
+            .create_model_configuration(model_configuration.clone())?;
+        Ok(model_configuration)
+    }
+
+    fn delete_model_configuration(&self, alias: &str) -> Result<ModelConfiguration, CatalogError> {
+        self.model_loader.delete_model_configuration(alias)
+    }
+
+    fn get_model_routes(&self) -> ModelRoutes {
+        self.model_loader.get_model_routes()
+    }
+
+    fn replace_model_routes(&self, model_routes: ModelRoutes) -> Result<(), CatalogError> {
+        self.model_loader.set_model_routes(model_routes)
+    }
+
+    fn set_model_route(
+        &self,
+        name: &str,
+        key_name: Option<&str>,
+        model_route: ModelRoute,
+    ) -> Result<(), CatalogError> {
+        let mut model_routes = self.model_loader.get_model_routes();
+        model_routes
+            .routes_mut(key_name)
+            .insert(name.to_owned(), model_route);
+        self.model_loader.set_model_routes(model_routes)
+    }
+
+    fn delete_model_route(
+        &self,
+        name: &str,
+        key_name: Option<&str>,
+    ) -> Result<ModelRoute, CatalogError> {
+        let mut model_routes = self.model_loader.get_model_routes();
+        let model_route = model_routes
+            .routes_mut(key_name)
+            .remove(name)
+            .ok_or_else(|| CatalogError::UnknownAlias(name.to_owned()))?;
+        model_routes.keys.retain(|_, routes| !routes.is_empty());
+        self.model_loader.set_model_routes(model_routes)?;
+        Ok(model_route)
+    }
+
+    // the metadata of the model (vocab, size, capabilities, ...) is not known to the running
+    // process ; it is taken from the catalog-entry the running config was derived of (or any
+    // entry of the same model-file)
+    async fn save_running_configuration(
+        &self,
+        backend: &str,
+        new_alias: &str,
+    ) -> Result<ModelConfiguration, CatalogError> {
+        let monitored_backend = self
+            .backends
+            .iter()
+            .find(|monitored_backend| monitored_backend.name == backend)
+            .ok_or_else(|| CatalogError::UnknownBackend(backend.to_owned()))?;
+        let LlamaCppProcessState::Running(running_config, _) =
+            monitored_backend.controller.get_llamacpp_state().await
+        else {
+            return Err(CatalogError::NotRunning(backend.to_owned()));
+        };
+        let args = running_config.args_handle;
+
+        let model_configurations = self.model_loader.get_static_model_configurations();
+        let base_alias = ContextSizeAwareAlias::resolve(&args.alias, |model| {
+            model_configurations
+                .iter()
+                .any(|config| config.alias == model)
+        })
+        .map(|(model, _)| model)
+        .unwrap_or_else(|| args.alias.clone());
+        let base = model_configurations
+            .iter()
+            .find(|config| config.alias == base_alias)
+            .or_else(|| {
+                model_configurations
+                    .iter()
+                    .find(|config| config.model_path == args.model_path)
+            })
+            .ok_or_else(|| {
+                CatalogError::Invalid(format!(
+                    "no model in the catalog to take the metadata of '{}' from",
+                    args.model_path
+                ))
+            })?;
+
+        let model_configuration = ModelConfiguration {
+            alias: new_alias.to_owned(),
+            extends: None,
+            model_path: args.model_path.clone(),
+            max_ctx_size: args.ctx_size.unwrap_or(base.max_ctx_size),
+            mmproj_path: args.mmproj_path.clone(),
+            model_draft: args.model_draft.clone(),
+            prio: args.prio,
+            n_gpu_layers: args.n_gpu_layers,
+            flash_attn: args.flash_attn.clone(),
+            fit: args.fit.clone(),
+            batch_size: args.batch_size,
+            ubatch_size: args.ubatch_size,
+            cache_type_v: args.cache_type_v.clone(),
+            cache_ram: args.cache_ram,
+            cache_reuse: args.cache_reuse,
+            spec_type: args.spec_type.clone(),
+            spec_draft_n_max: args.spec_draft_n_max,
+            cache_type_k: args.cache_type_k.clone(),
+            temp: args.temp,
+            repeat_penalty: args.repeat_penalty,
+            presence_penalty: args.presence_penalty,
+            seed: args.seed,
+            min_p: args.min_p,
+            top_k: args.top_k,
+            top_p: args.top_p,
+            jinja: args.jinja,
+            no_mmap: args.no_mmap,
+            mlock: args.mlock,
+            no_warmup: args.no_warmup,
+            no_context_shift: args.no_context_shift,
+            no_cont_batching: args.no_cont_batching,
+            chat_template_kwargs: args.chat_template_kwargs.clone(),
+            reasoning: args.reasoning.clone(),
+            reasoning_budget: args.reasoning_budget,
+            embeddings: args.embeddings,
+            no_cache_prompt: args.no_cache_prompt,
+            resource_limits: if args.resource_limits == ResourceLimits::default() {
+                base.resource_limits.clone()
+            } else {
+                Some((&args.resource_limits).into())
+            },
+            backend_host: args.backend_host.clone(),
+            ..base.clone()
+        };
+        self.model_loader
+            .create_model_configuration(model_configuration.clone())?;
+        Ok(model_configuration)
+    }
+}
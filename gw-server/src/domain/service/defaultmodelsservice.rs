@@ -1,4 +1,10 @@
-use crate::domain::ports::{LlamaCppControllerOutPort, ModelLoaderOutPort, ModelsServiceInPort};
+use crate::domain::{
+    model::{
+        EMBEDDINGMODEL_BACKEND, LANGUAGEMODEL_BACKEND, ModelLoadError, REQUESTED_BY_ADMIN_API,
+        REQUESTED_BY_MODEL_REQUEST, RunOverrides,
+    },
+    ports::{LlamaCppControllerOutPort, MetricsOutPort, ModelLoaderOutPort, ModelsServiceInPort},
+};
 use async_trait::async_trait;
 use inference_backends::{
     ContextSize, LlamaCppConfigArgs, LlamaCppProcessState, LlamaCppRunConfig,
@@ -6,10 +12,16 @@ use inference_backends::{
 use staticmodelconfig::{ContextSizeAwareAlias, ModelList};
 use std::{
     collections::HashMap,
-    sync::{Arc, OnceLock, RwLock},
+    sync::{Arc, RwLock},
     time::Duration,
 };
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, info_span, trace, warn};
+
+// time a model loaded via the admin-api (waiting for it) gets to be served
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// the overrides along with the alias of the model they were set for
+type ModelOverrides = RwLock<Option<(String, RunOverrides)>>;
 
 pub struct DefaultModelsService {
     llamacpp_languagemodel_controller: Arc<dyn LlamaCppControllerOutPort>,
@@ -19,10 +31,17 @@ pub struct DefaultModelsService {
     threads: i8,
     threads_batch: i8,
     environment_args: Arc<HashMap<String, String>>,
-    cached_model_list: OnceLock<Arc<ModelList>>,
+    // the model-list along with the catalog-revision it was built of
+    cached_model_list: RwLock<Option<(u64, Arc<ModelList>)>>,
+    metrics: Arc<dyn MetricsOutPort>,
+    // set when loading a model via the admin-api ; they apply to that model (by its alias) until
+    // another model is started on the backend
+    languagemodel_overrides: ModelOverrides,
+    embeddingmodel_overrides: ModelOverrides,
 }
 
 impl DefaultModelsService {
+    #[allow(clippy::too_many_arguments)]
     pub fn create_service(
         llamacpp_languagemodel_controller: Arc<dyn LlamaCppControllerOutPort>,
         llamacpp_embeddingmodel_controller: Arc<dyn LlamaCppControllerOutPort>,
@@ -31,6 +50,7 @@ impl DefaultModelsService {
         threads: i8,
         threads_batch: i8,
         environment_args: HashMap<String, String>,
+        metrics: Arc<dyn MetricsOutPort>,
     ) -> Arc<dyn ModelsServiceInPort> {
         Arc::new(Self {
             llamacpp_languagemodel_controller,
@@ -40,94 +60,172 @@ impl DefaultModelsService {
             threads,
             threads_batch,
             environment_args: Arc::new(environment_args),
-            cached_model_list: OnceLock::new(),
+            cached_model_list: RwLock::new(None),
+            metrics,
+            languagemodel_overrides: RwLock::default(),
+            embeddingmodel_overrides: RwLock::default(),
         })
     }
 
+    fn controller_and_overrides_of(
+        &self,
+        backend: &str,
+    ) -> (&Arc<dyn LlamaCppControllerOutPort>, &ModelOverrides) {
+        if backend == EMBEDDINGMODEL_BACKEND {
+            (
+                &self.llamacpp_embeddingmodel_controller,
+                &self.embeddingmodel_overrides,
+            )
+        } else {
+            (
+                &self.llamacpp_languagemodel_controller,
+                &self.languagemodel_overrides,
+            )
+        }
+    }
+
+    // the overrides of the model with the given alias (none if they were set for another one)
+    fn overrides_of(overrides: &ModelOverrides, alias: &str) -> RunOverrides {
+        match &*overrides.read().expect("reading overrides must not fail") {
+            Some((overridden_alias, overrides)) if overridden_alias == alias => overrides.clone(),
+            _ => RunOverrides::default(),
+        }
+    }
+
+    fn drop_overrides_of_others(overrides: &ModelOverrides, alias: &str) {
+        let mut overrides = overrides.write().expect("writing overrides must not fail");
+        if let Some((overridden_alias, _)) = overrides.as_ref()
+            && overridden_alias != alias
+        {
+            info!("dropping the overrides of '{overridden_alias}' as '{alias}' is started");
+            *overrides = None;
+        }
+    }
+
     fn create_run_config_from_args_and_current_state(
         &self,
         llamacpp_config_args: Arc<LlamaCppConfigArgs>,
+        overrides: &ModelOverrides,
     ) -> LlamaCppRunConfig {
         let llamacpp_parallel_processings = *self
             .llamacpp_parallel_processings
             .read()
             .expect("reading llamacpp_parallel_processings must not fail");
+        let overrides = Self::overrides_of(overrides, &llamacpp_config_args.alias);
+        let env_handle = if overrides.env.is_empty() {
+            self.environment_args.clone()
+        } else {
+            let mut environment_args = (*self.environment_args).clone();
+            environment_args.extend(overrides.env.clone());
+            Arc::new(environment_args)
+        };
         LlamaCppRunConfig {
             args_handle: llamacpp_config_args.clone(),
-            env_handle: self.environment_args.clone(),
-            parallel: llamacpp_parallel_processings,
-            threads: self.threads,
-            threads_batch: self.threads_batch,
+            env_handle,
+            parallel: overrides.parallel.unwrap_or(llamacpp_parallel_processings),
+            threads: overrides.threads.unwrap_or(self.threads),
+            threads_batch: overrides.threads_batch.unwrap_or(self.threads_batch),
         }
     }
-}
 
-#[async_trait]
-impl ModelsServiceInPort for DefaultModelsService {
-    fn set_parallel_backend_requests(&self, parallel_backend_requests: u8) {
-        let old = {
-            let _guard = self.llamacpp_parallel_processings.read().unwrap();
-            *_guard
-        };
-        if parallel_backend_requests != old {
-            info!("switching parallel_backend_requests to {parallel_backend_requests}");
-            let mut _guard = self.llamacpp_parallel_processings.write().unwrap();
-            *_guard = parallel_backend_requests;
+    // context-size-aware aliases in their canonical form ('alias@32k'), so the running model is
+    // recognized whichever form was requested
+    fn canonical_model_name(&self, name: &str) -> String {
+        match ContextSizeAwareAlias::resolve(name, |model| {
+            self.model_loader
+                .get_static_model_configuration(model)
+                .is_some()
+        }) {
+            Some((model, Some(ctx_size))) => ContextSizeAwareAlias::from((model, ctx_size)).alias(),
+            _ => name.to_owned(),
         }
     }
 
-    async fn ensure_any_languagemodel_is_served(
-        &self,
-        default_model_alias: &str,
-        timeout: Duration,
-    ) -> Result<(), ()> {
-        let current_state = self
-            .llamacpp_languagemodel_controller
-            .get_llamacpp_state()
-            .await;
-        if matches!(
-            current_state,
-            inference_backends::LlamaCppProcessState::Running(_)
-        ) {
-            Ok(())
-        } else {
-            self.ensure_requested_languagemodel_is_served(default_model_alias, timeout)
-                .await
+    // custom context-sizes ('alias@999999') are bounded by the max-ctx-size of the model
+    fn check_context_size(&self, model: &str) -> Result<(), ModelLoadError> {
+        let Some((model_key, Some(context_size))) =
+            ContextSizeAwareAlias::resolve(model, |model| {
+                self.model_loader
+                    .get_static_model_configuration(model)
+                    .is_some()
+            })
+        else {
+            return Ok(());
+        };
+        match self.model_loader.get_static_model_configuration(&model_key) {
+            Some(model_configuration) if context_size > model_configuration.max_ctx_size => {
+                Err(ModelLoadError::ContextSizeExceeded(
+                    model.to_owned(),
+                    u64::from(&model_configuration.max_ctx_size),
+                ))
+            }
+            _ => Ok(()),
         }
     }
 
-    async fn ensure_requested_languagemodel_is_served(
+    // (re)starts the backend with the requested model until it is served with the current
+    // run-params
+    async fn ensure_model_is_served(
         &self,
+        backend: &str,
         requested_model: &str,
         timeout: Duration,
-    ) -> Result<(), ()> {
+        requested_by: &str,
+    ) -> Result<(), ModelLoadError> {
+        let (controller, overrides) = self.controller_and_overrides_of(backend);
+        let kind = if backend == EMBEDDINGMODEL_BACKEND {
+            "embeddingmodel"
+        } else {
+            "languagemodel"
+        };
         let start_time = std::time::Instant::now();
+        let mut load_start_time: Option<std::time::Instant> = None;
+        // covers the time from issuing the start until the model is served
+        let mut _model_load_span = None;
 
         let mut waiting_notified = false;
         loop {
             if start_time.elapsed() >= timeout {
-                trace!("starting languagemodel variant '{requested_model}' ran into timeout");
-                return Err(());
+                trace!("starting {kind} variant '{requested_model}' ran into timeout");
+                return Err(ModelLoadError::NotServed(requested_model.to_owned()));
             }
-            if let inference_backends::LlamaCppProcessState::Running(running_config) = self
-                .llamacpp_languagemodel_controller
-                .get_llamacpp_state()
-                .await
+            let current_state = controller.get_llamacpp_state().await;
+            // a start was issued already but the config crashed (and is not restarted)
+            if let LlamaCppProcessState::Failed(failed_config, reason) = &current_state
+                && failed_config.args_handle.alias == requested_model
+                && load_start_time.is_some()
             {
+                error!("{kind} '{requested_model}' failed: {reason}");
+                return Err(ModelLoadError::Failed(
+                    requested_model.to_owned(),
+                    reason.clone(),
+                ));
+            }
+            if let LlamaCppProcessState::Running(running_config, _) = current_state {
                 let running_config_args_handle = running_config.args_handle.clone();
                 if requested_model == running_config_args_handle.alias {
                     let runconfig_as_requested = self
-                        .create_run_config_from_args_and_current_state(running_config_args_handle);
+                        .create_run_config_from_args_and_current_state(
+                            running_config_args_handle,
+                            overrides,
+                        );
                     if runconfig_as_requested == running_config {
+                        if let Some(load_start_time) = load_start_time {
+                            self.metrics.observe_model_load(
+                                backend,
+                                requested_model,
+                                load_start_time.elapsed(),
+                            );
+                        }
                         return Ok(());
                     } else {
                         debug!(
-                            "problem: requested languagemodel is '{requested_model}' is running but the process was started with different run-params"
+                            "problem: requested {kind} is '{requested_model}' is running but the process was started with different run-params"
                         );
                     }
                 } else {
                     trace!(
-                        "problem: requested languagemodel is '{requested_model}' but a model '{}' is still running",
+                        "problem: requested {kind} is '{requested_model}' but a model '{}' is still running",
                         running_config.args_handle.alias
                     )
                 }
@@ -139,12 +237,32 @@ impl ModelsServiceInPort for DefaultModelsService {
                 .await
             {
                 Ok(llamacpp_config_args) => {
-                    let llamacpp_run_config =
-                        self.create_run_config_from_args_and_current_state(llamacpp_config_args);
+                    let llamacpp_run_config = self.create_run_config_from_args_and_current_state(
+                        llamacpp_config_args,
+                        overrides,
+                    );
+
+                    // overrides of another model end with it
+                    Self::drop_overrides_of_others(overrides, requested_model);
 
-                    self.llamacpp_languagemodel_controller
-                        .start_llamacpp_process(llamacpp_run_config)
-                        .await;
+                    if load_start_time.is_none() {
+                        load_start_time = Some(std::time::Instant::now());
+                        self.metrics.count_model_swap(backend, requested_model);
+                        _model_load_span = Some(info_span!(
+                            "model_load",
+                            backend = backend,
+                            model = requested_model
+                        ));
+                    }
+                    // a refused start is reported right away instead of being waited for
+                    if let LlamaCppProcessState::Failed(failed_config, reason) = controller
+                        .start_llamacpp_process(llamacpp_run_config, requested_by)
+                        .await
+                        && failed_config.args_handle.alias == requested_model
+                    {
+                        error!("{kind} '{requested_model}' failed: {reason}");
+                        return Err(ModelLoadError::Failed(requested_model.to_owned(), reason));
+                    }
                     if !waiting_notified {
                         debug!("waiting for backend to serve '{requested_model}'...)");
                     }
@@ -153,14 +271,231 @@ impl ModelsServiceInPort for DefaultModelsService {
                 }
                 Err(()) => {
                     error!("could not retrieve a configuration for model '{requested_model}'");
-                    return Err(());
+                    return Err(ModelLoadError::UnknownModel(requested_model.to_owned()));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ModelsServiceInPort for DefaultModelsService {
+    async fn load_model(
+        &self,
+        alias: &str,
+        overrides: RunOverrides,
+        wait: bool,
+    ) -> Result<LlamaCppProcessState, ModelLoadError> {
+        let alias = &self.canonical_model_name(alias);
+        self.check_context_size(alias)?;
+        let llamacpp_config_args = self
+            .model_loader
+            .get_model_configuration(alias)
+            .await
+            .map_err(|_| ModelLoadError::UnknownModel(alias.to_owned()))?;
+        let backend = if llamacpp_config_args.embeddings {
+            EMBEDDINGMODEL_BACKEND
+        } else {
+            LANGUAGEMODEL_BACKEND
+        };
+        let (controller, backend_overrides) = self.controller_and_overrides_of(backend);
+        info!("loading '{alias}' on backend '{backend}' with {overrides:?}");
+        *backend_overrides
+            .write()
+            .expect("writing overrides must not fail") = Some((alias.to_owned(), overrides));
+
+        if wait {
+            self.ensure_model_is_served(backend, alias, LOAD_TIMEOUT, REQUESTED_BY_ADMIN_API)
+                .await?;
+        } else {
+            let llamacpp_run_config = self.create_run_config_from_args_and_current_state(
+                llamacpp_config_args,
+                backend_overrides,
+            );
+            if let LlamaCppProcessState::Failed(failed_config, reason) = controller
+                .start_llamacpp_process(llamacpp_run_config, REQUESTED_BY_ADMIN_API)
+                .await
+                && failed_config.args_handle.alias == *alias
+            {
+                return Err(ModelLoadError::Failed(alias.to_owned(), reason));
+            }
+        }
+        Ok(controller.get_llamacpp_state().await)
+    }
+
+    async fn unload_model(&self, alias: &str) -> Result<(), ModelLoadError> {
+        let alias = &self.canonical_model_name(alias);
+        for backend in [LANGUAGEMODEL_BACKEND, EMBEDDINGMODEL_BACKEND] {
+            let (controller, _) = self.controller_and_overrides_of(backend);
+            let loaded_alias = match controller.get_llamacpp_state().await {
+                // a model replacing the running one counts as loaded already
+                LlamaCppProcessState::Running(config, _)
+                | LlamaCppProcessState::Starting(config, _)
+                | LlamaCppProcessState::Stopping(_, Some(config), _) => {
+                    Some(config.args_handle.alias.clone())
                 }
+                _ => None,
+            };
+            if loaded_alias.as_deref() == Some(alias) {
+                info!("unloading '{alias}' from backend '{backend}'");
+                controller
+                    .stop_llamacpp_process(REQUESTED_BY_ADMIN_API)
+                    .await;
+                return Ok(());
             }
         }
+        Err(ModelLoadError::NotLoaded(alias.to_owned()))
+    }
+
+    fn set_parallel_backend_requests(&self, parallel_backend_requests: u8) {
+        let old = {
+            let _guard = self.llamacpp_parallel_processings.read().unwrap();
+            *_guard
+        };
+        if parallel_backend_requests != old {
+            info!("switching parallel_backend_requests to {parallel_backend_requests}");
+            let mut _guard = self.llamacpp_parallel_processings.write().unwrap();
+            *_guard = parallel_backend_requests;
+        }
+    }
+
+    async fn ensure_any_languagemodel_is_served(
+        &self,
+        default_model_alias: &str,
+        timeout: Duration,
+    ) -> Result<(), ModelLoadError> {
+        let current_state = self
+            .llamacpp_languagemodel_controller
+            .get_llamacpp_state()
+            .await;
+        if matches!(
+            current_state,
+            inference_backends::LlamaCppProcessState::Running(..)
+        ) {
+            Ok(())
+        } else {
+            self.ensure_requested_languagemodel_is_served(default_model_alias, timeout)
+                .await
+        }
+    }
+
+    async fn ensure_requested_languagemodel_is_served(
+        &self,
+        requested_model: &str,
+        timeout: Duration,
+    ) -> Result<(), ModelLoadError> {
+        self.ensure_model_is_served(
+            LANGUAGEMODEL_BACKEND,
+            requested_model,
+            timeout,
+            REQUESTED_BY_MODEL_REQUEST,
+        )
+        .await
+    }
+
+    fn resolve_model_name(
+        &self,
+        requested_model: &str,
+        key_name: Option<&str>,
+    ) -> Result<String, ModelLoadError> {
+        let model = match self
+            .model_loader
+            .get_model_routes()
+            .resolve(requested_model, key_name)
+        {
+            Some(model_route) => {
+                let target = model_route.target();
+                debug!("routing '{requested_model}' to '{target}'");
+                target
+            }
+            None => self.canonical_model_name(requested_model),
+        };
+        match self.catalog_alias_of(&model) {
+            Some(_) => {
+                self.check_context_size(&model)?;
+                Ok(model)
+            }
+            None => Err(ModelLoadError::UnknownModel(model)),
+        }
+    }
+
+    async fn select_context_size(&self, requested_model: &str, required_tokens: u64) -> String {
+        let Some(model) = ContextSizeAwareAlias::auto_sized_model(requested_model) else {
+            return requested_model.to_owned();
+        };
+        let Some(model_configuration) = self.model_loader.get_static_model_configuration(model)
+        else {
+            return requested_model.to_owned();
+        };
+        let backend = if model_configuration.embeddings {
+            EMBEDDINGMODEL_BACKEND
+        } else {
+            LANGUAGEMODEL_BACKEND
+        };
+        let (controller, overrides) = self.controller_and_overrides_of(backend);
+        // the overrides of a loaded context-size of the model apply to the other ones as well
+        let parallel = overrides
+            .read()
+            .expect("reading overrides must not fail")
+            .as_ref()
+            .filter(|(alias, _)| {
+                ContextSizeAwareAlias::resolve(alias, |alias| alias == model)
+                    .is_some_and(|(overridden_model, _)| overridden_model == model)
+            })
+            .and_then(|(_, overrides)| overrides.parallel)
+            .unwrap_or(
+                *self
+                    .llamacpp_parallel_processings
+                    .read()
+                    .expect("reading llamacpp_parallel_processings must not fail"),
+            );
+        // every parallel request gets its share of the context
+        let required_ctx_size = required_tokens * u64::from(parallel.max(1));
+
+        // restarting the model with a smaller context-size is not worth it
+        if let LlamaCppProcessState::Running(running_config, _) =
+            controller.get_llamacpp_state().await
+        {
+            let running_alias = &running_config.args_handle.alias;
+            let running_ctx_size = match running_config.args_handle.ctx_size {
+                Some(ctx_size)
+                    if *running_alias
+                        == ContextSizeAwareAlias::from((model.to_owned(), ctx_size)).alias() =>
+                {
+                    Some(ctx_size)
+                }
+                // without a context-size the model is served with the one it was trained with
+                None if running_alias == model => Some(model_configuration.max_ctx_size),
+                _ => None,
+            };
+            if let Some(running_ctx_size) = running_ctx_size
+                && u64::from(&running_ctx_size) >= required_ctx_size
+            {
+                debug!(
+                    "'{requested_model}' is served by running '{running_alias}' ({required_ctx_size} tokens required)"
+                );
+                return running_alias.clone();
+            }
+        }
+
+        let caa = ContextSizeAwareAlias::fitting(
+            model.to_owned(),
+            required_ctx_size,
+            model_configuration.max_ctx_size,
+        );
+        if u64::from(&caa.context_size()) < required_ctx_size {
+            warn!(
+                "'{requested_model}' requires {required_ctx_size} tokens exceeding the max-ctx-size {}",
+                u64::from(&caa.context_size())
+            );
+        }
+        let alias = caa.alias();
+        debug!("'{requested_model}' is sized as '{alias}' ({required_ctx_size} tokens required)");
+        alias
     }
 
     async fn get_running_languagemodel_alias(&self) -> Option<String> {
-        if let LlamaCppProcessState::Running(llamacpp_run_config) = self
+        if let LlamaCppProcessState::Running(llamacpp_run_config, _) = self
             .llamacpp_languagemodel_controller
             .get_llamacpp_state()
             .await
@@ -179,63 +514,29 @@ impl ModelsServiceInPort for DefaultModelsService {
         &self,
         requested_model: &str,
         timeout: Duration,
-    ) -> Result<(), ()> {
-        let start_time = std::time::Instant::now();
-
-        let mut waiting_notified = false;
-        loop {
-            if start_time.elapsed() >= timeout {
-                trace!("starting embeddingmodel variant '{requested_model}' ran into timeout");
-                return Err(());
-            }
-            if let inference_backends::LlamaCppProcessState::Running(running_config) = self
-                .llamacpp_embeddingmodel_controller
-                .get_llamacpp_state()
-                .await
-            {
-                let running_config_args_handle = running_config.args_handle.clone();
-                if requested_model == running_config_args_handle.alias {
-                    let runconfig_as_requested = self
-                        .create_run_config_from_args_and_current_state(running_config_args_handle);
-                    if runconfig_as_requested == running_config {
-                        return Ok(());
-                    } else {
-                        debug!(
-                            "problem: requested embeddingmodel is '{requested_model}' is running but the process was started with different run-params"
-                        );
-                    }
-                } else {
-                    trace!(
-                        "problem: requested embeddingmodel is '{requested_model}' but a model '{}' is still running",
-                        running_config.args_handle.alias
-                    )
-                }
-            }
-
-            match self
-                .model_loader
-                .get_model_configuration(requested_model)
-                .await
-            {
-                Ok(llamacpp_config_args) => {
-                    let llamacpp_run_config =
-                        self.create_run_config_from_args_and_current_state(llamacpp_config_args);
+    ) -> Result<(), ModelLoadError> {
+        self.ensure_model_is_served(
+            EMBEDDINGMODEL_BACKEND,
+            requested_model,
+            timeout,
+            REQUESTED_BY_MODEL_REQUEST,
+        )
+        .await
+    }
 
-                    self.llamacpp_embeddingmodel_controller
-                        .start_llamacpp_process(llamacpp_run_config)
-                        .await;
-                    if !waiting_notified {
-                        debug!("waiting for backend to serve '{requested_model}'...)");
-                    }
-                    waiting_notified = true;
-                    tokio::time::sleep(Duration::from_millis(500)).await
-                }
-                Err(()) => {
-                    error!("could not retrieve a configuration for model '{requested_model}'");
-                    return Err(());
-                }
-            }
-        }
+    fn catalog_alias_of(&self, model: &str) -> Option<String> {
+        let is_model = |model: &str| {
+            self.model_loader
+                .get_static_model_configuration(model)
+                .is_some()
+        };
+        ContextSizeAwareAlias::resolve(model, is_model)
+            .map(|(model, _)| model)
+            .or_else(|| {
+                ContextSizeAwareAlias::auto_sized_model(model)
+                    .filter(|model| is_model(model))
+                    .map(str::to_owned)
+            })
     }
 
     fn get_default_embeddingmodel_alias(&self) -> String {
@@ -243,9 +544,23 @@ impl ModelsServiceInPort for DefaultModelsService {
     }
 
     fn get_models(&self) -> Arc<ModelList> {
-        let handle = self.cached_model_list.get_or_init(|| {
+        let catalog_revision = self.model_loader.catalog_revision();
+        if let Some((revision, model_list)) = self
+            .cached_model_list
+            .read()
+            .expect("reading cached_model_list must not fail")
+            .as_ref()
+            && *revision == catalog_revision
+        {
+            return model_list.clone();
+        }
+
+        let model_list = {
             let static_model_configurations = self.model_loader.get_static_model_configurations();
             let mut model_list = ModelList::with_capacity(static_model_configurations.len());
+            // the names of the '-' form ('alias-small', 'alias-auto') are listed after the
+            // others until their removal is announced
+            let mut deprecated_names = Vec::new();
 
             for base_configuration in static_model_configurations.iter().cloned() {
                 'inner: for ctx_size in [
@@ -264,15 +579,273 @@ impl ModelsServiceInPort for DefaultModelsService {
                     base_configuration.alias = caa.alias();
                     trace!("adding model '{}' to list", base_configuration.alias);
                     model_list.add_model_configuration(&base_configuration);
+                    if let Some(legacy_alias) = caa.legacy_alias() {
+                        deprecated_names.push((legacy_alias, base_configuration));
+                    }
+                }
+                // language-models with the context-size fitting the request
+                if !base_configuration.embeddings {
+                    let mut base_configuration = base_configuration.clone();
+                    let legacy_alias =
+                        ContextSizeAwareAlias::legacy_auto_sized_alias(&base_configuration.alias);
+                    base_configuration.alias =
+                        ContextSizeAwareAlias::auto_sized_alias(&base_configuration.alias);
+                    model_list.add_model_configuration(&base_configuration);
+                    deprecated_names.push((legacy_alias, base_configuration));
                 }
             }
 
+            // virtual model-names are listed with the metadata of their targets (routes of
+            // api-keys are not listed as this endpoint is not secured)
+            for (name, model_route) in self.model_loader.get_model_routes().routes {
+                let Some(mut model_configuration) = static_model_configurations
+                    .iter()
+                    .find(|model_configuration| model_configuration.alias == model_route.alias)
+                    .cloned()
+                else {
+                    continue;
+                };
+                model_configuration.alias = name;
+                model_list.add_model_configuration(&model_configuration);
+            }
+
+            for (legacy_alias, mut model_configuration) in deprecated_names {
+                let replaced_by = std::mem::replace(&mut model_configuration.alias, legacy_alias);
+                model_list.add_deprecated_model_configuration(&model_configuration, &replaced_by);
+            }
+
             Arc::new(model_list)
-        });
-        handle.clone()
+        };
+        *self
+            .cached_model_list
+            .write()
+            .expect("writing cached_model_list must not fail") =
+            Some((catalog_revision, model_list.clone()));
+        model_list
     }
 
     fn get_model_names(&self) -> String {
         self.get_models().names()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::service::fakes::{FakeModelLoader, models_service_with as service_with};
+
+    fn running_config(state: LlamaCppProcessState) -> LlamaCppRunConfig {
+        let LlamaCppProcessState::Running(config, _) = state else {
+            panic!("no model running: {state:?}");
+        };
+        config
+    }
+
+    #[tokio::test]
+    async fn overrides_apply_to_the_loaded_model_only() {
+        let (controller, service) =
+            service_with(FakeModelLoader::with_models(&["model-a", "model-b"]), 2);
+
+        let overrides = RunOverrides {
+            parallel: Some(8),
+            threads: Some(16),
+            threads_batch: Some(16),
+            env: HashMap::from([("K".to_owned(), "V".to_owned())]),
+        };
+        let config = running_config(
+            service
+                .load_model("model-a", overrides, false)
+                .await
+                .unwrap(),
+        );
+        assert_eq!(
+            (config.parallel, config.threads, config.threads_batch),
+            (8, 16, 16)
+        );
+        assert_eq!(config.env_handle.get("K").map(String::as_str), Some("V"));
+
+        // another model on the same backend is served with the run-params of the gateway ...
+        service
+            .ensure_requested_languagemodel_is_served("model-b", Duration::from_secs(5))
+            .await
+            .unwrap();
+        let config = running_config(controller.get_llamacpp_state().await);
+        assert_eq!(config.args_handle.alias, "model-b");
+        assert_eq!(
+            (config.parallel, config.threads, config.threads_batch),
+            (2, 4, 4)
+        );
+        assert!(config.env_handle.is_empty());
+
+        // ... and so is the model loaded before once it is requested again
+        service
+            .ensure_requested_languagemodel_is_served("model-a", Duration::from_secs(5))
+            .await
+            .unwrap();
+        let config = running_config(controller.get_llamacpp_state().await);
+        assert_eq!(config.args_handle.alias, "model-a");
+        assert_eq!(
+            (config.parallel, config.threads, config.threads_batch),
+            (2, 4, 4)
+        );
+    }
+
+    #[tokio::test]
+    async fn virtual_names_are_routed_to_models_of_the_catalog() {
+        let mut model_loader = FakeModelLoader::with_models(&["model-a", "model-b"]);
+        model_loader.model_routes = serde_json::from_value(serde_json::json!({
+            "routes": {
+                "coder": {"alias": "model-a", "ctx-size": 32768},
+                "gone": {"alias": "deleted-model"}
+            },
+            "keys": {"ci": {"coder": {"alias": "model-b"}}}
+        }))
+        .unwrap();
+        let (controller, service) = service_with(model_loader, 1);
+
+        let model = service.resolve_model_name("coder", None).unwrap();
+        assert_eq!(model, "model-a@32k");
+        assert_eq!(
+            service.resolve_model_name("coder", Some("ci")),
+            Ok("model-b".to_owned())
+        );
+        assert_eq!(
+            service.resolve_model_name("model-b", None),
+            Ok("model-b".to_owned())
+        );
+        // the request reaches the backend with the catalog alias
+        service
+            .ensure_requested_languagemodel_is_served(&model, Duration::from_secs(5))
+            .await
+            .unwrap();
+        let config = running_config(controller.get_llamacpp_state().await);
+        assert_eq!(config.args_handle.alias, "model-a@32k");
+        assert_eq!(config.args_handle.ctx_size, Some(ContextSize::T32768));
+
+        // models which are not in the catalog are not found (answered with 404)
+        assert_eq!(
+            service.resolve_model_name("gone", None),
+            Err(ModelLoadError::UnknownModel("deleted-model".to_owned()))
+        );
+        assert_eq!(
+            service.resolve_model_name("unknown", None),
+            Err(ModelLoadError::UnknownModel("unknown".to_owned()))
+        );
+    }
+
+    #[tokio::test]
+    async fn auto_sized_models_are_routed_to_the_smallest_fitting_context_size() {
+        let (controller, service) = service_with(FakeModelLoader::with_models(&["model-a"]), 1);
+        // as estimated for a prompt with an image (and no limit of the completion)
+        let required_tokens = 5134;
+
+        let model = service.resolve_model_name("model-a@auto", None).unwrap();
+        assert_eq!(
+            service.select_context_size(&model, required_tokens).await,
+            "model-a@8k"
+        );
+        // every parallel request gets its share of the context
+        service.set_parallel_backend_requests(4);
+        let model = service
+            .select_context_size("model-a@auto", required_tokens)
+            .await;
+        assert_eq!(model, "model-a@32k");
+        service
+            .ensure_requested_languagemodel_is_served(&model, Duration::from_secs(5))
+            .await
+            .unwrap();
+        let config = running_config(controller.get_llamacpp_state().await);
+        assert_eq!(
+            (config.args_handle.ctx_size, config.parallel),
+            (Some(ContextSize::T32768), 4)
+        );
+
+        // the running context-size is kept if it suffices ...
+        assert_eq!(
+            service.select_context_size("model-a@auto", 1000).await,
+            "model-a@32k"
+        );
+        // ... while the max-ctx-size of the model is not exceeded
+        assert_eq!(
+            service.select_context_size("model-a@auto", 100_000).await,
+            "model-a@128k"
+        );
+        // other model-names are kept as they are
+        assert_eq!(
+            service.select_context_size("model-a", 100_000).await,
+            "model-a"
+        );
+    }
+
+    #[test]
+    fn deprecated_names_are_listed_after_the_others() {
+        let (_, service) = service_with(FakeModelLoader::with_models(&["model-a"]), 1);
+
+        assert_eq!(
+            service.get_model_names(),
+            [
+                "model-a@8k",
+                "model-a@16k",
+                "model-a@32k",
+                "model-a@64k",
+                "model-a@128k",
+                "model-a@auto",
+                "model-a-min",
+                "model-a-tiny",
+                "model-a-small",
+                "model-a-moderate",
+                "model-a-large",
+                "model-a-auto"
+            ]
+            .join(",")
+        );
+        let model_list = serde_json::to_value(service.get_models().as_ref()).unwrap();
+        assert_eq!(model_list["data"][2].get("replaced_by"), None);
+        assert_eq!(model_list["data"][8]["replaced_by"], "model-a@32k");
+        assert_eq!(
+            model_list["models"][8]["tags"],
+            serde_json::json!(["deprecated"])
+        );
+        // and still accepted
+        assert_eq!(
+            service.resolve_model_name("model-a-small", None),
+            Ok("model-a@32k".to_owned())
+        );
+        assert_eq!(
+            service.resolve_model_name("model-a-40000", None),
+            Ok("model-a@40000".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn models_refused_by_the_backend_fail_without_waiting() {
+        let (controller, service) =
+            service_with(FakeModelLoader::with_models(&["model-a", "model-b"]), 1);
+        service
+            .ensure_requested_languagemodel_is_served("model-b", Duration::from_secs(5))
+            .await
+            .unwrap();
+        controller.exclude("model-a");
+
+        let start = std::time::Instant::now();
+        assert_eq!(
+            service
+                .ensure_requested_languagemodel_is_served("model-a", Duration::from_secs(5))
+                .await,
+            Err(ModelLoadError::Failed(
+                "model-a".to_owned(),
+                "crashed repeatedly".to_owned()
+            ))
+        );
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(matches!(
+            service
+                .load_model("model-a", RunOverrides::default(), false)
+                .await,
+            Err(ModelLoadError::Failed(..))
+        ));
+        // the model served before is kept
+        let config = running_config(controller.get_llamacpp_state().await);
+        assert_eq!(config.args_handle.alias, "model-b");
+    }
+}
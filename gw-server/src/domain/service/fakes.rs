@@ -0,0 +1,220 @@
+//! the fakes of the out-ports the services are tested with
+
+use crate::domain::{
+    model::{BackendProbe, CatalogError},
+    ports::{
+        LlamaCppControllerOutPort, MetricsOutPort, ModelLoaderOutPort, ModelsServiceInPort,
+        OpenAiClientOutPort,
+    },
+    service::DefaultModelsService,
+};
+use async_openai::types::{chat::CreateChatCompletionRequest, embeddings::CreateEmbeddingRequest};
+use async_trait::async_trait;
+use axum::{extract::Request, http::StatusCode, response::Response};
+use inference_backends::{
+    LlamaCppConfigArgs, LlamaCppProcessHistory, LlamaCppProcessState, LlamaCppRunConfig, ProcessLog,
+};
+use staticmodelconfig::{ContextSizeAwareAlias, ModelConfiguration, ModelRoutes};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// a backend which is running the config it was last started with ; the excluded aliases are
+/// refused like configs which crashed repeatedly
+pub(crate) struct FakeController {
+    state: Mutex<LlamaCppProcessState>,
+    excluded: Mutex<Vec<String>>,
+}
+
+impl FakeController {
+    pub(crate) fn stopped() -> Self {
+        Self {
+            state: Mutex::new(LlamaCppProcessState::Stopped),
+            excluded: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn exclude(&self, alias: &str) {
+        self.excluded.lock().unwrap().push(alias.to_owned());
+    }
+}
+
+#[async_trait]
+impl LlamaCppControllerOutPort for FakeController {
+    async fn get_llamacpp_state(&self) -> LlamaCppProcessState {
+        self.state.lock().unwrap().clone()
+    }
+    async fn start_llamacpp_process(
+        &self,
+        config: LlamaCppRunConfig,
+        _: &str,
+    ) -> LlamaCppProcessState {
+        if self
+            .excluded
+            .lock()
+            .unwrap()
+            .contains(&config.args_handle.alias)
+        {
+            return LlamaCppProcessState::Failed(config, "crashed repeatedly".into());
+        }
+        *self.state.lock().unwrap() = LlamaCppProcessState::Running(config, None);
+        self.state.lock().unwrap().clone()
+    }
+    async fn stop_llamacpp_process(&self, _: &str) {
+        *self.state.lock().unwrap() = LlamaCppProcessState::Stopped;
+    }
+    async fn reset_failures(&self, _: &str) {}
+    fn process_log(&self) -> ProcessLog {
+        ProcessLog::new(0)
+    }
+    fn process_history(&self) -> LlamaCppProcessHistory {
+        LlamaCppProcessHistory::new(0)
+    }
+}
+
+/// a backend which cannot be reached
+pub(crate) struct NoClient;
+
+#[async_trait]
+impl OpenAiClientOutPort for NoClient {
+    async fn post_chat_completions(
+        &self,
+        _: CreateChatCompletionRequest,
+    ) -> Result<Response, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+    async fn post_embedding(&self, _: CreateEmbeddingRequest) -> Result<Response, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+    async fn forward_api_request(&self, _: Request) -> Result<Response, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+    async fn forward_ui_request(&self, _: Request) -> Result<Response, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+    async fn request_chat(&self) -> Result<Response, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+    async fn probe_health(&self) -> BackendProbe {
+        BackendProbe::Healthy
+    }
+    async fn fetch_backend_metrics(&self) -> Option<String> {
+        None
+    }
+}
+
+/// a read-only catalog
+pub(crate) struct FakeModelLoader {
+    pub(crate) model_configurations: Vec<ModelConfiguration>,
+    pub(crate) model_routes: ModelRoutes,
+}
+
+impl FakeModelLoader {
+    pub(crate) fn with_models(aliases: &[&str]) -> Self {
+        Self {
+            model_configurations: aliases
+                .iter()
+                .map(|alias| model_configuration(alias))
+                .collect(),
+            model_routes: ModelRoutes::default(),
+        }
+    }
+}
+
+pub(crate) fn model_configuration(alias: &str) -> ModelConfiguration {
+    serde_json::from_value(serde_json::json!({
+        "alias": alias,
+        "model-path": format!("/models/{alias}.gguf"),
+        "max-ctx-size": 131072,
+        "vocab-type": 2,
+        "n-vocab": 1000,
+        "n-ctx-train": 131072,
+        "n-embd": 64,
+        "n-params": 1000,
+        "size": 4,
+        "capabilities": ["completion"]
+    }))
+    .unwrap()
+}
+
+#[async_trait]
+impl ModelLoaderOutPort for FakeModelLoader {
+    fn get_static_model_configurations(&self) -> Vec<ModelConfiguration> {
+        self.model_configurations.clone()
+    }
+    fn get_static_model_configuration(&self, alias: &str) -> Option<ModelConfiguration> {
+        self.model_configurations
+            .iter()
+            .find(|model_configuration| model_configuration.alias == alias)
+            .cloned()
+    }
+    fn catalog_revision(&self) -> u64 {
+        0
+    }
+    fn create_model_configuration(&self, _: ModelConfiguration) -> Result<(), CatalogError> {
+        Err(CatalogError::Io("read-only".into()))
+    }
+    fn update_model_configuration(
+        &self,
+        alias: &str,
+        _: ModelConfiguration,
+    ) -> Result<(), CatalogError> {
+        Err(CatalogError::UnknownAlias(alias.into()))
+    }
+    fn delete_model_configuration(&self, alias: &str) -> Result<ModelConfiguration, CatalogError> {
+        Err(CatalogError::UnknownAlias(alias.into()))
+    }
+    fn get_model_routes(&self) -> ModelRoutes {
+        self.model_routes.clone()
+    }
+    fn set_model_routes(&self, _: ModelRoutes) -> Result<(), CatalogError> {
+        Ok(())
+    }
+    async fn get_model_configuration(&self, alias: &str) -> Result<Arc<LlamaCppConfigArgs>, ()> {
+        let (_, ctx_size) = ContextSizeAwareAlias::resolve(alias, |model| {
+            self.get_static_model_configuration(model).is_some()
+        })
+        .ok_or(())?;
+        Ok(Arc::new(LlamaCppConfigArgs {
+            alias: alias.to_owned(),
+            ctx_size,
+            ..Default::default()
+        }))
+    }
+}
+
+pub(crate) struct NoMetrics;
+
+impl MetricsOutPort for NoMetrics {
+    fn observe_request(&self, _: &str, _: &str, _: u16, _: Duration) {}
+    fn observe_time_to_first_token(&self, _: &str, _: &str, _: Duration) {}
+    fn observe_generation(&self, _: &str, _: &str, _: u64, _: Option<f64>) {}
+    fn observe_model_load(&self, _: &str, _: &str, _: Duration) {}
+    fn count_model_swap(&self, _: &str, _: &str) {}
+    fn count_heartbeat(&self, _: &str) {}
+    fn set_process_state(&self, _: &str, _: &str) {}
+    fn encode(&self, _: Vec<(String, String)>) -> String {
+        String::new()
+    }
+}
+
+/// the models-service with stopped backends, returning the controller of the language-models
+pub(crate) fn models_service_with(
+    model_loader: FakeModelLoader,
+    parallel: u8,
+) -> (Arc<FakeController>, Arc<dyn ModelsServiceInPort>) {
+    let controller = Arc::new(FakeController::stopped());
+    let service = DefaultModelsService::create_service(
+        controller.clone(),
+        Arc::new(FakeController::stopped()),
+        Arc::new(model_loader),
+        parallel,
+        4,
+        4,
+        HashMap::new(),
+        Arc::new(NoMetrics),
+    );
+    (controller, service)
+}
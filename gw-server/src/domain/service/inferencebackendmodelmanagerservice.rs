@@ -1,6 +1,8 @@
 use crate::domain::ports::{LlamaCppControllerOutPort, ModelManagerServiceInPort};
 use async_trait::async_trait;
-use inference_backends::{LlamaCppProcessState, LlamaCppRunConfig};
+use inference_backends::{
+    LlamaCppProcessHistory, LlamaCppProcessState, LlamaCppRunConfig, ProcessLog,
+};
 use std::sync::Arc;
 
 pub struct InferenceBackendModelManagerService {
@@ -23,16 +25,31 @@ impl ModelManagerServiceInPort for InferenceBackendModelManagerService {
         self.llamacpp_controller.get_llamacpp_state().await
     }
 
-    async fn stop_llamacpp_process(&self) {
-        self.llamacpp_controller.stop_llamacpp_process().await
+    async fn stop_llamacpp_process(&self, requested_by: &str) {
+        self.llamacpp_controller
+            .stop_llamacpp_process(requested_by)
+            .await
     }
 
     async fn start_llamacpp_process(
         &self,
         llamacpp_run_config: LlamaCppRunConfig,
+        requested_by: &str,
     ) -> LlamaCppProcessState {
         self.llamacpp_controller
-            .start_llamacpp_process(llamacpp_run_config)
+            .start_llamacpp_process(llamacpp_run_config, requested_by)
             .await
     }
+
+    async fn reset_failures(&self, requested_by: &str) {
+        self.llamacpp_controller.reset_failures(requested_by).await
+    }
+
+    fn process_log(&self) -> ProcessLog {
+        self.llamacpp_controller.process_log()
+    }
+
+    fn process_history(&self) -> LlamaCppProcessHistory {
+        self.llamacpp_controller.process_history()
+    }
 }
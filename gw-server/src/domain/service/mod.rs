@@ -4,3 +4,15 @@ mod inferencebackendmodelmanagerservice;
 pub use inferencebackendmodelmanagerservice::InferenceBackendModelManagerService;
 mod defaultmodelsservice;
 pub use defaultmodelsservice::DefaultModelsService;
+mod defaulthealthservice;
+pub use defaulthealthservice::DefaultHealthService;
+mod defaultmetricsservice;
+pub use defaultmetricsservice::DefaultMetricsService;
+mod defaultauditservice;
+pub use defaultauditservice::DefaultAuditService;
+mod defaultidleunloadservice;
+pub use defaultidleunloadservice::DefaultIdleUnloadService;
+mod defaultmodelcatalogservice;
+pub use defaultmodelcatalogservice::DefaultModelCatalogService;
+#[cfg(test)]
+pub(crate) mod fakes;
@@ -0,0 +1,259 @@
+use crate::domain::{model::AuditRecord, ports::AuditLogOutPort};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+    thread::JoinHandle,
+};
+use tracing::error;
+
+/// when the current audit-log file is moved aside and a new one is started
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AuditLogRotation {
+    pub max_file_size: Option<u64>,
+    pub daily: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct AuditLine {
+    timestamp: String,
+    key_name: Option<String>,
+    method: String,
+    route: String,
+    model: Option<String>,
+    status: u16,
+    duration_ms: u128,
+    queue_ms: Option<u128>,
+    prompt_tokens: Option<u64>,
+    completion_tokens: Option<u64>,
+    trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_body: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_body: Option<serde_json::Value>,
+}
+
+impl From<AuditRecord> for AuditLine {
+    fn from(value: AuditRecord) -> Self {
+        Self {
+            timestamp: DateTime::<Utc>::from(value.timestamp)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            key_name: value.key_name,
+            method: value.method,
+            route: value.route,
+            model: value.model,
+            status: value.status,
+            duration_ms: value.duration.as_millis(),
+            queue_ms: value.queue_time.map(|d| d.as_millis()),
+            prompt_tokens: value.prompt_tokens,
+            completion_tokens: value.completion_tokens,
+            trace_id: value.trace_id,
+            request_body: value.request_body,
+            response_body: value.response_body,
+        }
+    }
+}
+
+/// appends audit-records as json-lines; writing happens on a dedicated thread so request
+/// handling never waits for the disk
+pub struct JsonlAuditLogAdapter {
+    sender: Option<mpsc::Sender<AuditRecord>>,
+    writer_thread: Option<JoinHandle<()>>,
+}
+
+impl JsonlAuditLogAdapter {
+    pub fn create_adapter(
+        path: impl Into<PathBuf>,
+        rotation: AuditLogRotation,
+    ) -> std::io::Result<Arc<dyn AuditLogOutPort>> {
+        let mut writer = AuditLogWriter::open(path.into(), rotation)?;
+        let (sender, receiver) = mpsc::channel::<AuditRecord>();
+        let writer_thread =
+            std::thread::Builder::new()
+                .name("audit-log".into())
+                .spawn(move || {
+                    for record in receiver {
+                        if let Err(e) = writer.write(record) {
+                            error!("error writing audit-log: {e}");
+                        }
+                    }
+                })?;
+        Ok(Arc::new(Self {
+            sender: Some(sender),
+            writer_thread: Some(writer_thread),
+        }))
+    }
+}
+
+impl AuditLogOutPort for JsonlAuditLogAdapter {
+    fn append(&self, record: AuditRecord) {
+        if let Some(sender) = &self.sender
+            && sender.send(record).is_err()
+        {
+            error!("audit-log writer is gone, dropping record");
+        }
+    }
+}
+
+// pending records are written before the adapter is gone
+impl Drop for JsonlAuditLogAdapter {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(writer_thread) = self.writer_thread.take() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+struct AuditLogWriter {
+    path: PathBuf,
+    rotation: AuditLogRotation,
+    file: File,
+    size: u64,
+    day: NaiveDate,
+}
+
+impl AuditLogWriter {
+    fn open(path: PathBuf, rotation: AuditLogRotation) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        // an existing file is continued but still rotated if it was started on an earlier day
+        let day = metadata
+            .modified()
+            .map(|modified| DateTime::<Utc>::from(modified).date_naive())
+            .unwrap_or_else(|_| Utc::now().date_naive());
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            size: metadata.len(),
+            day,
+        })
+    }
+
+    fn write(&mut self, record: AuditRecord) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(&AuditLine::from(record))?;
+        line.push(b'\n');
+
+        let now = Utc::now();
+        let exceeds_size = self
+            .rotation
+            .max_file_size
+            .is_some_and(|max_file_size| self.size + line.len() as u64 > max_file_size);
+        let day_changed = self.rotation.daily && now.date_naive() != self.day;
+        if self.size > 0 && (exceeds_size || day_changed) {
+            self.rotate(now)?;
+        }
+
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> std::io::Result<()> {
+        self.file.flush()?;
+        let rotated_path = rotated_path(&self.path, now);
+        std::fs::rename(&self.path, rotated_path)?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.day = now.date_naive();
+        Ok(())
+    }
+}
+
+/// 'audit.jsonl' is moved to 'audit-20260101T120000Z.jsonl' (a counter is added on clashes)
+fn rotated_path(path: &Path, now: DateTime<Utc>) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let timestamp = now.format("%Y%m%dT%H%M%SZ");
+
+    let mut candidate = path.with_file_name(format!("{stem}-{timestamp}{extension}"));
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = path.with_file_name(format!("{stem}-{timestamp}-{counter}{extension}"));
+        counter += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn record(route: &str) -> AuditRecord {
+        AuditRecord {
+            timestamp: SystemTime::UNIX_EPOCH,
+            key_name: Some("default".into()),
+            method: "POST".into(),
+            route: route.into(),
+            model: Some("gpt-oss-120b".into()),
+            status: 200,
+            duration: Duration::from_millis(1500),
+            queue_time: Some(Duration::from_millis(250)),
+            prompt_tokens: Some(12),
+            completion_tokens: Some(34),
+            trace_id: None,
+            request_body: None,
+            response_body: None,
+        }
+    }
+
+    #[test]
+    fn records_are_written_as_json_lines_and_rotated_by_size() {
+        let dir = std::env::temp_dir().join(format!("maiserver-audit-{}", std::process::id()));
+        let path = dir.join("audit.jsonl");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let audit_log = JsonlAuditLogAdapter::create_adapter(
+            &path,
+            AuditLogRotation {
+                max_file_size: Some(300),
+                daily: false,
+            },
+        )
+        .unwrap();
+        audit_log.append(record("/api/v1/chat/completions"));
+        audit_log.append(record("/api/v1/embeddings"));
+        drop(audit_log);
+
+        let current = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            current,
+            "{\"timestamp\":\"1970-01-01T00:00:00.000Z\",\"key-name\":\"default\",\"method\":\"POST\",\
+            \"route\":\"/api/v1/embeddings\",\"model\":\"gpt-oss-120b\",\"status\":200,\"duration-ms\":1500,\
+            \"queue-ms\":250,\"prompt-tokens\":12,\"completion-tokens\":34,\"trace-id\":null}\n"
+        );
+        let rotated = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|p| p != &path)
+            .collect::<Vec<_>>();
+        assert_eq!(rotated.len(), 1);
+        assert!(
+            std::fs::read_to_string(&rotated[0])
+                .unwrap()
+                .contains("/api/v1/chat/completions")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
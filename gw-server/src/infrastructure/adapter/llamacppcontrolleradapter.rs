@@ -1,28 +1,40 @@
 use crate::domain::ports::LlamaCppControllerOutPort;
 use async_trait::async_trait;
 use inference_backends::{
-    LlamaCppBackend, LlamaCppBackendController, LlamaCppProcessState, LlamaCppRunConfig,
+    LlamaCppBackend, LlamaCppBackendController, LlamaCppProcessHistory, LlamaCppProcessState,
+    LlamaCppRunConfig, ProcessLog, RemoteAgent, ResourceLimits, RestartPolicy, StopStrategy,
 };
-use std::sync::Arc;
-use tracing::trace;
+use std::{collections::HashMap, sync::Arc};
+use tracing::{error, trace};
 
 pub struct LlamaCppControllerAdapter {
     llamacpp_controller: LlamaCppBackendController,
 }
 
 impl LlamaCppControllerAdapter {
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_adapter(
-        port: u16,
+        name: &str,
         timeout: Option<u16>,
         llama_cpp_command: impl Into<String>,
         llama_cpp_execdir: impl Into<String>,
+        metrics: bool,
+        restart_policy: RestartPolicy,
+        stop_strategy: StopStrategy,
+        resource_limits: ResourceLimits,
+        agents: HashMap<String, RemoteAgent>,
     ) -> Arc<dyn LlamaCppControllerOutPort> {
         let llamacpp_controller = LlamaCppBackendController::init_backend(LlamaCppBackend {
             host: "localhost".to_owned(),
-            port,
             timeout,
             llama_cpp_command: llama_cpp_command.into(),
             llama_cpp_execdir: llama_cpp_execdir.into(),
+            metrics,
+            restart_policy,
+            stop_strategy,
+            resource_limits,
+            name: name.to_owned(),
+            agents,
         })
         .await;
 
@@ -35,22 +47,60 @@ impl LlamaCppControllerAdapter {
 #[async_trait]
 impl LlamaCppControllerOutPort for LlamaCppControllerAdapter {
     async fn get_llamacpp_state(&self) -> LlamaCppProcessState {
-        self.llamacpp_controller.read_state().await
+        self.llamacpp_controller
+            .read_state()
+            .await
+            .unwrap_or_else(|e| {
+                // nothing can be controlled anymore ; behaves as if no process is running
+                error!("error reading state of llamacpp-backend: {e}");
+                LlamaCppProcessState::Stopped
+            })
     }
 
     async fn start_llamacpp_process(
         &self,
         llamacpp_run_config: LlamaCppRunConfig,
+        requested_by: &str,
     ) -> LlamaCppProcessState {
         trace!(
             "starting llamacpp-backend process (using 'parallel' of {}, 'threads' of {})",
             llamacpp_run_config.parallel, llamacpp_run_config.threads
         );
-        self.llamacpp_controller.start(llamacpp_run_config).await;
-        self.llamacpp_controller.read_state().await
+        match self
+            .llamacpp_controller
+            .start(llamacpp_run_config.clone(), requested_by)
+            .await
+        {
+            // the config is not started at all ; waiting for it would be in vain
+            Err(e @ managed_process::Error::ConfigExcluded(_)) => {
+                error!("refused to start llamacpp-backend process: {e}");
+                LlamaCppProcessState::Failed(llamacpp_run_config, e.to_string())
+            }
+            Err(e) => {
+                error!("error starting llamacpp-backend process: {e}");
+                self.get_llamacpp_state().await
+            }
+            Ok(()) => self.get_llamacpp_state().await,
+        }
     }
 
-    async fn stop_llamacpp_process(&self) {
-        self.llamacpp_controller.stop().await;
+    async fn stop_llamacpp_process(&self, requested_by: &str) {
+        if let Err(e) = self.llamacpp_controller.stop(requested_by).await {
+            error!("error stopping llamacpp-backend process: {e}");
+        }
+    }
+
+    async fn reset_failures(&self, requested_by: &str) {
+        if let Err(e) = self.llamacpp_controller.reset_failures(requested_by).await {
+            error!("error resetting failures of llamacpp-backend: {e}");
+        }
+    }
+
+    fn process_log(&self) -> ProcessLog {
+        self.llamacpp_controller.log()
+    }
+
+    fn process_history(&self) -> LlamaCppProcessHistory {
+        self.llamacpp_controller.history()
     }
 }
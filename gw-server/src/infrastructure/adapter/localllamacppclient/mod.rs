@@ -1,4 +1,11 @@
-use crate::{domain::ports::OpenAiClientOutPort, model::SecurityConfig};
+use crate::{
+    domain::{
+        model::BackendProbe,
+        ports::{LlamaCppControllerOutPort, MetricsOutPort, OpenAiClientOutPort},
+    },
+    infrastructure::telemetry::inject_trace_context,
+    model::SecurityConfig,
+};
 use async_openai::types::{chat::CreateChatCompletionRequest, embeddings::CreateEmbeddingRequest};
 use async_trait::async_trait;
 use axum::{
@@ -16,19 +23,22 @@ use axum::{
 use flate2::Compression;
 use futures::StreamExt;
 use http_body_util::BodyExt;
-use std::{io::Write, sync::Arc};
+use inference_backends::LlamaCppProcessState;
+use std::{io::Write, sync::Arc, time::Duration};
 use tokio_util::{
     codec::{FramedRead, LinesCodec},
     io::StreamReader,
 };
-use tracing::{debug, error, info, trace, warn};
+use tracing::{Instrument, debug, error, info, info_span, trace, warn};
 
 mod responsepayload;
 use responsepayload::ResponsePayload;
 
 const LLAMACPP_HTTP_SCHEME: &str = "http";
-const LLAMACPP_HOST: &str = "localhost";
 const LLAMACPP_API_BASE_PATH: &str = "v1";
+const LLAMACPP_HEALTH_PATH: &str = "health";
+const LLAMACPP_METRICS_PATH: &str = "metrics";
+const LLAMACPP_HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
 
 use hyper_util::{
     client::legacy::{Client as LegacyClient, connect::HttpConnector},
@@ -39,21 +49,39 @@ type Client = LegacyClient<HttpConnector, axum::body::Body>;
 
 pub struct LocalLlamaCppClientAdapter {
     client: Client,
-    llamacpp_port: u16,
+    backend_name: Arc<str>,
+    // tells the address of the running llama-server
+    llamacpp_controller: Arc<dyn LlamaCppControllerOutPort>,
     security_config: Arc<dyn SecurityConfig>,
+    metrics: Arc<dyn MetricsOutPort>,
 }
 
 impl LocalLlamaCppClientAdapter {
     pub fn create_adapter(
-        port: u16,
+        backend_name: impl Into<String>,
+        llamacpp_controller: Arc<dyn LlamaCppControllerOutPort>,
         security_config: Arc<dyn SecurityConfig>,
+        metrics: Arc<dyn MetricsOutPort>,
     ) -> Arc<dyn OpenAiClientOutPort> {
         let client = LegacyClient::builder(TokioExecutor::new()).build(HttpConnector::new());
 
         Arc::new(Self {
             client,
-            llamacpp_port: port,
+            backend_name: Arc::from(backend_name.into()),
+            llamacpp_controller,
             security_config,
+            metrics,
+        })
+    }
+
+    /// 'host:port' of the llama-server process currently starting or running
+    async fn upstream(&self) -> Result<String, StatusCode> {
+        upstream_of(&self.llamacpp_controller.get_llamacpp_state().await).ok_or_else(|| {
+            debug!(
+                "no llama-server process of backend '{}' to forward to",
+                self.backend_name
+            );
+            StatusCode::SERVICE_UNAVAILABLE
         })
     }
 
@@ -71,16 +99,11 @@ impl LocalLlamaCppClientAdapter {
                 .unwrap_or(path)
         };
 
+        let upstream = self.upstream().await?;
         let uri_string = if let Some(path_prefix) = prefix_target_path {
-            format!(
-                "{LLAMACPP_HTTP_SCHEME}://{LLAMACPP_HOST}:{}/{path_prefix}/{}",
-                self.llamacpp_port, path_and_query
-            )
+            format!("{LLAMACPP_HTTP_SCHEME}://{upstream}/{path_prefix}/{path_and_query}")
         } else {
-            format!(
-                "{LLAMACPP_HTTP_SCHEME}://{LLAMACPP_HOST}:{}/{}",
-                self.llamacpp_port, path_and_query
-            )
+            format!("{LLAMACPP_HTTP_SCHEME}://{upstream}/{path_and_query}")
         };
 
         trace!("forwarding request to llama-server using uri {uri_string}");
@@ -90,14 +113,18 @@ impl LocalLlamaCppClientAdapter {
 
         request.headers_mut().insert(
             HOST_HEADER,
-            HeaderValue::from_str(LLAMACPP_HOST).expect("hostname expected as valid headervalue"),
+            HeaderValue::from_str(&upstream).expect("upstream expected as valid headervalue"),
         );
 
         *request.version_mut() = Version::HTTP_11;
 
+        let upstream_span = info_span!("upstream_request", backend = %self.backend_name);
+        inject_trace_context(&upstream_span, request.headers_mut());
+
         Ok(self
             .client
             .request(request)
+            .instrument(upstream_span)
             .await
             .map_err(|e| {
                 error!("error forwarding request to llama.cpp: {e}");
@@ -149,8 +176,8 @@ impl LocalLlamaCppClientAdapter {
         };
 
         let uri_string = format!(
-            "{LLAMACPP_HTTP_SCHEME}://{LLAMACPP_HOST}:{}{}",
-            self.llamacpp_port, path_and_query
+            "{LLAMACPP_HTTP_SCHEME}://{}{path_and_query}",
+            self.upstream().await?
         );
 
         trace!("forwarding request to llama-server using uri {uri_string}");
@@ -251,9 +278,9 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+        let upstream = self.upstream().await?;
         let mut request_builder = Request::post(format!(
-            "{LLAMACPP_HTTP_SCHEME}://{LLAMACPP_HOST}:{}/{LLAMACPP_API_BASE_PATH}/chat/completions",
-            self.llamacpp_port
+            "{LLAMACPP_HTTP_SCHEME}://{upstream}/{LLAMACPP_API_BASE_PATH}/chat/completions"
         ));
 
         if let Some(security_apikey) = self.security_config.get_apikey() {
@@ -262,7 +289,7 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
         }
 
         let mut request = request_builder
-            .header(HOST_HEADER, LLAMACPP_HOST)
+            .header(HOST_HEADER, &upstream)
             .version(Version::HTTP_11)
             .body(Body::from(json_string))
             .map_err(|e| {
@@ -274,14 +301,29 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
             .headers_mut()
             .insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
 
+        // the stream is polled outside of the request-span, so its spans get explicit parents
+        let upstream_span = info_span!(
+            "upstream_request",
+            backend = %self.backend_name,
+            model = payload.model
+        );
+        inject_trace_context(&upstream_span, request.headers_mut());
+
         let client = self.client.clone();
+        let metrics = self.metrics.clone();
+        let backend_name = self.backend_name.clone();
+        let model = payload.model.clone();
 
         let sanitized_stream = async_stream::stream! {
+            let request_start = std::time::Instant::now();
+            let mut first_chunk_received_at: Option<std::time::Instant> = None;
             let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(10));
             // WICHTIG: Das erste .tick() feuert sofort, was gewünscht ist (Sofort-Heartbeat)
 
             let mut connect_future = Box::pin(client.request(request));
             let response_result;
+            // llama-server answers once a slot picked up the request and the prompt was processed
+            let queue_wait_span = info_span!(parent: &upstream_span, "queue_wait");
 
             // PHASE A: Warten auf Verbindung
             info!("connecting to llama-server");
@@ -289,6 +331,7 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
                 tokio::select! {
                     _ = heartbeat_interval.tick() => {
                         info!("\tsending a heartbeat while still waiting for response from llama-server");
+                        metrics.count_heartbeat(&backend_name);
                         yield Ok::<Bytes, std::io::Error>(Bytes::from(": heartbeat\n\n"));
                     }
                     res = &mut connect_future => {
@@ -298,6 +341,7 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
                     }
                 }
             }
+            drop(queue_wait_span);
 
             let response = match response_result {
                 Ok(r) => r,
@@ -308,6 +352,7 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
             };
 
             // PHASE B: Daten streamen
+            let _streaming_span = info_span!(parent: &upstream_span, "upstream_streaming");
             let body = response.into_body();
             let stream_reader = StreamReader::new(
                 body.into_data_stream().map(|res| res.map_err(std::io::Error::other)),
@@ -322,6 +367,7 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
                 tokio::select! {
                     _ = heartbeat_interval.tick() => {
                         info!("\tsending a heartbeat while waiting for tokens");
+                        metrics.count_heartbeat(&backend_name);
                         yield Ok::<Bytes, std::io::Error>(Bytes::from(": heartbeat\n\n"));
                     }
                     next_line = lines.next() => {
@@ -341,6 +387,13 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
                             warn!("skipping non-data sse-event from llama-server: '{trimmed}' -> skipping");
                             continue;
                         }
+                        let first_chunk_received_at = *first_chunk_received_at.get_or_insert_with(|| {
+                            metrics.observe_time_to_first_token(&backend_name, &model, request_start.elapsed());
+                            std::time::Instant::now()
+                        });
+                        if let Some((completion_tokens, tokens_per_second)) = parse_generation_stats(trimmed, first_chunk_received_at.elapsed()) {
+                            metrics.observe_generation(&backend_name, &model, completion_tokens, tokens_per_second);
+                        }
                         // Ersetze alle vorkommenden :null durch :"" oder entferne sie.
                         // FIXME: UNSAUBER!!! BESSER:JSON PARSEN
                         let mut sanitized = trimmed.replace(":null", ":\"\"");
@@ -392,9 +445,9 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+        let upstream = self.upstream().await?;
         let mut request_builder = Request::post(format!(
-            "{LLAMACPP_HTTP_SCHEME}://{LLAMACPP_HOST}:{}/{LLAMACPP_API_BASE_PATH}/embeddings",
-            self.llamacpp_port
+            "{LLAMACPP_HTTP_SCHEME}://{upstream}/{LLAMACPP_API_BASE_PATH}/embeddings"
         ));
 
         if let Some(security_apikey) = self.security_config.get_apikey() {
@@ -402,8 +455,8 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
                 request_builder.header(AUTHORIZATION, format!("Bearer {}", security_apikey));
         }
 
-        let request = request_builder
-            .header(HOST_HEADER, LLAMACPP_HOST)
+        let mut request = request_builder
+            .header(HOST_HEADER, &upstream)
             .version(Version::HTTP_11)
             .body(Body::from(json_string))
             .map_err(|e| {
@@ -411,9 +464,17 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
 
+        let upstream_span = info_span!(
+            "upstream_request",
+            backend = %self.backend_name,
+            model = payload.model
+        );
+        inject_trace_context(&upstream_span, request.headers_mut());
+
         Ok(self
             .client
             .request(request)
+            .instrument(upstream_span)
             .await
             .map_err(|e| {
                 error!("error posting embeddings to llama.cpp: {e}");
@@ -423,13 +484,11 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
     }
 
     async fn request_chat(&self) -> Result<Response, StatusCode> {
-        let url = format!(
-            "{LLAMACPP_HTTP_SCHEME}://{LLAMACPP_HOST}:{}",
-            self.llamacpp_port
-        );
+        let upstream = self.upstream().await?;
+        let url = format!("{LLAMACPP_HTTP_SCHEME}://{upstream}");
         trace!("requesting '{url}'");
         let request = Request::get(url)
-            .header(HOST_HEADER, LLAMACPP_HOST)
+            .header(HOST_HEADER, &upstream)
             .header(ACCEPT_ENCODING, "gzip")
             .version(Version::HTTP_11)
             .body(Body::empty())
@@ -440,4 +499,194 @@ impl OpenAiClientOutPort for LocalLlamaCppClientAdapter {
 
         self.forward_uichat_request_and_process(request).await
     }
+
+    async fn probe_health(&self) -> BackendProbe {
+        let upstream = match self.upstream().await {
+            Ok(upstream) => upstream,
+            Err(_) => return BackendProbe::Unreachable("no llama-server process".to_owned()),
+        };
+        let url = format!("{LLAMACPP_HTTP_SCHEME}://{upstream}/{LLAMACPP_HEALTH_PATH}");
+        let request = match Request::get(&url)
+            .header(HOST_HEADER, &upstream)
+            .version(Version::HTTP_11)
+            .body(Body::empty())
+        {
+            Ok(request) => request,
+            Err(e) => {
+                error!("error building llama.cpp-health-request: {e}");
+                return BackendProbe::Unreachable(e.to_string());
+            }
+        };
+
+        trace!("probing '{url}'");
+        match tokio::time::timeout(LLAMACPP_HEALTH_PROBE_TIMEOUT, self.client.request(request))
+            .await
+        {
+            Err(_) => BackendProbe::Unreachable(format!(
+                "no response within {}ms",
+                LLAMACPP_HEALTH_PROBE_TIMEOUT.as_millis()
+            )),
+            Ok(Err(e)) => BackendProbe::Unreachable(e.to_string()),
+            Ok(Ok(response)) => match response.status() {
+                StatusCode::OK => BackendProbe::Healthy,
+                // llama-server answers 503 while the model is still being loaded
+                StatusCode::SERVICE_UNAVAILABLE => BackendProbe::Loading,
+                other => BackendProbe::Unhealthy(other.as_u16()),
+            },
+        }
+    }
+
+    async fn fetch_backend_metrics(&self) -> Option<String> {
+        let upstream = self.upstream().await.ok()?;
+        let request = Request::get(format!(
+            "{LLAMACPP_HTTP_SCHEME}://{upstream}/{LLAMACPP_METRICS_PATH}"
+        ))
+        .header(HOST_HEADER, &upstream)
+        .version(Version::HTTP_11);
+        let request = if let Some(security_apikey) = self.security_config.get_apikey() {
+            request.header(AUTHORIZATION, format!("Bearer {}", security_apikey))
+        } else {
+            request
+        }
+        .body(Body::empty())
+        .ok()?;
+
+        let response =
+            tokio::time::timeout(LLAMACPP_HEALTH_PROBE_TIMEOUT, self.client.request(request))
+                .await
+                .ok()?
+                .inspect_err(|e| trace!("error fetching metrics from llama-server: {e}"))
+                .ok()?;
+        if response.status() != StatusCode::OK {
+            trace!(
+                "llama-server answered metrics-request with {}",
+                response.status()
+            );
+            return None;
+        }
+        let bytes = response.into_body().collect().await.ok()?.to_bytes();
+        Some(String::from_utf8_lossy(&bytes).to_string())
+    }
+}
+
+// the address llama-server listens on ; known from spawning the process until it is stopped
+fn upstream_of(state: &LlamaCppProcessState) -> Option<String> {
+    match state {
+        LlamaCppProcessState::Starting(_, endpoint)
+        | LlamaCppProcessState::Running(_, endpoint) => endpoint.as_ref().map(ToString::to_string),
+        _ => None,
+    }
+}
+
+/// extracts the number of generated tokens and the generation-speed from a streamed chunk
+/// (llama-server adds its 'timings' to the final chunk ; without them the 'usage' of the chunk is
+/// used and the speed is derived from the time spent generating since the first chunk, unless no
+/// time was measured)
+fn parse_generation_stats(
+    data_line: &str,
+    generation_time: Duration,
+) -> Option<(u64, Option<f64>)> {
+    if !data_line.contains("\"timings\"") && !data_line.contains("\"usage\"") {
+        return None;
+    }
+    let chunk: serde_json::Value =
+        serde_json::from_str(data_line.strip_prefix("data:")?.trim()).ok()?;
+    let usage_completion_tokens = || {
+        chunk
+            .get("usage")?
+            .get("completion_tokens")
+            .and_then(serde_json::Value::as_u64)
+    };
+    match chunk.get("timings") {
+        Some(timings) => {
+            let completion_tokens = timings
+                .get("predicted_n")
+                .and_then(serde_json::Value::as_u64)
+                .or_else(usage_completion_tokens)?;
+            let tokens_per_second = timings
+                .get("predicted_per_second")
+                .and_then(serde_json::Value::as_f64)?;
+            Some((completion_tokens, Some(tokens_per_second)))
+        }
+        None => {
+            let completion_tokens = usage_completion_tokens()?;
+            let seconds = generation_time.as_secs_f64();
+            let tokens_per_second = (seconds > 0.0).then(|| completion_tokens as f64 / seconds);
+            Some((completion_tokens, tokens_per_second))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use inference_backends::{Endpoint, LlamaCppConfigArgs, LlamaCppRunConfig};
+
+    #[test]
+    fn requests_are_forwarded_to_the_endpoint_of_the_current_process() {
+        let config = LlamaCppRunConfig {
+            args_handle: Arc::new(LlamaCppConfigArgs::default()),
+            env_handle: Default::default(),
+            parallel: 1,
+            threads: 1,
+            threads_batch: 1,
+        };
+        let endpoint = Some(Endpoint {
+            host: "127.0.0.1".to_owned(),
+            port: 40123,
+        });
+
+        assert_eq!(
+            upstream_of(&LlamaCppProcessState::Starting(
+                config.clone(),
+                endpoint.clone()
+            )),
+            Some("127.0.0.1:40123".to_owned())
+        );
+        assert_eq!(
+            upstream_of(&LlamaCppProcessState::Running(config.clone(), endpoint)),
+            Some("127.0.0.1:40123".to_owned())
+        );
+        // waiting for a restart after a crash
+        assert_eq!(
+            upstream_of(&LlamaCppProcessState::Starting(config.clone(), None)),
+            None
+        );
+        assert_eq!(upstream_of(&LlamaCppProcessState::Stopped), None);
+    }
+
+    #[test]
+    fn generation_stats_fall_back_to_the_usage_without_timings() {
+        let generation_time = Duration::from_secs(4);
+
+        assert_eq!(
+            parse_generation_stats(
+                r#"data: {"choices":[],"timings":{"predicted_n":42,"predicted_per_second":21.5}}"#,
+                generation_time
+            ),
+            Some((42, Some(21.5)))
+        );
+        assert_eq!(
+            parse_generation_stats(
+                r#"data: {"choices":[],"usage":{"prompt_tokens":7,"completion_tokens":40}}"#,
+                generation_time
+            ),
+            Some((40, Some(10.0)))
+        );
+        // the speed is not made up if no time was measured
+        assert_eq!(
+            parse_generation_stats(
+                r#"data: {"choices":[],"usage":{"prompt_tokens":7,"completion_tokens":40}}"#,
+                Duration::ZERO
+            ),
+            Some((40, None))
+        );
+        assert_eq!(
+            parse_generation_stats(
+                r#"data: {"choices":[{"delta":{"content":"hi"}}]}"#,
+                generation_time
+            ),
+            None
+        );
+    }
 }
@@ -6,3 +6,9 @@ pub use llamacppcontrolleradapter::LlamaCppControllerAdapter;
 
 mod staticmodelloader;
 pub use staticmodelloader::StaticModelLoader;
+
+mod prometheusmetrics;
+pub use prometheusmetrics::PrometheusMetricsAdapter;
+
+mod jsonlauditlog;
+pub use jsonlauditlog::{AuditLogRotation, JsonlAuditLogAdapter};
@@ -0,0 +1,315 @@
+use crate::domain::ports::MetricsOutPort;
+use prometheus::{
+    GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::{sync::Arc, time::Duration};
+use tracing::error;
+
+const METRICS_NAMESPACE: &str = "maiserver";
+const PROCESS_STATES: [&str; 5] = ["stopped", "starting", "running", "stopping", "failed"];
+const UNKNOWN_MODEL: &str = "none";
+
+pub struct PrometheusMetricsAdapter {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    time_to_first_token: HistogramVec,
+    tokens_per_second: HistogramVec,
+    generated_tokens: IntCounterVec,
+    model_load_duration: HistogramVec,
+    model_swaps: IntCounterVec,
+    heartbeats: IntCounterVec,
+    process_state: GaugeVec,
+}
+
+impl PrometheusMetricsAdapter {
+    pub fn create_adapter() -> Arc<dyn MetricsOutPort> {
+        let registry = Registry::new_custom(Some(METRICS_NAMESPACE.into()), None)
+            .expect("namespace is a valid prometheus-prefix");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "number of handled http-requests"),
+            &["route", "model", "status"],
+        )
+        .expect("valid metric-definition");
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "duration of http-requests including streamed responses",
+            )
+            .buckets(vec![
+                0.01, 0.05, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+            ]),
+            &["route", "model", "status"],
+        )
+        .expect("valid metric-definition");
+        let time_to_first_token = HistogramVec::new(
+            HistogramOpts::new(
+                "time_to_first_token_seconds",
+                "time until the first streamed chunk was received from the backend",
+            )
+            .buckets(vec![
+                0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0,
+            ]),
+            &["backend", "model"],
+        )
+        .expect("valid metric-definition");
+        let tokens_per_second = HistogramVec::new(
+            HistogramOpts::new(
+                "generation_tokens_per_second",
+                "generation speed as reported by the backend",
+            )
+            .buckets(vec![1.0, 5.0, 10.0, 20.0, 30.0, 50.0, 75.0, 100.0, 200.0]),
+            &["backend", "model"],
+        )
+        .expect("valid metric-definition");
+        let generated_tokens = IntCounterVec::new(
+            Opts::new("generated_tokens_total", "number of generated tokens"),
+            &["backend", "model"],
+        )
+        .expect("valid metric-definition");
+        let model_load_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "model_load_duration_seconds",
+                "time until a requested model was served",
+            )
+            .buckets(vec![1.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 180.0, 300.0]),
+            &["backend", "model"],
+        )
+        .expect("valid metric-definition");
+        let model_swaps = IntCounterVec::new(
+            Opts::new(
+                "model_swaps_total",
+                "number of model-(re)starts per backend",
+            ),
+            &["backend", "model"],
+        )
+        .expect("valid metric-definition");
+        let heartbeats = IntCounterVec::new(
+            Opts::new(
+                "heartbeats_total",
+                "number of heartbeats sent to clients while waiting for the backend",
+            ),
+            &["backend"],
+        )
+        .expect("valid metric-definition");
+        let process_state = GaugeVec::new(
+            Opts::new(
+                "backend_process_state",
+                "current process-state per backend (1 for the active state)",
+            ),
+            &["backend", "state"],
+        )
+        .expect("valid metric-definition");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration.clone()),
+            Box::new(time_to_first_token.clone()),
+            Box::new(tokens_per_second.clone()),
+            Box::new(generated_tokens.clone()),
+            Box::new(model_load_duration.clone()),
+            Box::new(model_swaps.clone()),
+            Box::new(heartbeats.clone()),
+            Box::new(process_state.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metrics are registered exactly once");
+        }
+
+        Arc::new(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            time_to_first_token,
+            tokens_per_second,
+            generated_tokens,
+            model_load_duration,
+            model_swaps,
+            heartbeats,
+            process_state,
+        })
+    }
+}
+
+impl MetricsOutPort for PrometheusMetricsAdapter {
+    fn observe_request(&self, route: &str, model: &str, status: u16, duration: Duration) {
+        let model = if model.is_empty() {
+            UNKNOWN_MODEL
+        } else {
+            model
+        };
+        let status = status.to_string();
+        self.http_requests
+            .with_label_values(&[route, model, &status])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[route, model, &status])
+            .observe(duration.as_secs_f64());
+    }
+
+    fn observe_time_to_first_token(&self, backend: &str, model: &str, duration: Duration) {
+        self.time_to_first_token
+            .with_label_values(&[backend, model])
+            .observe(duration.as_secs_f64());
+    }
+
+    fn observe_generation(
+        &self,
+        backend: &str,
+        model: &str,
+        completion_tokens: u64,
+        tokens_per_second: Option<f64>,
+    ) {
+        self.generated_tokens
+            .with_label_values(&[backend, model])
+            .inc_by(completion_tokens);
+        if let Some(tokens_per_second) = tokens_per_second {
+            self.tokens_per_second
+                .with_label_values(&[backend, model])
+                .observe(tokens_per_second);
+        }
+    }
+
+    fn observe_model_load(&self, backend: &str, model: &str, duration: Duration) {
+        self.model_load_duration
+            .with_label_values(&[backend, model])
+            .observe(duration.as_secs_f64());
+    }
+
+    fn count_model_swap(&self, backend: &str, model: &str) {
+        self.model_swaps.with_label_values(&[backend, model]).inc();
+    }
+
+    fn count_heartbeat(&self, backend: &str) {
+        self.heartbeats.with_label_values(&[backend]).inc();
+    }
+
+    fn set_process_state(&self, backend: &str, state: &str) {
+        for known_state in PROCESS_STATES {
+            self.process_state
+                .with_label_values(&[backend, known_state])
+                .set(if known_state == state { 1.0 } else { 0.0 });
+        }
+    }
+
+    fn encode(&self, backend_metrics: Vec<(String, String)>) -> String {
+        let mut encoded = TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_else(|e| {
+                error!("error encoding metrics: {e}");
+                String::new()
+            });
+        encoded.push_str(&merge_backend_metrics(backend_metrics));
+        encoded
+    }
+}
+
+struct MetricFamily {
+    name: String,
+    header_lines: Vec<String>,
+    sample_lines: Vec<String>,
+}
+
+/// merges the text-formatted metrics of several backends into one exposition, adding a
+/// backend-label to every sample so equally named metrics of different backends can coexist
+fn merge_backend_metrics(backend_metrics: Vec<(String, String)>) -> String {
+    let mut families: Vec<MetricFamily> = Vec::new();
+
+    fn family_for<'a>(families: &'a mut Vec<MetricFamily>, name: &str) -> &'a mut MetricFamily {
+        let base_name = ["_bucket", "_sum", "_count"]
+            .iter()
+            .find_map(|suffix| {
+                name.strip_suffix(suffix)
+                    .filter(|base| families.iter().any(|f| f.name == *base))
+            })
+            .unwrap_or(name);
+        if let Some(idx) = families.iter().position(|f| f.name == base_name) {
+            &mut families[idx]
+        } else {
+            families.push(MetricFamily {
+                name: base_name.to_owned(),
+                header_lines: Vec::new(),
+                sample_lines: Vec::new(),
+            });
+            families.last_mut().expect("was pushed right before")
+        }
+    }
+
+    for (backend, text) in backend_metrics {
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some(rest) = line
+                .strip_prefix("# HELP ")
+                .or_else(|| line.strip_prefix("# TYPE "))
+            {
+                let Some(name) = rest.split_whitespace().next() else {
+                    continue;
+                };
+                let family = family_for(&mut families, name);
+                if !family.header_lines.iter().any(|h| h == line) {
+                    family.header_lines.push(line.to_owned());
+                }
+            } else if !line.starts_with('#') {
+                let (name, labels, value) = if let Some((name, rest)) = line.split_once('{')
+                    && let Some((labels, value)) = rest.rsplit_once('}')
+                {
+                    (name, labels.trim_end_matches(','), value.trim())
+                } else if let Some((name, value)) = line.split_once(' ') {
+                    (name, "", value.trim())
+                } else {
+                    continue;
+                };
+                let labels = if labels.is_empty() {
+                    format!("backend=\"{backend}\"")
+                } else {
+                    format!("backend=\"{backend}\",{labels}")
+                };
+                family_for(&mut families, name)
+                    .sample_lines
+                    .push(format!("{name}{{{labels}}} {value}"));
+            }
+        }
+    }
+
+    let mut merged = String::new();
+    for family in families {
+        for line in family.header_lines.iter().chain(family.sample_lines.iter()) {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merging_backend_metrics_adds_backend_label_and_deduplicates_headers() {
+        let llamacpp_metrics = "# HELP llamacpp:prompt_tokens_total Number of prompt tokens processed.\n\
+            # TYPE llamacpp:prompt_tokens_total counter\n\
+            llamacpp:prompt_tokens_total 12\n\
+            # HELP llamacpp:requests_processing Number of requests processing.\n\
+            # TYPE llamacpp:requests_processing gauge\n\
+            llamacpp:requests_processing{slot=\"0\"} 1\n";
+
+        let merged = merge_backend_metrics(vec![
+            ("llm".into(), llamacpp_metrics.into()),
+            ("embedding".into(), llamacpp_metrics.into()),
+        ]);
+
+        assert_eq!(
+            merged,
+            "# HELP llamacpp:prompt_tokens_total Number of prompt tokens processed.\n\
+            # TYPE llamacpp:prompt_tokens_total counter\n\
+            llamacpp:prompt_tokens_total{backend=\"llm\"} 12\n\
+            llamacpp:prompt_tokens_total{backend=\"embedding\"} 12\n\
+            # HELP llamacpp:requests_processing Number of requests processing.\n\
+            # TYPE llamacpp:requests_processing gauge\n\
+            llamacpp:requests_processing{backend=\"llm\",slot=\"0\"} 1\n\
+            llamacpp:requests_processing{backend=\"embedding\",slot=\"0\"} 1\n"
+        );
+    }
+}
@@ -1,49 +1,177 @@
-use crate::{domain::ports::ModelLoaderOutPort, model::SecurityConfig};
+use crate::{
+    domain::{model::CatalogError, ports::ModelLoaderOutPort},
+    model::SecurityConfig,
+};
 use async_trait::async_trait;
 use inference_backends::LlamaCppConfigArgs;
-use staticmodelconfig::{ContextSizeAwareAlias, ModelConfiguration};
-use std::{error::Error, path::Path, sync::Arc};
-use tracing::error;
+use staticmodelconfig::{ContextSizeAwareAlias, ModelCatalog, ModelConfiguration, ModelRoutes};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use tracing::{error, info, warn};
 
+/// serves the model-configurations of a directory along with the virtual model-names routed to
+/// them ; changes are written back
 pub struct StaticModelLoader {
-    static_model_configuration_list: Vec<ModelConfiguration>,
+    catalog: RwLock<ModelCatalog>,
+    model_routes: RwLock<ModelRoutes>,
+    model_routes_file: PathBuf,
+    revision: AtomicU64,
     security_config: Arc<dyn SecurityConfig>,
 }
 
 impl StaticModelLoader {
     pub fn create_adapter(
         configurations_dir: &Path,
+        model_routes_file: &Path,
         security_config: Arc<dyn SecurityConfig>,
     ) -> Result<Arc<dyn ModelLoaderOutPort>, Box<dyn Error>> {
+        let catalog = ModelCatalog::load(configurations_dir)?;
+        let model_routes = ModelRoutes::load_from_json_file(model_routes_file)?;
+        if let Err(e) = model_routes.validate(&catalog, &security_config.key_names()) {
+            warn!("the model-routes of {model_routes_file:?} do not match the catalog: {e}");
+        }
         Ok(Arc::new(Self {
-            static_model_configuration_list: ModelConfiguration::load_from_json_files(
-                configurations_dir,
-            )?
-            .0,
+            catalog: RwLock::new(catalog),
+            model_routes: RwLock::new(model_routes),
+            model_routes_file: model_routes_file.to_path_buf(),
+            revision: AtomicU64::new(0),
             security_config,
         }))
     }
+
+    // applies the change to the catalog and bumps the revision if it succeeded
+    fn change_catalog<T>(
+        &self,
+        change: impl FnOnce(&mut ModelCatalog) -> staticmodelconfig::Result<T>,
+    ) -> Result<T, CatalogError> {
+        let mut catalog = self
+            .catalog
+            .write()
+            .expect("writing the model-catalog must not fail");
+        let result = change(&mut catalog).map_err(catalog_error)?;
+        self.revision.fetch_add(1, Ordering::SeqCst);
+        Ok(result)
+    }
+}
+
+fn catalog_error(e: staticmodelconfig::Error) -> CatalogError {
+    match e {
+        staticmodelconfig::Error::UnknownAlias(alias) => CatalogError::UnknownAlias(alias),
+        staticmodelconfig::Error::AliasTaken(alias) => CatalogError::AliasTaken(alias),
+        staticmodelconfig::Error::InvalidConfiguration(reason) => CatalogError::Invalid(reason),
+        e => CatalogError::Io(e.to_string()),
+    }
 }
 
 #[async_trait]
 impl ModelLoaderOutPort for StaticModelLoader {
     fn get_static_model_configurations(&self) -> Vec<ModelConfiguration> {
-        self.static_model_configuration_list.clone()
+        self.catalog
+            .read()
+            .expect("reading the model-catalog must not fail")
+            .configurations()
+    }
+
+    fn get_static_model_configuration(&self, alias: &str) -> Option<ModelConfiguration> {
+        self.catalog
+            .read()
+            .expect("reading the model-catalog must not fail")
+            .get(alias)
+            .cloned()
+    }
+
+    fn catalog_revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    fn create_model_configuration(
+        &self,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError> {
+        let alias = model_configuration.alias.clone();
+        self.change_catalog(|catalog| catalog.create(model_configuration))?;
+        info!("added model '{alias}' to the catalog");
+        Ok(())
+    }
+
+    fn update_model_configuration(
+        &self,
+        alias: &str,
+        model_configuration: ModelConfiguration,
+    ) -> Result<(), CatalogError> {
+        self.change_catalog(|catalog| catalog.update(alias, model_configuration))?;
+        info!("updated model '{alias}' of the catalog");
+        Ok(())
+    }
+
+    fn delete_model_configuration(&self, alias: &str) -> Result<ModelConfiguration, CatalogError> {
+        let model_configuration = self.change_catalog(|catalog| catalog.delete(alias))?;
+        info!("removed model '{alias}' from the catalog");
+        Ok(model_configuration)
+    }
+
+    fn get_model_routes(&self) -> ModelRoutes {
+        self.model_routes
+            .read()
+            .expect("reading the model-routes must not fail")
+            .clone()
+    }
+
+    fn set_model_routes(&self, model_routes: ModelRoutes) -> Result<(), CatalogError> {
+        // the catalog must not change while the routes are validated against it
+        let catalog = self
+            .catalog
+            .read()
+            .expect("reading the model-catalog must not fail");
+        model_routes
+            .validate(&catalog, &self.security_config.key_names())
+            .map_err(catalog_error)?;
+        let mut current_model_routes = self
+            .model_routes
+            .write()
+            .expect("writing the model-routes must not fail");
+        model_routes
+            .save_to_json_file(&self.model_routes_file)
+            .map_err(catalog_error)?;
+        *current_model_routes = model_routes;
+        self.revision.fetch_add(1, Ordering::SeqCst);
+        info!("updated the model-routes");
+        Ok(())
     }
 
     async fn get_model_configuration(&self, alias: &str) -> Result<Arc<LlamaCppConfigArgs>, ()> {
-        let alias = alias.to_owned();
-        let (model_key, optional_context_size) =
-            match ContextSizeAwareAlias::try_from(alias.clone()) {
-                Ok(caa) => (caa.model(), Some(caa.context_size())),
-                Err(_) => (alias.clone(), None),
-            };
+        let Some((model_key, optional_context_size)) =
+            ContextSizeAwareAlias::resolve(alias, |model| {
+                self.get_static_model_configuration(model).is_some()
+            })
+        else {
+            error!("no model-configuration found for alias '{alias}'");
+            return Err(());
+        };
+        // context-size-aware aliases are served by their canonical form ('alias@32k')
+        let alias = match optional_context_size {
+            Some(context_size) => {
+                ContextSizeAwareAlias::from((model_key.clone(), context_size)).alias()
+            }
+            None => model_key.clone(),
+        };
 
-        if let Some(model_configuration) = self
-            .static_model_configuration_list
-            .iter()
-            .find(|&config| config.alias == model_key)
-        {
+        if let Some(model_configuration) = self.get_static_model_configuration(&model_key) {
+            if let Some(context_size) = &optional_context_size
+                && *context_size > model_configuration.max_ctx_size
+            {
+                error!(
+                    "context-size of '{alias}' exceeds the max-ctx-size {}",
+                    u64::from(&model_configuration.max_ctx_size)
+                );
+                return Err(());
+            }
             Ok(Arc::new(LlamaCppConfigArgs {
                 alias,
                 api_key: self
@@ -84,6 +212,12 @@ impl ModelLoaderOutPort for StaticModelLoader {
                 reasoning_budget: model_configuration.reasoning_budget,
                 embeddings: model_configuration.embeddings,
                 no_cache_prompt: model_configuration.no_cache_prompt,
+                resource_limits: model_configuration
+                    .resource_limits
+                    .as_ref()
+                    .map(Into::into)
+                    .unwrap_or_default(),
+                backend_host: model_configuration.backend_host.clone(),
             }))
         } else {
             error!("no model-configuration found for alias '{model_key}'");
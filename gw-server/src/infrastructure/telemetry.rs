@@ -0,0 +1,183 @@
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::{
+    Context, global,
+    propagation::{Extractor, Injector},
+    trace::{TraceContextExt, TracerProvider},
+};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::SdkTracerProvider};
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    Layer, filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+const SERVICE_NAME: &str = "mai-server";
+const OTLP_TRACES_PATH: &str = "v1/traces";
+
+/// keeps the otlp-exporter (if any) alive; remaining spans are flushed on drop
+pub struct TelemetryGuard {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(tracer_provider) = self.tracer_provider.take()
+            && let Err(e) = tracer_provider.shutdown()
+        {
+            eprintln!("error shutting down otlp-exporter: {e}");
+        }
+    }
+}
+
+/// installs the global subscriber: log-output to stdout limited to `max_level` and - if an
+/// otlp-endpoint (e.g. 'http://localhost:4318') is given - export of all spans via otlp/http
+pub fn init_tracing(
+    max_level: Level,
+    otlp_endpoint: Option<&str>,
+) -> Result<TelemetryGuard, ExporterBuildError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let tracer_provider = otlp_endpoint.map(create_tracer_provider).transpose()?;
+    let otel_layer = tracer_provider.as_ref().map(|tracer_provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer_provider.tracer(SERVICE_NAME))
+            .with_filter(LevelFilter::INFO)
+    });
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(LevelFilter::from_level(max_level)))
+        .with(otel_layer)
+        .init();
+
+    Ok(TelemetryGuard { tracer_provider })
+}
+
+fn create_tracer_provider(otlp_endpoint: &str) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!(
+            "{}/{OTLP_TRACES_PATH}",
+            otlp_endpoint.trim_end_matches('/')
+        ))
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// writes the w3c trace-context ('traceparent') of the given span into the headers of an
+/// upstream-request (nothing is written if spans are not exported)
+pub fn inject_trace_context(span: &Span, headers: &mut HeaderMap) {
+    let context = span.context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+/// reads the w3c trace-context a caller may have sent along with its request
+pub fn extract_trace_context(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+/// the trace-id of the given span (none if spans are not exported)
+pub fn trace_id(span: &Span) -> Option<String> {
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let Ok(name) = HeaderName::from_bytes(key.as_bytes())
+            && let Ok(value) = HeaderValue::from_str(&value)
+        {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{
+        Router,
+        body::Bytes,
+        http::{HeaderMap as Headers, StatusCode},
+        routing::post,
+    };
+    use tokio::sync::mpsc;
+    use tracing::info_span;
+
+    // a minimal stand-in for an otlp-collector which just hands over every received export
+    async fn start_collector() -> (String, mpsc::UnboundedReceiver<(Headers, Bytes)>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let router = Router::new().route(
+            "/v1/traces",
+            post(move |headers: Headers, body: Bytes| async move {
+                sender.send((headers, body)).unwrap();
+                StatusCode::OK
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (endpoint, receiver)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn spans_are_exported_and_propagated_as_traceparent() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let (endpoint, mut receiver) = start_collector().await;
+        let tracer_provider = create_tracer_provider(&endpoint).unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(SERVICE_NAME)));
+
+        let (exported_trace_id, headers) = tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("http_request");
+            let mut headers = HeaderMap::new();
+            inject_trace_context(&span, &mut headers);
+            (trace_id(&span).expect("span is exported"), headers)
+        });
+
+        let traceparent = headers["traceparent"].to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{exported_trace_id}-")));
+        assert_eq!(
+            extract_trace_context(&headers)
+                .span()
+                .span_context()
+                .trace_id()
+                .to_string(),
+            exported_trace_id
+        );
+
+        tokio::task::spawn_blocking(move || tracer_provider.shutdown())
+            .await
+            .unwrap()
+            .unwrap();
+        let (headers, body) = receiver.recv().await.expect("collector received spans");
+        assert_eq!(headers["content-type"], "application/x-protobuf");
+        let trace_id_bytes = u128::from_str_radix(&exported_trace_id, 16)
+            .unwrap()
+            .to_be_bytes();
+        assert!(body.windows(16).any(|w| w == trace_id_bytes));
+    }
+}
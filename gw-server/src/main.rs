@@ -3,25 +3,44 @@ use axum_server::tls_rustls::RustlsConfig;
 use gw_server::{
     application,
     domain::{
+        model::{
+            AuditConfig, EMBEDDINGMODEL_BACKEND, KeepAlive, LANGUAGEMODEL_BACKEND,
+            MonitoredBackend, REQUESTED_BY_SHUTDOWN,
+        },
         ports::{
-            ModelManagerServiceInPort, ModelsServiceInPort, OpenAiRequestForwardPServiceInPort,
+            AuditServiceInPort, HealthServiceInPort, IdleUnloadServiceInPort, MetricsServiceInPort,
+            ModelCatalogServiceInPort, ModelManagerServiceInPort, ModelsServiceInPort,
+            OpenAiRequestForwardPServiceInPort,
         },
         service::{
-            DefaultModelsService, InferenceBackendModelManagerService,
-            OpenAiClientRequestForwardService,
+            DefaultAuditService, DefaultHealthService, DefaultIdleUnloadService,
+            DefaultMetricsService, DefaultModelCatalogService, DefaultModelsService,
+            InferenceBackendModelManagerService, OpenAiClientRequestForwardService,
         },
     },
-    infrastructure::adapter::{
-        LlamaCppControllerAdapter, LocalLlamaCppClientAdapter, StaticModelLoader,
+    infrastructure::{
+        adapter::{
+            AuditLogRotation, JsonlAuditLogAdapter, LlamaCppControllerAdapter,
+            LocalLlamaCppClientAdapter, PrometheusMetricsAdapter, StaticModelLoader,
+        },
+        telemetry,
     },
-    model::{ApplicationConfig, SecurityConfig},
+    model::{ADMIN_APIKEY_NAME, APIKEY_NAME, ApplicationConfig, NO_APIKEY_NAME, SecurityConfig},
+};
+use inference_backends::{
+    LlamaCppProcessState, RemoteAgent, ResourceLimits, RestartPolicy, StopStrategy,
 };
 use rand::Rng;
 use rustls::pki_types::{IpAddr, Ipv4Addr};
 use std::{
-    borrow::Cow, collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use tracing::{Level, info};
+use tracing::{Level, info, warn};
 
 //mod application;
 //mod domain;
@@ -31,13 +50,20 @@ use tracing::{Level, info};
 //pub(crate) use model::{ApplicationConfig, SecurityConfig};
 
 const MAISERVER_LOG_KEY: &str = "MAISERVER_LOG";
+const MAISERVER_OTLP_ENDPOINT_KEY: &str = "MAISERVER_OTLP_ENDPOINT";
+// the token of the remote-agents (the same the agents are started with)
+const MAIAGENT_TOKEN_KEY: &str = "MAIAGENT_TOKEN";
 const RANDOM_APIKEY_LEN: u8 = 25;
-const LLAMACPP_LLM_PORT: u16 = 11440;
 const LLAMACPP_LLM_TIMEOUT_SECS: u16 = 60000;
-const LLAMACPP_EMBEDDINGS_PORT: u16 = 11441;
 //const LLAMACPP_COMMAND: &str = "./build-vulkan/bin/llama-server";
 const LLAMACPP_COMMAND: &str = "./build-rocm/bin/llama-server";
 const LLAMACPP_EXECDIR: &str = "/data0/inference/llama.cpp/";
+// time in-flight requests (e.g. streamed answers) get to finish on shutdown
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+// time a backend gets to exit on shutdown after its stop-deadline (when it is killed) passed
+const BACKEND_KILL_TIMEOUT: Duration = Duration::from_secs(5);
+// models without a keep-alive in their configuration are stopped after being idle this long
+const DEFAULT_KEEP_ALIVE_SECS: i64 = 30 * 60;
 
 struct MyAppState {
     openai_chat_completions_service: Arc<dyn OpenAiRequestForwardPServiceInPort>,
@@ -45,6 +71,11 @@ struct MyAppState {
     languagemodelmanager_service: Arc<dyn ModelManagerServiceInPort>,
     embeddingmodelmanager_service: Arc<dyn ModelManagerServiceInPort>,
     models_service: Arc<dyn ModelsServiceInPort>,
+    model_catalog_service: Arc<dyn ModelCatalogServiceInPort>,
+    health_service: Arc<dyn HealthServiceInPort>,
+    metrics_service: Arc<dyn MetricsServiceInPort>,
+    idle_unload_service: Arc<dyn IdleUnloadServiceInPort>,
+    audit_service: Option<Arc<dyn AuditServiceInPort>>,
 }
 
 impl ApplicationConfig for MyAppState {
@@ -67,10 +98,32 @@ impl ApplicationConfig for MyAppState {
     fn models_service(&self) -> Arc<dyn ModelsServiceInPort> {
         self.models_service.clone()
     }
+
+    fn model_catalog_service(&self) -> Arc<dyn ModelCatalogServiceInPort> {
+        self.model_catalog_service.clone()
+    }
+
+    fn health_service(&self) -> Arc<dyn HealthServiceInPort> {
+        self.health_service.clone()
+    }
+
+    fn metrics_service(&self) -> Arc<dyn MetricsServiceInPort> {
+        self.metrics_service.clone()
+    }
+
+    fn idle_unload_service(&self) -> Arc<dyn IdleUnloadServiceInPort> {
+        self.idle_unload_service.clone()
+    }
+
+    fn audit_service(&self) -> Option<Arc<dyn AuditServiceInPort>> {
+        self.audit_service.clone()
+    }
 }
 
 struct MySecurityConfig {
     apikey: Option<String>,
+    admin_apikey: Option<String>,
+    named_apikeys: BTreeMap<String, String>,
 }
 
 impl SecurityConfig for MySecurityConfig {
@@ -79,18 +132,37 @@ impl SecurityConfig for MySecurityConfig {
             .as_ref()
             .map(|apikey| Cow::Owned(apikey.clone()))
     }
+
+    fn get_admin_apikey(&self) -> Option<std::borrow::Cow<'_, str>> {
+        self.admin_apikey
+            .as_ref()
+            .map(|admin_apikey| Cow::Owned(admin_apikey.clone()))
+    }
+
+    fn get_named_apikeys(&self) -> &BTreeMap<String, String> {
+        &self.named_apikeys
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn create_app(
     provided_apikey: Option<String>,
+    provided_admin_apikey: Option<String>,
+    provided_named_apikeys: BTreeMap<String, String>,
     localhost: bool,
     log_request_info: bool,
-) -> Router {
-    let security_config = match provided_apikey {
-        None if localhost => Arc::new(MySecurityConfig { apikey: None }),
-        Some(apikey) => Arc::new(MySecurityConfig {
-            apikey: Some(apikey),
-        }),
+    backend_metrics: bool,
+    audit_log: Option<(PathBuf, AuditLogRotation, AuditConfig)>,
+    restart_policy: RestartPolicy,
+    stop_strategy: StopStrategy,
+    (llm_resource_limits, embedding_resource_limits): (ResourceLimits, ResourceLimits),
+    default_keep_alive: KeepAlive,
+    model_routes_file: PathBuf,
+    agents: HashMap<String, RemoteAgent>,
+) -> (Router, Vec<MonitoredBackend>) {
+    let apikey = match provided_apikey {
+        None if localhost => None,
+        Some(apikey) => Some(apikey),
         None => {
             const CHARSET: &[u8] =
                 b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
@@ -104,36 +176,64 @@ async fn create_app(
                 .collect();
 
             info!("your current api-key is '{apikey}'");
-            Arc::new(MySecurityConfig {
-                apikey: Some(apikey),
-            })
+            Some(apikey)
         }
     };
+    let security_config = Arc::new(MySecurityConfig {
+        apikey,
+        admin_apikey: provided_admin_apikey,
+        named_apikeys: provided_named_apikeys,
+    });
 
     // init adapters
-    let llamacpp_llm_client =
-        LocalLlamaCppClientAdapter::create_adapter(LLAMACPP_LLM_PORT, security_config.clone());
-    let llamacpp_embeddings_client = LocalLlamaCppClientAdapter::create_adapter(
-        LLAMACPP_EMBEDDINGS_PORT,
-        security_config.clone(),
-    );
+    let metrics = PrometheusMetricsAdapter::create_adapter();
     let llamacpp_llm_backend_controller = LlamaCppControllerAdapter::create_adapter(
-        LLAMACPP_LLM_PORT,
+        LANGUAGEMODEL_BACKEND,
         Some(LLAMACPP_LLM_TIMEOUT_SECS),
         LLAMACPP_COMMAND,
         LLAMACPP_EXECDIR,
+        backend_metrics,
+        restart_policy.clone(),
+        stop_strategy.clone(),
+        llm_resource_limits,
+        agents.clone(),
     )
     .await;
     let llamacpp_embeddings_backend_controller = LlamaCppControllerAdapter::create_adapter(
-        LLAMACPP_EMBEDDINGS_PORT,
+        EMBEDDINGMODEL_BACKEND,
         None,
         LLAMACPP_COMMAND,
         LLAMACPP_EXECDIR,
+        backend_metrics,
+        restart_policy.clone(),
+        stop_strategy.clone(),
+        embedding_resource_limits,
+        agents,
     )
     .await;
+    let llamacpp_llm_client = LocalLlamaCppClientAdapter::create_adapter(
+        LANGUAGEMODEL_BACKEND,
+        llamacpp_llm_backend_controller.clone(),
+        security_config.clone(),
+        metrics.clone(),
+    );
+    let llamacpp_embeddings_client = LocalLlamaCppClientAdapter::create_adapter(
+        EMBEDDINGMODEL_BACKEND,
+        llamacpp_embeddings_backend_controller.clone(),
+        security_config.clone(),
+        metrics.clone(),
+    );
+
+    let audit_service = audit_log.map(|(path, rotation, audit_config)| {
+        info!("writing audit-log to {}", path.display());
+        let audit_log = JsonlAuditLogAdapter::create_adapter(&path, rotation)
+            .unwrap_or_else(|e| panic!("error opening audit-log {}: {e}", path.display()));
+        DefaultAuditService::create_service(audit_config, audit_log)
+    });
 
     let model_loader = StaticModelLoader::create_adapter(
         &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../staticmodelconfig/static_config_files"),
+        &model_routes_file,
         security_config.clone(),
     )
     .unwrap();
@@ -150,6 +250,33 @@ async fn create_app(
         environment_args
     };
 
+    let monitored_backends = vec![
+        MonitoredBackend {
+            name: LANGUAGEMODEL_BACKEND.into(),
+            controller: llamacpp_llm_backend_controller.clone(),
+            client: llamacpp_llm_client.clone(),
+        },
+        MonitoredBackend {
+            name: EMBEDDINGMODEL_BACKEND.into(),
+            controller: llamacpp_embeddings_backend_controller.clone(),
+            client: llamacpp_embeddings_client.clone(),
+        },
+    ];
+    let health_service = DefaultHealthService::create_service(monitored_backends.clone());
+    let metrics_service = DefaultMetricsService::create_service(
+        metrics.clone(),
+        monitored_backends.clone(),
+        backend_metrics,
+    );
+    let idle_unload_service = DefaultIdleUnloadService::create_service(
+        monitored_backends.clone(),
+        model_loader.clone(),
+        default_keep_alive,
+    );
+    let model_catalog_service = DefaultModelCatalogService::create_service(
+        model_loader.clone(),
+        monitored_backends.clone(),
+    );
     let openai_chat_completions_service =
         OpenAiClientRequestForwardService::create_service(llamacpp_llm_client);
     let openai_embeddings_service =
@@ -162,6 +289,7 @@ async fn create_app(
         number_of_llamacpp_threads,
         number_of_llamacpp_batch_threads,
         environment_args,
+        metrics,
     );
 
     {
@@ -170,8 +298,8 @@ async fn create_app(
         models_service
             .ensure_requested_embeddingmodel_is_served(&default_model, Duration::from_millis(60000))
             .await
-            .unwrap_or_else(|_| {
-                panic!("error starting default embedding-model ('{default_model}')")
+            .unwrap_or_else(|e| {
+                panic!("error starting default embedding-model ('{default_model}'): {e}")
             });
     }
 
@@ -187,6 +315,11 @@ async fn create_app(
         languagemodelmanager_service,
         embeddingmodelmanager_service,
         models_service,
+        model_catalog_service,
+        health_service,
+        metrics_service,
+        idle_unload_service,
+        audit_service,
     });
 
     let router = Router::new()
@@ -194,39 +327,168 @@ async fn create_app(
             config.clone(),
             security_config.clone(),
         ))
-        .merge(application::model_manager_router(config, security_config));
+        .merge(application::model_manager_router(
+            config.clone(),
+            security_config.clone(),
+        ))
+        .merge(application::model_catalog_router(
+            config.clone(),
+            security_config.clone(),
+        ))
+        .merge(application::dashboard_router(
+            config.clone(),
+            security_config.clone(),
+        ))
+        .merge(application::metrics_router(config.clone(), security_config))
+        .merge(application::health_router(config.clone()))
+        .layer(axum::middleware::from_fn_with_state(
+            config.clone() as Arc<dyn ApplicationConfig>,
+            application::middleware::track_request_metrics,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            config as Arc<dyn ApplicationConfig>,
+            application::middleware::audit_request,
+        ))
+        .layer(axum::middleware::from_fn(
+            application::middleware::trace_request,
+        ));
 
-    if log_request_info {
+    let router = if log_request_info {
         router.layer(axum::middleware::from_fn(
             application::middleware::request_logger,
         ))
     } else {
         router
+    };
+    (router, monitored_backends)
+}
+
+/// resolves on ctrl-c or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install ctrl-c handler")
+    };
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await
+    };
+    tokio::select! {
+        _ = ctrl_c => info!("received ctrl-c"),
+        _ = terminate => info!("received SIGTERM"),
+    }
+}
+
+/// stops the backends one after another, each waiting until its process exited (or was killed)
+async fn stop_backends(backends: Vec<MonitoredBackend>) {
+    for backend in backends {
+        if matches!(
+            backend.controller.get_llamacpp_state().await,
+            LlamaCppProcessState::Stopped | LlamaCppProcessState::Failed(_, _)
+        ) {
+            continue;
+        }
+        info!("stopping backend '{}'", backend.name);
+        backend
+            .controller
+            .stop_llamacpp_process(REQUESTED_BY_SHUTDOWN)
+            .await;
+
+        let stop_timeout = match backend.controller.get_llamacpp_state().await {
+            LlamaCppProcessState::Stopping(_, _, deadline) => {
+                deadline.saturating_duration_since(Instant::now()) + BACKEND_KILL_TIMEOUT
+            }
+            _ => BACKEND_KILL_TIMEOUT,
+        };
+        let stopped = tokio::time::timeout(stop_timeout, async {
+            while !matches!(
+                backend.controller.get_llamacpp_state().await,
+                LlamaCppProcessState::Stopped | LlamaCppProcessState::Failed(_, _)
+            ) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await;
+        match stopped {
+            Ok(()) => info!("backend '{}' stopped", backend.name),
+            Err(_) => warn!(
+                "backend '{}' did not stop within {}s",
+                backend.name,
+                stop_timeout.as_secs()
+            ),
+        }
     }
 }
 
+/// reports an invalid resource-limit on the command-line and exits
+fn resource_limit_usage_error(message: &str) -> ! {
+    eprintln!("error: {message}");
+    eprintln!(
+        "usage: --llm-limit|--embedding-limit <key>=<value> (keys: nice, cpus, max-memory-mb, max-open-files, cgroup)"
+    );
+    std::process::exit(2)
+}
+
 #[tokio::main]
 async fn main() {
-    if let Ok(max_log_level) = std::env::var(MAISERVER_LOG_KEY) {
-        let max_level = match max_log_level.to_lowercase().as_str() {
+    let max_level = if let Ok(max_log_level) = std::env::var(MAISERVER_LOG_KEY) {
+        match max_log_level.to_lowercase().as_str() {
             "info" => Level::INFO,
             "trace" => Level::TRACE,
             "debug" => Level::DEBUG,
             "error" => Level::ERROR,
             "warn" => Level::WARN,
             _ => Level::ERROR,
-        };
-        tracing_subscriber::fmt().with_max_level(max_level).init();
+        }
     } else {
-        tracing_subscriber::fmt().init();
-    }
+        Level::INFO
+    };
+    let otlp_endpoint = std::env::var(MAISERVER_OTLP_ENDPOINT_KEY).ok();
+    let _telemetry_guard = telemetry::init_tracing(max_level, otlp_endpoint.as_deref())
+        .unwrap_or_else(|e| panic!("error setting up otlp-export: {e}"));
 
     let mut args = std::env::args();
-    let (host, port, tls, provided_api_key, provided_log_request_info, _provided_llama_cpp_chatui) = {
+    let (
+        host,
+        port,
+        tls,
+        provided_api_key,
+        provided_admin_api_key,
+        provided_named_api_keys,
+        provided_log_request_info,
+        _provided_llama_cpp_chatui,
+        provided_backend_metrics,
+        provided_audit_log,
+        provided_restart_policy,
+        provided_stop_strategy,
+        provided_resource_limits,
+        provided_keep_alive,
+        provided_shutdown_timeout,
+        provided_model_routes_file,
+        provided_agents,
+    ) = {
         let mut port = None;
         let mut api_key = None;
+        let mut admin_api_key = None;
+        let mut named_api_keys = BTreeMap::new();
         let mut log_request_info = false;
         let mut llama_cpp_chatui = false;
+        let mut backend_metrics = false;
+        let mut audit_log_path = None;
+        let mut audit_log_rotation = AuditLogRotation::default();
+        let mut audit_config = AuditConfig::default();
+        let mut restart_policy = RestartPolicy::on_failure();
+        let mut stop_strategy = StopStrategy::default();
+        let mut llm_resource_limits = ResourceLimits::default();
+        let mut embedding_resource_limits = ResourceLimits::default();
+        let mut keep_alive = KeepAlive::from_secs(DEFAULT_KEEP_ALIVE_SECS);
+        let mut shutdown_timeout = Duration::from_secs(DEFAULT_SHUTDOWN_TIMEOUT_SECS);
+        let mut model_routes_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../staticmodelconfig/model_routes.json");
+        let mut agent_addresses: Vec<(String, String, u16)> = Vec::new();
         let mut no_https = false;
         let mut override_host = None;
         while let Some(a) = args.next() {
@@ -254,6 +516,36 @@ async fn main() {
                 panic!("you must not provide \"-k\" (\"--api-key\") more than once")
             }
 
+            if a == "--admin-key" {
+                match args.next() {
+                    Some(admin_api_key_value) if !admin_api_key_value.is_empty() => {
+                        admin_api_key = Some(admin_api_key_value)
+                    }
+                    _ => panic!("no value for \"--admin-key\" provided"),
+                }
+            }
+
+            if a == "--named-key" {
+                match args.next().as_deref().and_then(|named_key_value| {
+                    let (name, key) = named_key_value.split_once('=')?;
+                    let valid_name = !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+                        && ![APIKEY_NAME, ADMIN_APIKEY_NAME, NO_APIKEY_NAME].contains(&name);
+                    (valid_name && !key.is_empty()).then(|| (name.to_owned(), key.to_owned()))
+                }) {
+                    Some((name, key)) => {
+                        if named_api_keys.insert(name.clone(), key).is_some() {
+                            panic!("you must not provide the named key \"{name}\" more than once")
+                        }
+                    }
+                    None => panic!(
+                        "\"--named-key\" expects \"<name>=<key>\" (the name of letters, digits, '-' and '_', other than \"{APIKEY_NAME}\", \"{ADMIN_APIKEY_NAME}\" and \"{NO_APIKEY_NAME}\")"
+                    ),
+                }
+            }
+
             if override_host.is_none() && a == "--override-host-ip" || a == "-H" {
                 if let Some(override_host_val) = args.next() {
                     override_host = match IpAddr::try_from(override_host_val.as_str()) {
@@ -280,6 +572,123 @@ async fn main() {
             if a == "--no-https" {
                 no_https = true;
             }
+
+            if a == "--backend-metrics" {
+                backend_metrics = true;
+            }
+
+            if a == "--audit-log" {
+                if let Some(audit_log_value) = args.next() {
+                    audit_log_path = Some(PathBuf::from(audit_log_value));
+                } else {
+                    panic!("no value for \"--audit-log\" provided")
+                }
+            }
+
+            if a == "--audit-capture" {
+                match args.next().as_deref() {
+                    Some("request") => audit_config.capture_request_bodies = true,
+                    Some("response") => audit_config.capture_response_bodies = true,
+                    Some("all") => {
+                        audit_config.capture_request_bodies = true;
+                        audit_config.capture_response_bodies = true;
+                    }
+                    _ => panic!(
+                        "\"--audit-capture\" expects one of \"request\", \"response\" or \"all\""
+                    ),
+                }
+            }
+
+            if a == "--audit-redact-keys" {
+                if let Some(keys_value) = args.next() {
+                    audit_config
+                        .redacted_keys
+                        .extend(keys_value.split(',').map(|k| k.trim().to_owned()));
+                } else {
+                    panic!("no value for \"--audit-redact-keys\" provided")
+                }
+            }
+
+            if a == "--audit-keep-images" {
+                audit_config.redact_images = false;
+            }
+
+            if a == "--audit-max-size-mb" {
+                match args.next().map(|v| v.parse::<u64>()) {
+                    Some(Ok(max_size_mb)) => {
+                        audit_log_rotation.max_file_size = Some(max_size_mb * 1024 * 1024)
+                    }
+                    _ => panic!("invalid or no value for \"--audit-max-size-mb\" provided"),
+                }
+            }
+
+            if a == "--audit-rotate-daily" {
+                audit_log_rotation.daily = true;
+            }
+
+            if a == "--no-restart" {
+                restart_policy = RestartPolicy::Never;
+            }
+
+            if a == "--stop-grace-period" {
+                match args.next().map(|v| v.parse::<u64>()) {
+                    Some(Ok(grace_period_secs)) => {
+                        stop_strategy.grace_period = Duration::from_secs(grace_period_secs)
+                    }
+                    _ => panic!("invalid or no value for \"--stop-grace-period\" provided"),
+                }
+            }
+
+            if a == "--llm-limit" || a == "--embedding-limit" {
+                let resource_limits = if a == "--llm-limit" {
+                    &mut llm_resource_limits
+                } else {
+                    &mut embedding_resource_limits
+                };
+                match args.next() {
+                    Some(setting) => resource_limits.set(&setting).unwrap_or_else(|e| {
+                        resource_limit_usage_error(&format!(
+                            "invalid value for \"{a}\" provided: {e}"
+                        ))
+                    }),
+                    None => resource_limit_usage_error(&format!("no value for \"{a}\" provided")),
+                }
+            }
+
+            if a == "--keep-alive" {
+                match args.next().map(|v| v.parse::<i64>()) {
+                    Some(Ok(keep_alive_secs)) => keep_alive = KeepAlive::from_secs(keep_alive_secs),
+                    _ => panic!("invalid or no value for \"--keep-alive\" provided"),
+                }
+            }
+
+            if a == "--model-routes" {
+                if let Some(model_routes_value) = args.next() {
+                    model_routes_file = PathBuf::from(model_routes_value);
+                } else {
+                    panic!("no value for \"--model-routes\" provided")
+                }
+            }
+
+            if a == "--agent" {
+                match args.next().as_deref().and_then(|agent_value| {
+                    let (name, address) = agent_value.split_once('=')?;
+                    let (host, port) = address.rsplit_once(':')?;
+                    Some((name.to_owned(), host.to_owned(), port.parse::<u16>().ok()?))
+                }) {
+                    Some(agent_address) => agent_addresses.push(agent_address),
+                    None => panic!("\"--agent\" expects \"<name>=<host>:<port>\""),
+                }
+            }
+
+            if a == "--shutdown-timeout" {
+                match args.next().map(|v| v.parse::<u64>()) {
+                    Some(Ok(shutdown_timeout_secs)) => {
+                        shutdown_timeout = Duration::from_secs(shutdown_timeout_secs)
+                    }
+                    _ => panic!("invalid or no value for \"--shutdown-timeout\" provided"),
+                }
+            }
         }
         let port = match port {
             Some(p) => p,
@@ -287,20 +696,63 @@ async fn main() {
             None => 8443,
         };
         let host = override_host.unwrap_or(IpAddr::V4(Ipv4Addr::from([0, 0, 0, 0])));
+        // a key must identify a single name
+        let mut keys: Vec<&String> = named_api_keys
+            .values()
+            .chain(api_key.iter())
+            .chain(admin_api_key.iter())
+            .collect();
+        keys.sort();
+        if keys.windows(2).any(|pair| pair[0] == pair[1]) {
+            panic!("the named keys must differ from each other, the api-key and the admin-key")
+        }
+        let agents: HashMap<_, _> = if agent_addresses.is_empty() {
+            HashMap::new()
+        } else {
+            let token = std::env::var(MAIAGENT_TOKEN_KEY).unwrap_or_default();
+            if token.trim().is_empty() {
+                panic!("no token for the remote-agents provided (set \"{MAIAGENT_TOKEN_KEY}\")")
+            }
+            agent_addresses
+                .into_iter()
+                .map(|(name, host, port)| (name, RemoteAgent::new(host, port, token.clone())))
+                .collect()
+        };
         (
             host,
             port,
             !no_https,
             api_key,
+            admin_api_key,
+            named_api_keys,
             log_request_info,
             llama_cpp_chatui,
+            backend_metrics,
+            audit_log_path.map(|path| (path, audit_log_rotation, audit_config)),
+            restart_policy,
+            stop_strategy,
+            (llm_resource_limits, embedding_resource_limits),
+            keep_alive,
+            shutdown_timeout,
+            model_routes_file,
+            agents,
         )
     };
 
-    let app = create_app(
+    let (app, backends) = create_app(
         provided_api_key,
+        provided_admin_api_key,
+        provided_named_api_keys,
         host == IpAddr::V4(Ipv4Addr::from([127, 0, 0, 1])),
         provided_log_request_info,
+        provided_backend_metrics,
+        provided_audit_log,
+        provided_restart_policy,
+        provided_stop_strategy,
+        provided_resource_limits,
+        provided_keep_alive,
+        provided_model_routes_file,
+        provided_agents,
     )
     .await;
     let addr = SocketAddr::from((host, port));
@@ -327,19 +779,38 @@ async fn main() {
         None
     };
 
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown_signal().await;
+            info!(
+                "shutting down: no new connections are accepted, waiting up to {}s for {} open connection(s)",
+                provided_shutdown_timeout.as_secs(),
+                handle.connection_count()
+            );
+            handle.graceful_shutdown(Some(provided_shutdown_timeout));
+        }
+    });
+
     info!("server started on {addr}");
 
     if let Some(tls_config) = tls_config {
         axum_server::bind_rustls(addr, tls_config)
+            .handle(handle)
             .serve(app_service)
             .await
             .unwrap_or_else(|_| panic!("error serving via tls on {addr}"));
     } else {
         axum_server::bind(addr)
+            .handle(handle)
             .serve(app_service)
             .await
             .unwrap_or_else(|_| panic!("error serving without tls on {addr}"));
     }
 
+    info!("all connections closed");
+    stop_backends(backends).await;
+
     info!("server shutdown");
 }
@@ -1,10 +1,36 @@
 use crate::domain::ports::{
-    ModelManagerServiceInPort, ModelsServiceInPort, OpenAiRequestForwardPServiceInPort,
+    AuditServiceInPort, HealthServiceInPort, IdleUnloadServiceInPort, MetricsServiceInPort,
+    ModelCatalogServiceInPort, ModelManagerServiceInPort, ModelsServiceInPort,
+    OpenAiRequestForwardPServiceInPort,
 };
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+
+// names under which requests show up in the audit-log and select their routes (see ModelRoutes)
+pub const APIKEY_NAME: &str = "default";
+pub const ADMIN_APIKEY_NAME: &str = "admin";
+pub const NO_APIKEY_NAME: &str = "anonymous";
 
 pub trait SecurityConfig: Send + Sync + 'static {
     fn get_apikey(&self) -> Option<Cow<'_, str>>;
+
+    /// required to change the catalog (see 'check_admin_auth')
+    fn get_admin_apikey(&self) -> Option<Cow<'_, str>>;
+
+    /// further api-keys by their names
+    fn get_named_apikeys(&self) -> &BTreeMap<String, String>;
+
+    /// the names requests can be authenticated with
+    fn key_names(&self) -> Vec<String> {
+        let mut key_names = vec![match self.get_apikey() {
+            Some(_) => APIKEY_NAME.to_owned(),
+            None => NO_APIKEY_NAME.to_owned(),
+        }];
+        if self.get_admin_apikey().is_some() {
+            key_names.push(ADMIN_APIKEY_NAME.to_owned());
+        }
+        key_names.extend(self.get_named_apikeys().keys().cloned());
+        key_names
+    }
 }
 
 pub trait ApplicationConfig: Send + Sync + 'static {
@@ -13,4 +39,11 @@ pub trait ApplicationConfig: Send + Sync + 'static {
     fn languagemodelmanager_service(&self) -> Arc<dyn ModelManagerServiceInPort>;
     fn embeddingmodelmanager_service(&self) -> Arc<dyn ModelManagerServiceInPort>;
     fn models_service(&self) -> Arc<dyn ModelsServiceInPort>;
+    fn model_catalog_service(&self) -> Arc<dyn ModelCatalogServiceInPort>;
+    fn health_service(&self) -> Arc<dyn HealthServiceInPort>;
+    fn metrics_service(&self) -> Arc<dyn MetricsServiceInPort>;
+    fn idle_unload_service(&self) -> Arc<dyn IdleUnloadServiceInPort>;
+
+    /// the audit-log is opt-in
+    fn audit_service(&self) -> Option<Arc<dyn AuditServiceInPort>>;
 }
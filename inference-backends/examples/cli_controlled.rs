@@ -3,7 +3,7 @@ use std::{collections::HashMap, io::stdin, sync::Arc};
 use inference_backends::{
     AttnSetting, ComfyUiBackend, ComfyUiBackendController, ComfyUiConfig, ComfyUiConfigArgs,
     ContextSize, LlamaCppBackend, LlamaCppBackendController, LlamaCppConfigArgs, LlamaCppRunConfig,
-    OnOffAutoValue, VRamSetting,
+    OnOffAutoValue, ResourceLimits, RestartPolicy, StopStrategy, VRamSetting,
 };
 
 #[tokio::main]
@@ -14,10 +14,15 @@ async fn main() {
 
     let llamacpp_backend = LlamaCppBackend {
         host: "0.0.0.0".to_owned(),
-        port: 11440,
         timeout: None,
         llama_cpp_command: "./build/bin/llama-server".to_owned(),
         llama_cpp_execdir: "/data0/inference/llama.cpp/".to_owned(),
+        metrics: false,
+        restart_policy: RestartPolicy::on_failure(),
+        stop_strategy: StopStrategy::default(),
+        resource_limits: ResourceLimits::default(),
+        name: "llm".to_owned(),
+        agents: HashMap::new(),
     };
 
     let llama_cpp_backend_controller =
@@ -29,6 +34,8 @@ async fn main() {
         comfyui_main_py: "main.py".to_owned(),
         comfyui_setup_sh: "setup_for_normal_run.sh".to_owned(),
         comfyui_execdir: "/data0/inference/ComfyUI".to_owned(),
+        stop_strategy: StopStrategy::default(),
+        resource_limits: ResourceLimits::default(),
     };
     let comfyui_backend_controller = ComfyUiBackendController::init_backend(comfyui_backend).await;
 
@@ -74,6 +81,8 @@ async fn main() {
             reasoning_budget: None,
             embeddings: false,
             no_cache_prompt: false,
+            resource_limits: ResourceLimits::default(),
+            backend_host: None,
         }
         .into()
     };
@@ -121,6 +130,8 @@ async fn main() {
             reasoning_budget: None,
             embeddings: false,
             no_cache_prompt: false,
+            resource_limits: ResourceLimits::default(),
+            backend_host: None,
         }
         .into(),
     };
@@ -141,26 +152,31 @@ async fn main() {
         let mut cmd = String::new();
         stdin().read_line(&mut cmd).unwrap();
         match cmd.trim() {
-            "1" => llama_cpp_backend_controller.start(config_1.clone()).await,
-            "2" => llama_cpp_backend_controller.start(config_2.clone()).await,
-            "s" => llama_cpp_backend_controller.stop().await,
+            "1" => llama_cpp_backend_controller
+                .start(config_1.clone(), "cli")
+                .await
+                .unwrap(),
+            "2" => llama_cpp_backend_controller
+                .start(config_2.clone(), "cli")
+                .await
+                .unwrap(),
+            "s" => llama_cpp_backend_controller.stop("cli").await.unwrap(),
             "r" => println!(
                 "llamacpp-state: {:?}",
-                llama_cpp_backend_controller.read_state().await
+                llama_cpp_backend_controller.read_state().await.unwrap()
             ),
             "q" => {
                 println!("bye");
                 break;
             }
-            "3" => {
-                comfyui_backend_controller
-                    .start(comfyui_config.clone())
-                    .await
-            }
-            "d" => comfyui_backend_controller.stop().await,
+            "3" => comfyui_backend_controller
+                .start(comfyui_config.clone(), "cli")
+                .await
+                .unwrap(),
+            "d" => comfyui_backend_controller.stop("cli").await.unwrap(),
             "t" => println!(
                 "comfyui-state: {:?}",
-                comfyui_backend_controller.read_state().await
+                comfyui_backend_controller.read_state().await.unwrap()
             ),
             _ => {
                 println!(
@@ -1,5 +1,8 @@
-use managed_process::{BackendController, ProcessProtocol, RunBackendProcess};
-use std::process::Stdio;
+use managed_process::{
+    BackendController, Endpoint, ExitReason, LogStream, ProcessLogWriter, ProcessProtocol,
+    ReadinessCheck, ReadinessProbe, Regex, ResourceLimits, RunBackendProcess, StopStrategy,
+};
+use std::{process::Stdio, time::Duration};
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::Command,
@@ -13,27 +16,57 @@ pub use comfyuiconfig::{AttnSetting, ComfyUiConfig, ComfyUiConfigArgs, VRamSetti
 pub type ComfyUiProtocol = ProcessProtocol<ComfyUiConfig>;
 pub type ComfyUiBackendController = BackendController<ComfyUiConfig>;
 
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(300);
+
 pub struct ComfyUiBackend {
     pub listen: String,
     pub port: u16,
     pub comfyui_setup_sh: String,
     pub comfyui_main_py: String,
     pub comfyui_execdir: String,
+    pub stop_strategy: StopStrategy,
+    pub resource_limits: ResourceLimits,
 }
 
 impl RunBackendProcess for ComfyUiBackend {
     type ProcessConfig = ComfyUiConfig;
 
+    fn readiness_probe(
+        &self,
+        _process_config: &ComfyUiConfig,
+        _endpoint: Option<&Endpoint>,
+    ) -> Option<ReadinessProbe> {
+        Some(ReadinessProbe::new(
+            ReadinessCheck::All(vec![
+                ReadinessCheck::LogLine(
+                    Regex::new("To see the GUI go to: http").expect("regex must be valid"),
+                ),
+                ReadinessCheck::TcpConnect {
+                    host: self.listen.clone(),
+                    port: self.port,
+                },
+            ]),
+            STARTUP_TIMEOUT,
+        ))
+    }
+
+    fn stop_strategy(&self) -> StopStrategy {
+        self.stop_strategy.clone()
+    }
+
     fn run_backend_process(
         &self,
         process_config: Self::ProcessConfig,
         cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
         notifier: tokio::sync::mpsc::Sender<ProcessProtocol<Self::ProcessConfig>>,
-    ) {
+        log: ProcessLogWriter,
+    ) -> managed_process::Result<Option<Endpoint>> {
         // run normal-setup
         let mut cmd = std::process::Command::new(format!("./{}", self.comfyui_setup_sh).as_str());
         cmd.current_dir(&self.comfyui_execdir);
-        cmd.spawn().unwrap().wait().unwrap();
+        cmd.spawn()
+            .and_then(|mut setup| setup.wait())
+            .map_err(managed_process::Error::SpawnFailed)?;
 
         let mut cmd = Command::new(".venv/bin/python3");
         cmd.current_dir(&self.comfyui_execdir);
@@ -55,44 +88,53 @@ impl RunBackendProcess for ComfyUiBackend {
 
         cmd.kill_on_drop(true);
 
+        self.resource_limits
+            .apply(&mut cmd)
+            .map_err(managed_process::Error::SpawnFailed)?;
+
         // spawn process
-        let mut proc_handle = cmd.spawn().unwrap();
+        let mut proc_handle = cmd.spawn().map_err(managed_process::Error::SpawnFailed)?;
 
         // spawn std-err observing task
         let stderr = proc_handle.stderr.take().unwrap();
-        let notifier_cloned = notifier.clone();
         spawn(async move {
             let mut lines = BufReader::new(stderr).lines();
-            loop {
-                if let Ok(Some(line)) = lines.next_line().await
-                    && line.contains("To see the GUI go to: http")
-                {
-                    notifier_cloned
-                        .send(ComfyUiProtocol::ProcessStarted)
-                        .await
-                        .unwrap();
-                }
+            while let Ok(Some(line)) = lines.next_line().await {
+                log.push(LogStream::Stderr, line);
             }
         });
 
+        let stop_strategy = self.stop_strategy.clone();
         spawn(async move {
             tokio::select! {
                 s = proc_handle.wait() => {
-                    let exit_status = s.unwrap();
-                    if exit_status.success() {
-                        info!("comfyui-process ended successfully");
-                        notifier.send(ComfyUiProtocol::ProcessFinished(None)).await.unwrap();
-                    } else {
-                        error!("comfyui-process ended unsuccessfully with error exit_status {exit_status}");
-                        notifier.send(ComfyUiProtocol::ProcessFinished(Some(exit_status))).await.unwrap();
-                    }
+                    let exit_reason = match s {
+                        Ok(exit_status) if exit_status.success() => {
+                            info!("comfyui-process ended successfully");
+                            ExitReason::Exited(exit_status)
+                        }
+                        Ok(exit_status) => {
+                            error!("comfyui-process ended unsuccessfully with error exit_status {exit_status}");
+                            ExitReason::Exited(exit_status)
+                        }
+                        Err(e) => {
+                            error!("error waiting for comfyui-process: {e}");
+                            ExitReason::Unknown
+                        }
+                    };
+                    // the controller may be gone already
+                    let _ = notifier.send(ComfyUiProtocol::ProcessFinished(exit_reason)).await;
                 },
                 _ = cancel_receiver => {
-                    info!("killing comfyui-process");
-                    proc_handle.kill().await.unwrap();
-                    notifier.send(ComfyUiProtocol::ProcessFinished(None)).await.unwrap();
+                    let exit_reason = stop_strategy.stop(&mut proc_handle, "comfyui").await;
+                    let _ = notifier.send(ComfyUiProtocol::ProcessFinished(exit_reason)).await;
                 }
             }
         });
+
+        Ok(Some(Endpoint {
+            host: self.listen.clone(),
+            port: self.port,
+        }))
     }
 }
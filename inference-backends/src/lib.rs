@@ -11,4 +11,11 @@ pub use llamacpp::{
     OnOffAutoValue,
 };
 
+pub use managed_process::{
+    Endpoint, ExitReason, LogLine, LogStream, ProcessLog, RemoteAgent, ResourceLimits,
+    RestartPolicy, StopStrategy, TransitionCause,
+};
+
 pub type LlamaCppProcessState = managed_process::ProcessState<LlamaCppRunConfig>;
+pub type LlamaCppProcessHistory = managed_process::ProcessHistory<LlamaCppRunConfig>;
+pub type LlamaCppTransition = managed_process::Transition<LlamaCppRunConfig>;
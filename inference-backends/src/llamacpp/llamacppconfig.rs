@@ -1,3 +1,4 @@
+use managed_process::ResourceLimits;
 use serde::{Deserialize, Serialize, de::Visitor};
 use std::{collections::HashMap, sync::Arc};
 use tokio::process::Command;
@@ -67,6 +68,7 @@ impl From<u64> for ContextSize {
             32768 => Self::T32768,
             65536 => Self::T65536,
             131072 => Self::T131072,
+            262144 => Self::T262144,
             other => Self::Custom(other),
         }
     }
@@ -246,7 +248,7 @@ impl Serialize for ContextSize {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct LlamaCppConfigArgs {
     pub alias: String,
     pub api_key: Option<String>,
@@ -287,6 +289,11 @@ pub struct LlamaCppConfigArgs {
     pub reasoning_budget: Option<i16>,
     pub embeddings: bool,
     pub no_cache_prompt: bool,
+    // override the limits of the backend
+    pub resource_limits: ResourceLimits,
+    // the remote-agent the model is run at (see 'LlamaCppBackend::agents') ; the paths of the
+    // model are paths on its host
+    pub backend_host: Option<String>,
 }
 
 impl LlamaCppConfigArgs {
@@ -1,9 +1,15 @@
-use managed_process::{BackendController, ProcessProtocol, RunBackendProcess};
-use std::process::Stdio;
+use managed_process::{
+    BackendController, CommandConfig, Endpoint, ExitReason, LogStream, ProcessLogWriter,
+    ProcessProtocol, ReadinessCheck, ReadinessProbe, Regex, RemoteAgent, RemoteBackend,
+    ResourceLimits, RestartPolicy, RunBackendProcess, StopStrategy,
+};
+use rand::Rng;
+use std::{collections::HashMap, process::Stdio, time::Duration};
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::Command,
     spawn,
+    time::timeout,
 };
 use tracing::{error, info};
 
@@ -13,23 +19,96 @@ pub use llamacppconfig::{ContextSize, LlamaCppConfigArgs, LlamaCppRunConfig, OnO
 pub type LlamaCppProtocol = ProcessProtocol<LlamaCppRunConfig>;
 pub type LlamaCppBackendController = BackendController<LlamaCppRunConfig>;
 
+// loading large models from slow disks takes a while
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(600);
+// how long the lines llama-server printed before it ended may take to be logged
+const LAST_LINES_TIMEOUT: Duration = Duration::from_secs(1);
+// llama-server run at a remote-agent listens on a port of this range (on all interfaces)
+const REMOTE_PORTS: std::ops::Range<u16> = 20000..60000;
+const REMOTE_LISTEN_HOST: &str = "0.0.0.0";
+
 pub struct LlamaCppBackend {
+    // llama-server listens on a free port of this host (kept as endpoint in the process-state)
     pub host: String,
-    pub port: u16,
     pub timeout: Option<u16>,
     pub llama_cpp_command: String,
     pub llama_cpp_execdir: String,
+    pub metrics: bool,
+    pub restart_policy: RestartPolicy,
+    pub stop_strategy: StopStrategy,
+    // applied to every process unless the config of the model overrides them
+    pub resource_limits: ResourceLimits,
+    // the name of the processes at the remote-agents (e.g. the name of the backend)
+    pub name: String,
+    // the remote-agents models may be run at by their names (see 'backend_host' of the model)
+    pub agents: HashMap<String, RemoteAgent>,
 }
 
 impl RunBackendProcess for LlamaCppBackend {
     type ProcessConfig = LlamaCppRunConfig;
 
+    fn restart_policy(&self) -> RestartPolicy {
+        self.restart_policy.clone()
+    }
+
+    // remote processes are stopped by their agent (expected to use the same grace-period),
+    // which may take a while to confirm it
+    fn stop_strategy(&self) -> StopStrategy {
+        if self.agents.is_empty() {
+            self.stop_strategy.clone()
+        } else {
+            StopStrategy {
+                grace_period: self.stop_strategy.grace_period + RemoteAgent::TIMEOUT,
+                ..self.stop_strategy.clone()
+            }
+        }
+    }
+
+    // llama-server answers '/health' with 503 while the model is still loading
+    fn readiness_probe(
+        &self,
+        _process_config: &LlamaCppRunConfig,
+        endpoint: Option<&Endpoint>,
+    ) -> Option<ReadinessProbe> {
+        let Endpoint { host, port } = endpoint?.clone();
+        Some(ReadinessProbe::new(
+            ReadinessCheck::All(vec![
+                ReadinessCheck::LogLine(
+                    Regex::new("listening on http").expect("regex must be valid"),
+                ),
+                ReadinessCheck::HttpGet {
+                    host,
+                    port,
+                    path: "/health".to_owned(),
+                    expected_status: 200,
+                },
+            ]),
+            STARTUP_TIMEOUT,
+        ))
+    }
+
+    // the free port may be taken by someone else before llama-server binds it (the port of a
+    // remote process is picked at random)
+    fn respawn_on(&self) -> Option<Regex> {
+        Some(Regex::new("couldn't bind|[Aa]ddress already in use").expect("regex must be valid"))
+    }
+
     fn run_backend_process(
         &self,
         process_config: Self::ProcessConfig,
         cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
         notifier: tokio::sync::mpsc::Sender<ProcessProtocol<Self::ProcessConfig>>,
-    ) {
+        log: ProcessLogWriter,
+    ) -> managed_process::Result<Option<Endpoint>> {
+        let remote_agent = match &process_config.args_handle.backend_host {
+            Some(backend_host) => Some(self.agents.get(backend_host).ok_or_else(|| {
+                managed_process::Error::SpawnFailed(std::io::Error::other(format!(
+                    "no remote-agent '{backend_host}' configured"
+                )))
+            })?),
+            None => None,
+        };
+
         // prepare llama-cpp-command:
         let mut cmd = Command::new(&self.llama_cpp_command);
         cmd.current_dir(&self.llama_cpp_execdir);
@@ -38,11 +117,24 @@ impl RunBackendProcess for LlamaCppBackend {
         process_config.apply_env(&mut cmd);
 
         // set params
+        let endpoint = match remote_agent {
+            Some(remote_agent) => Endpoint {
+                host: remote_agent.host.clone(),
+                port: rand::rng().random_range(REMOTE_PORTS),
+            },
+            None => Endpoint {
+                host: self.host.clone(),
+                port: free_port(&self.host).map_err(managed_process::Error::SpawnFailed)?,
+            },
+        };
         cmd.arg("--host");
-        cmd.arg(&self.host);
-
+        cmd.arg(if remote_agent.is_some() {
+            REMOTE_LISTEN_HOST
+        } else {
+            &self.host
+        });
         cmd.arg("--port");
-        cmd.arg(self.port.to_string());
+        cmd.arg(endpoint.port.to_string());
 
         cmd.arg("--threads-http");
         cmd.arg(1.to_string());
@@ -52,64 +144,223 @@ impl RunBackendProcess for LlamaCppBackend {
             cmd.arg(timeout.to_string());
         }
 
+        if self.metrics {
+            cmd.arg("--metrics");
+        }
+
         process_config.apply_args(&mut cmd);
 
+        // the resource-limits of a remote process are the ones of its agent
+        if let Some(remote_agent) = remote_agent {
+            let readiness_probe = self.readiness_probe(&process_config, Some(&endpoint));
+            RemoteBackend::new(
+                remote_agent.clone(),
+                &self.name,
+                self.restart_policy.clone(),
+                readiness_probe,
+                self.stop_strategy.clone(),
+            )
+            .run_command(command_config(&cmd), cancel_receiver, notifier, log);
+            return Ok(Some(endpoint));
+        }
+
         // provide std-streams
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
         cmd.kill_on_drop(true);
 
+        process_config
+            .args_handle
+            .resource_limits
+            .clone()
+            .or(&self.resource_limits)
+            .apply(&mut cmd)
+            .map_err(managed_process::Error::SpawnFailed)?;
+
         // spawn process
-        let mut proc_handle = cmd.spawn().unwrap();
+        let mut proc_handle = cmd.spawn().map_err(managed_process::Error::SpawnFailed)?;
 
-        // spawn std-out observing task
+        // spawn std-out observing task (ends with the process closing the stream)
         let stdout = proc_handle.stdout.take().unwrap();
-        spawn(async move {
+        let stdout_log = log.clone();
+        let stdout_task = spawn(async move {
             let mut outlines = BufReader::new(stdout).lines();
-            loop {
-                if let Ok(Some(outline)) = outlines.next_line().await {
-                    info!("llama-server [stdout]: {outline}");
-                }
+            while let Ok(Some(outline)) = outlines.next_line().await {
+                info!("llama-server [stdout]: {outline}");
+                stdout_log.push(LogStream::Stdout, outline);
             }
         });
 
-        // spawn std-err observing task
+        // spawn std-err observing task (ends with the process closing the stream)
         let stderr = proc_handle.stderr.take().unwrap();
-        let notifier_cloned = notifier.clone();
-        spawn(async move {
+        let stderr_task = spawn(async move {
             let mut errlines = BufReader::new(stderr).lines();
-            loop {
-                if let Ok(Some(errline)) = errlines.next_line().await {
-                    info!("llama-server [stderr]: {errline}");
-                    if errline.contains("listening on http") {
-                        notifier_cloned
-                            .send(LlamaCppProtocol::ProcessStarted)
-                            .await
-                            .unwrap();
-                    }
-                }
+            while let Ok(Some(errline)) = errlines.next_line().await {
+                info!("llama-server [stderr]: {errline}");
+                log.push(LogStream::Stderr, errline);
             }
         });
 
+        let stop_strategy = self.stop_strategy.clone();
         spawn(async move {
             tokio::select! {
                 s = proc_handle.wait() => {
-                    let exit_status = s.unwrap();
-                    if exit_status.success() {
-                        info!("llama-cpp-process ended successfully");
-                        notifier.send(LlamaCppProtocol::ProcessFinished(None)).await.unwrap();
-                    } else {
-                        error!("llama-cpp-process ended unsuccessfully with error exit_status {exit_status}");
-                        notifier.send(LlamaCppProtocol::ProcessFinished(Some(exit_status))).await.unwrap();
+                    let exit_reason = match s {
+                        Ok(exit_status) if exit_status.success() => {
+                            info!("llama-cpp-process ended successfully");
+                            ExitReason::Exited(exit_status)
+                        }
+                        Ok(exit_status) => {
+                            error!("llama-cpp-process ended unsuccessfully with error exit_status {exit_status}");
+                            ExitReason::Exited(exit_status)
+                        }
+                        Err(e) => {
+                            error!("error waiting for llama-cpp-process: {e}");
+                            ExitReason::Unknown
+                        }
+                    };
+                    // the last lines tell why llama-server ended (e.g. its port was taken)
+                    for task in [stdout_task, stderr_task] {
+                        let _ = timeout(LAST_LINES_TIMEOUT, task).await;
                     }
+                    // the controller may be gone already
+                    let _ = notifier.send(LlamaCppProtocol::ProcessFinished(exit_reason)).await;
                 },
                 _ = cancel_receiver => {
-                    info!("killing llama-cpp-process");
-                    proc_handle.kill().await.unwrap();
-                    notifier.send(LlamaCppProtocol::ProcessFinished(None)).await.unwrap();
+                    let exit_reason = stop_strategy.stop(&mut proc_handle, "llama-cpp").await;
+                    let _ = notifier.send(LlamaCppProtocol::ProcessFinished(exit_reason)).await;
                 }
             }
         });
+
+        Ok(Some(endpoint))
+    }
+}
+
+// the command to be run at a remote-agent
+fn command_config(cmd: &Command) -> CommandConfig {
+    let cmd = cmd.as_std();
+    let lossy = |value: &std::ffi::OsStr| value.to_string_lossy().into_owned();
+    CommandConfig {
+        program: lossy(cmd.get_program()),
+        args: cmd.get_args().map(lossy).collect(),
+        env: cmd
+            .get_envs()
+            .filter_map(|(key, value)| Some((lossy(key), lossy(value?))))
+            .collect(),
+        working_dir: cmd.get_current_dir().map(Into::into),
+    }
+}
+
+// a port nothing listens on right now ; it is released again before llama-server binds it (which
+// cannot be handed the bound socket), so another process may take it in between: llama-server
+// then exits reporting it and is spawned again (see 'respawn_on') ; unix domain sockets (a
+// '--host' ending in '.sock') are not used as the client and the readiness-probes speak tcp only
+fn free_port(host: &str) -> std::io::Result<u16> {
+    Ok(std::net::TcpListener::bind((host, 0))?.local_addr()?.port())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    fn backend(agents: HashMap<String, RemoteAgent>) -> LlamaCppBackend {
+        LlamaCppBackend {
+            host: "localhost".to_owned(),
+            timeout: None,
+            llama_cpp_command: "./build/bin/llama-server".to_owned(),
+            llama_cpp_execdir: "/opt/llama.cpp".to_owned(),
+            metrics: false,
+            restart_policy: RestartPolicy::Never,
+            stop_strategy: StopStrategy::default(),
+            resource_limits: ResourceLimits::default(),
+            name: "llm".to_owned(),
+            agents,
+        }
+    }
+
+    fn run_config(backend_host: Option<&str>) -> LlamaCppRunConfig {
+        LlamaCppRunConfig {
+            args_handle: Arc::new(LlamaCppConfigArgs {
+                alias: "qwen".to_owned(),
+                api_key: Some("secret".to_owned()),
+                model_path: "/models/qwen.gguf".to_owned(),
+                backend_host: backend_host.map(Into::into),
+                ..Default::default()
+            }),
+            env_handle: Arc::new(HashMap::from([("HIP_VISIBLE_DEVICES".into(), "1".into())])),
+            parallel: 1,
+            threads: 8,
+            threads_batch: 8,
+        }
+    }
+
+    #[test]
+    fn the_command_of_a_remote_process_is_taken_from_the_local_one() {
+        let mut cmd = Command::new("./build/bin/llama-server");
+        cmd.current_dir("/opt/llama.cpp");
+        run_config(Some("gpu2")).apply_env(&mut cmd);
+        run_config(Some("gpu2")).apply_args(&mut cmd);
+
+        let command_config = command_config(&cmd);
+        assert_eq!(command_config.program, "./build/bin/llama-server");
+        assert_eq!(
+            command_config.working_dir,
+            Some(std::path::PathBuf::from("/opt/llama.cpp"))
+        );
+        assert_eq!(
+            command_config.env,
+            vec![("HIP_VISIBLE_DEVICES".to_owned(), "1".to_owned())]
+        );
+        assert_eq!(
+            command_config.args[..6],
+            [
+                "--alias",
+                "qwen",
+                "--model",
+                "/models/qwen.gguf",
+                "--api-key",
+                "secret"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn models_with_a_backend_host_listen_at_their_agent() {
+        let controller = LlamaCppBackendController::init_backend(backend(HashMap::from([(
+            "gpu2".to_owned(),
+            RemoteAgent::new("127.0.0.1", 1, "token"),
+        )])))
+        .await;
+
+        controller
+            .start(run_config(Some("gpu2")), "test")
+            .await
+            .unwrap();
+        match controller.read_state().await.unwrap() {
+            managed_process::ProcessState::Starting(_, Some(endpoint)) => {
+                assert_eq!(endpoint.host, "127.0.0.1");
+                assert!(REMOTE_PORTS.contains(&endpoint.port));
+            }
+            state => panic!("unexpected state {state:?}"),
+        }
+
+        // models naming an agent which is not configured are not run
+        controller
+            .start(run_config(Some("gpu3")), "test")
+            .await
+            .unwrap();
+        for _ in 0..100 {
+            if let managed_process::ProcessState::Failed(_, reason) =
+                controller.read_state().await.unwrap()
+            {
+                assert!(reason.contains("gpu3"), "{reason}");
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("the model naming an unknown agent was not reported as failed");
     }
 }
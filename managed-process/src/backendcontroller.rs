@@ -1,4 +1,7 @@
-use crate::{ProcessState, ProcessStateManager, RunBackendProcess, model::ProcessProtocol};
+use crate::{
+    Error, ExitReason, ProcessHistory, ProcessLog, ProcessState, ProcessStateManager, Result,
+    RunBackendProcess, model::ProcessProtocol,
+};
 use core::fmt::Debug as TDebug;
 use tokio::{
     spawn,
@@ -8,21 +11,15 @@ use tokio::{
     },
 };
 
-#[derive(Clone, Debug)]
-pub struct BackendController<ProcessConfig>(MpscSender<ProcessProtocol<ProcessConfig>>)
+#[derive(Clone)]
+pub struct BackendController<ProcessConfig>(
+    MpscSender<ProcessProtocol<ProcessConfig>>,
+    ProcessLog,
+    ProcessHistory<ProcessConfig>,
+)
 where
     ProcessConfig: Clone + PartialEq + TDebug + Send + 'static;
 
-impl<ProcessConfig> From<MpscSender<ProcessProtocol<ProcessConfig>>>
-    for BackendController<ProcessConfig>
-where
-    ProcessConfig: Clone + PartialEq + TDebug + Send + 'static,
-{
-    fn from(controller_sender: MpscSender<ProcessProtocol<ProcessConfig>>) -> Self {
-        Self(controller_sender)
-    }
-}
-
 impl<ProcessConfig> BackendController<ProcessConfig>
 where
     ProcessConfig: Clone + PartialEq + TDebug + Send + 'static,
@@ -37,25 +34,50 @@ where
         let mut state_manager =
             ProcessStateManager::<Backend, ProcessConfig>::new(backend, controller_sender);
 
-        let ret = BackendController::from(state_manager.controller_sender());
+        let ret = BackendController(
+            state_manager.controller_sender(),
+            state_manager.log(),
+            state_manager.history(),
+        );
         spawn(async move {
             loop {
                 match controller_receiver.recv().await {
-                    Some(ProcessProtocol::<ProcessConfig>::ProcessFinished(
-                        optional_exit_status,
-                    )) => state_manager.on_process_finished(optional_exit_status),
+                    Some(ProcessProtocol::<ProcessConfig>::ProcessFinished(exit_reason)) => {
+                        state_manager.on_process_finished(exit_reason)
+                    }
                     Some(ProcessProtocol::<ProcessConfig>::ProcessStarted) => {
                         state_manager.on_process_started()
                     }
-                    Some(ProcessProtocol::<ProcessConfig>::StartProcess(config)) => {
-                        state_manager.on_start_process(config)
+                    Some(ProcessProtocol::<ProcessConfig>::ProcessReady(generation)) => {
+                        state_manager.on_process_ready(generation)
+                    }
+                    Some(ProcessProtocol::<ProcessConfig>::ProcessNotReady(generation, reason)) => {
+                        state_manager.on_process_not_ready(generation, reason)
                     }
-                    Some(ProcessProtocol::<ProcessConfig>::StopProcess) => {
-                        state_manager.on_stop_process()
+                    Some(ProcessProtocol::<ProcessConfig>::StartProcess(
+                        config,
+                        requested_by,
+                        back_chan,
+                    )) => {
+                        let _ =
+                            back_chan.send(state_manager.on_start_process(config, requested_by));
+                    }
+                    Some(ProcessProtocol::<ProcessConfig>::StopProcess(requested_by)) => {
+                        state_manager.on_stop_process(requested_by)
                     }
                     Some(ProcessProtocol::<ProcessConfig>::ReadProcessState(back_chan)) => {
                         state_manager.on_read_config(back_chan)
                     }
+                    Some(ProcessProtocol::<ProcessConfig>::ReadLastExit(back_chan)) => {
+                        state_manager.on_read_last_exit(back_chan)
+                    }
+                    Some(ProcessProtocol::<ProcessConfig>::RestartProcess) => {
+                        state_manager.on_restart_process()
+                    }
+                    Some(ProcessProtocol::<ProcessConfig>::ResetFailures(requested_by)) => {
+                        state_manager.on_reset_failures(requested_by)
+                    }
+                    Some(ProcessProtocol::<ProcessConfig>::Shutdown) => break,
                     _ => {}
                 }
             }
@@ -63,26 +85,72 @@ where
         ret
     }
 
-    pub async fn start(&self, config: ProcessConfig) {
-        self.0
-            .send(ProcessProtocol::<ProcessConfig>::StartProcess(config))
-            .await
-            .unwrap();
+    /// 'requested_by' tells who requested the transition (recorded in the history) ; a config
+    /// which crashed repeatedly is refused until the failures are reset
+    pub async fn start(
+        &self,
+        config: ProcessConfig,
+        requested_by: impl Into<String>,
+    ) -> Result<()> {
+        let (start_sender, start_receiver) = oneshot_channel::<Result<()>>();
+        self.send(ProcessProtocol::<ProcessConfig>::StartProcess(
+            config,
+            requested_by.into(),
+            start_sender,
+        ))
+        .await?;
+        start_receiver.await.map_err(|_| Error::ControllerGone)?
     }
-    pub async fn stop(&self) {
-        self.0
-            .send(ProcessProtocol::<ProcessConfig>::StopProcess)
-            .await
-            .unwrap();
+    pub async fn stop(&self, requested_by: impl Into<String>) -> Result<()> {
+        self.send(ProcessProtocol::<ProcessConfig>::StopProcess(
+            requested_by.into(),
+        ))
+        .await
+    }
+    /// allows configs that crashed repeatedly to be started again
+    pub async fn reset_failures(&self, requested_by: impl Into<String>) -> Result<()> {
+        self.send(ProcessProtocol::<ProcessConfig>::ResetFailures(
+            requested_by.into(),
+        ))
+        .await
+    }
+    pub async fn read_state(&self) -> Result<ProcessState<ProcessConfig>> {
+        let (state_sender, state_receiver) =
+            oneshot_channel::<Result<ProcessState<ProcessConfig>>>();
+        self.send(ProcessProtocol::<ProcessConfig>::ReadProcessState(
+            state_sender,
+        ))
+        .await?;
+        state_receiver.await.map_err(|_| Error::ControllerGone)?
+    }
+
+    /// why the last process finished (none if no process finished yet)
+    pub async fn last_exit(&self) -> Result<Option<ExitReason>> {
+        let (exit_sender, exit_receiver) = oneshot_channel::<Option<ExitReason>>();
+        self.send(ProcessProtocol::<ProcessConfig>::ReadLastExit(exit_sender))
+            .await?;
+        exit_receiver.await.map_err(|_| Error::ControllerGone)
     }
-    pub async fn read_state(&self) -> ProcessState<ProcessConfig> {
-        let (state_sender, state_receiver) = oneshot_channel::<ProcessState<ProcessConfig>>();
+
+    /// the recent output of the processes run by this controller
+    pub fn log(&self) -> ProcessLog {
+        self.1.clone()
+    }
+
+    /// the recent state-transitions of this controller
+    pub fn history(&self) -> ProcessHistory<ProcessConfig> {
+        self.2.clone()
+    }
+
+    /// ends the task managing the process ; a process still running is cancelled
+    pub(crate) async fn shutdown(&self) -> Result<()> {
+        self.send(ProcessProtocol::<ProcessConfig>::Shutdown).await
+    }
+
+    async fn send(&self, message: ProcessProtocol<ProcessConfig>) -> Result<()> {
         self.0
-            .send(ProcessProtocol::<ProcessConfig>::ReadProcessState(
-                state_sender,
-            ))
+            .send(message)
             .await
-            .unwrap();
-        state_receiver.await.unwrap()
+            .map_err(|_| Error::ControllerGone)
     }
 }
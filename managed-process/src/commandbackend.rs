@@ -0,0 +1,130 @@
+use crate::{
+    Endpoint, Error, ExitReason, LogStream, ProcessLogWriter, ProcessProtocol, ResourceLimits,
+    Result, RunBackendProcess, StopStrategy,
+};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, process::Stdio, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    spawn,
+    sync::{mpsc::Sender as MpscSender, oneshot::Receiver as OneShotReceiver},
+    task::JoinHandle,
+    time::timeout,
+};
+use tracing::{error, info};
+
+// how long the lines a process printed before it ended may take to be forwarded
+const LAST_LINES_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// a process given by its command-line
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommandConfig {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<(String, String)>,
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+}
+
+/// runs arbitrary commands (e.g. on behalf of the remote-agent) ; both std-streams are recorded
+/// in the log ; the process is reported as started as soon as it is spawned
+#[derive(Clone, Debug, Default)]
+pub struct CommandBackend {
+    stop_strategy: StopStrategy,
+    resource_limits: ResourceLimits,
+}
+
+impl CommandBackend {
+    pub fn new(stop_strategy: StopStrategy, resource_limits: ResourceLimits) -> Self {
+        Self {
+            stop_strategy,
+            resource_limits,
+        }
+    }
+}
+
+impl RunBackendProcess for CommandBackend {
+    type ProcessConfig = CommandConfig;
+
+    fn run_backend_process(
+        &self,
+        process_config: CommandConfig,
+        cancel_receiver: OneShotReceiver<bool>,
+        notifier: MpscSender<ProcessProtocol<CommandConfig>>,
+        log: ProcessLogWriter,
+    ) -> Result<Option<Endpoint>> {
+        let mut cmd = Command::new(&process_config.program);
+        cmd.args(&process_config.args);
+        cmd.envs(process_config.env.iter().map(|(k, v)| (k, v)));
+        if let Some(working_dir) = &process_config.working_dir {
+            cmd.current_dir(working_dir);
+        }
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd.kill_on_drop(true);
+
+        self.resource_limits
+            .apply(&mut cmd)
+            .map_err(Error::SpawnFailed)?;
+
+        let mut proc_handle = cmd.spawn().map_err(Error::SpawnFailed)?;
+
+        let forwarders = [
+            forward_lines(proc_handle.stdout.take(), LogStream::Stdout, log.clone()),
+            forward_lines(proc_handle.stderr.take(), LogStream::Stderr, log),
+        ];
+
+        let name = process_config.program;
+        let stop_strategy = self.stop_strategy.clone();
+        spawn(async move {
+            let _ = notifier.send(ProcessProtocol::ProcessStarted).await;
+            let exit_reason = tokio::select! {
+                s = proc_handle.wait() => match s {
+                    Ok(exit_status) => {
+                        info!("{name}-process ended with {exit_status}");
+                        ExitReason::Exited(exit_status)
+                    }
+                    Err(e) => {
+                        error!("error waiting for {name}-process: {e}");
+                        ExitReason::Unknown
+                    }
+                },
+                _ = cancel_receiver => stop_strategy.stop(&mut proc_handle, &name).await,
+            };
+            // the last lines tell why the process ended ; children inheriting the pipes may keep
+            // them open though
+            for forwarder in forwarders.into_iter().flatten() {
+                let _ = timeout(LAST_LINES_TIMEOUT, forwarder).await;
+            }
+            // the controller may be gone already
+            let _ = notifier
+                .send(ProcessProtocol::ProcessFinished(exit_reason))
+                .await;
+        });
+
+        Ok(None)
+    }
+
+    fn stop_strategy(&self) -> StopStrategy {
+        self.stop_strategy.clone()
+    }
+}
+
+fn forward_lines(
+    stream: Option<impl AsyncRead + Unpin + Send + 'static>,
+    log_stream: LogStream,
+    log: ProcessLogWriter,
+) -> Option<JoinHandle<()>> {
+    stream.map(|stream| {
+        spawn(async move {
+            let mut lines = BufReader::new(stream).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log.push(log_stream, line);
+            }
+        })
+    })
+}
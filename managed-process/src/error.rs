@@ -0,0 +1,43 @@
+use std::fmt::Display;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    // the task managing the process is gone ; the controller can not be used anymore
+    ControllerGone,
+    // the process could not be spawned (e.g. the command does not exist)
+    SpawnFailed(std::io::Error),
+    // the process-group already holds its maximum number of instances
+    GroupFull(usize),
+    // every port of the process-group's range is allocated
+    NoFreePort,
+    // the process-group has no instance with the given key
+    UnknownInstance(String),
+    // the instance with the given key is still running and cannot be removed
+    InstanceActive(String),
+    // the config crashed repeatedly ; it is not started until the failures are reset
+    ConfigExcluded(String),
+    // the state-manager lost track of the process (a transition was interrupted)
+    StateUnknown,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ControllerGone => write!(f, "the task managing the process is gone"),
+            Self::SpawnFailed(e) => write!(f, "failed to spawn process: {e}"),
+            Self::GroupFull(max) => write!(f, "the process-group is full ({max} instances)"),
+            Self::NoFreePort => write!(f, "no free port left in the process-group's range"),
+            Self::UnknownInstance(key) => write!(f, "no instance {key} in the process-group"),
+            Self::InstanceActive(key) => write!(f, "instance {key} is still active"),
+            Self::ConfigExcluded(config) => write!(
+                f,
+                "config {config} crashed repeatedly ; reset the failures to start it again"
+            ),
+            Self::StateUnknown => write!(f, "the state of the process is unknown"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
@@ -1,19 +1,75 @@
 mod backendcontroller;
+mod commandbackend;
+mod error;
 mod model;
+mod processgroup;
+mod processhistory;
+mod processlog;
+mod readinessprobe;
+mod remotebackend;
+mod resourcelimits;
 mod statemanager;
+mod stopstrategy;
 
 pub use backendcontroller::BackendController;
+pub use commandbackend::{CommandBackend, CommandConfig};
+pub use error::{Error, Result};
+pub use model::Endpoint;
 pub use model::ProcessProtocol;
 pub use model::ProcessState;
+pub use model::RestartPolicy;
+pub use processgroup::{InstanceState, ProcessGroup};
+pub use processhistory::{DEFAULT_HISTORY_CAPACITY, ProcessHistory, Transition, TransitionCause};
+pub use processlog::{DEFAULT_LOG_CAPACITY, LogLine, LogStream, ProcessLog, ProcessLogWriter};
+pub use readinessprobe::{ReadinessCheck, ReadinessProbe};
+pub use regex::Regex;
+pub use remotebackend::{
+    RemoteAgent, RemoteBackend, RemoteExitReason, RemoteLogLine, RemoteProcessStatus, RemoteState,
+};
+pub use resourcelimits::ResourceLimits;
 pub(crate) use statemanager::ProcessStateManager;
+pub use stopstrategy::{ExitReason, StopStrategy};
 
 pub trait RunBackendProcess {
     type ProcessConfig: Clone + PartialEq + core::fmt::Debug + Send + 'static;
 
+    /// spawns the process ; an error (nothing was spawned) marks the config as failed ;
+    /// the output of the process is expected to be pushed to the log line by line ; once
+    /// 'cancel_receiver' fires the process is expected to be stopped using the stop-strategy ;
+    /// returns the address the process listens on (if it serves one)
     fn run_backend_process(
         &self,
         process_config: Self::ProcessConfig,
         cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
         notifier: tokio::sync::mpsc::Sender<ProcessProtocol<Self::ProcessConfig>>,
-    );
+        log: ProcessLogWriter,
+    ) -> Result<Option<Endpoint>>;
+
+    /// how the process is treated when it crashes
+    fn restart_policy(&self) -> RestartPolicy {
+        RestartPolicy::Never
+    }
+
+    /// decides when a process started with the given config (listening on the endpoint) is
+    /// running (asked for right after the process was spawned) ; without a probe the process is
+    /// expected to send 'ProcessStarted' itself
+    fn readiness_probe(
+        &self,
+        _process_config: &Self::ProcessConfig,
+        _endpoint: Option<&Endpoint>,
+    ) -> Option<ReadinessProbe> {
+        None
+    }
+
+    /// lines of a process exiting while starting which tell that spawning it again is worth it
+    /// (e.g. the port picked was taken by another process meanwhile) ; such a process is spawned
+    /// again right away instead of being treated as crashed
+    fn respawn_on(&self) -> Option<Regex> {
+        None
+    }
+
+    /// how the process is stopped (when it is cancelled)
+    fn stop_strategy(&self) -> StopStrategy {
+        StopStrategy::default()
+    }
 }
@@ -1,5 +1,6 @@
+use crate::{ExitReason, Result};
 use core::fmt::Debug as TDebug;
-use std::process::ExitStatus;
+use std::time::{Duration, Instant};
 pub use tokio::sync::oneshot::Sender as OneShotSender;
 
 pub enum ProcessProtocol<ProcessConfig>
@@ -8,12 +9,24 @@ where
 {
     // sent by process
     ProcessStarted,
-    ProcessFinished(Option<ExitStatus>),
+    ProcessFinished(ExitReason),
 
-    // sent by controller
-    StartProcess(ProcessConfig),
-    StopProcess,
-    ReadProcessState(OneShotSender<ProcessState<ProcessConfig>>),
+    // sent by the readiness-probe of the process of the given generation (if the backend has one)
+    ProcessReady(u64),
+    ProcessNotReady(u64, String),
+
+    // sent by the restart-timer once the backoff after a crash has elapsed
+    RestartProcess,
+
+    // sent by controller ; provides who requested the transition
+    StartProcess(ProcessConfig, String, OneShotSender<Result<()>>),
+    StopProcess(String),
+    ReadProcessState(OneShotSender<Result<ProcessState<ProcessConfig>>>),
+    ReadLastExit(OneShotSender<Option<ExitReason>>),
+    ResetFailures(String),
+
+    // sent by a process-group removing the controller ; ends the task managing the process
+    Shutdown,
 }
 
 #[derive(Clone, Debug)]
@@ -24,12 +37,57 @@ where
     // the process has not stopped or not started yet
     Stopped,
 
-    // process is stopping ; provides current config as well as optionally the next config to use (if set this state will transition to starting with the next_config, else it will transition to stopped)
-    Stopping(ProcessConfig, Option<ProcessConfig>),
+    // process is stopping ; provides current config as well as optionally the next config to use (if set this state will transition to starting with the next_config, else it will transition to stopped) and the deadline after which the process is killed
+    Stopping(ProcessConfig, Option<ProcessConfig>, Instant),
+
+    // process is running with given configuration ; provides the address it listens on (if any)
+    Running(ProcessConfig, Option<Endpoint>),
+
+    // process is starting using given configuration (also while waiting to be restarted after a crash) ; provides the address it listens on once it was spawned (if any)
+    Starting(ProcessConfig, Option<Endpoint>),
+
+    // process crashed and is not restarted (anymore) ; provides the config as well as the reason
+    Failed(ProcessConfig, String),
+}
 
-    // process is running with given configuration
-    Running(ProcessConfig),
+/// the address a spawned process listens on ; picked when spawning the process (see
+/// 'RunBackendProcess')
+#[derive(Clone, Debug, PartialEq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// what happens when a process exits with an error (without being asked to stop)
+#[derive(Clone, Debug, PartialEq)]
+pub enum RestartPolicy {
+    // the process is reported as failed right away ; it is started again on the next request
+    Never,
+
+    // the process is restarted after a backoff doubling with every crash (up to max_backoff) ;
+    // a config crashing more than max_retries times within the window is reported as failed
+    // and refused to be started again until the failures are reset
+    OnFailure {
+        initial_backoff: Duration,
+        max_backoff: Duration,
+        max_retries: u32,
+        window: Duration,
+    },
+}
 
-    // process is starting using given configuration
-    Starting(ProcessConfig),
+impl RestartPolicy {
+    pub fn on_failure() -> Self {
+        Self::OnFailure {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_retries: 3,
+            window: Duration::from_secs(300),
+        }
+    }
 }
@@ -0,0 +1,250 @@
+use crate::{BackendController, Error, ProcessState, Result, RunBackendProcess};
+use core::fmt::Debug as TDebug;
+use std::{collections::BTreeMap, ops::RangeInclusive, sync::Arc};
+use tokio::sync::Mutex;
+
+/// an instance of a process-group
+#[derive(Clone, Debug)]
+pub struct InstanceState<Key, ProcessConfig>
+where
+    ProcessConfig: Clone + PartialEq + TDebug + Send + 'static,
+{
+    pub key: Key,
+    // the port allocated to the instance
+    pub port: u16,
+    pub state: ProcessState<ProcessConfig>,
+}
+
+struct Instance<ProcessConfig>
+where
+    ProcessConfig: Clone + PartialEq + TDebug + Send + 'static,
+{
+    port: u16,
+    controller: BackendController<ProcessConfig>,
+}
+
+// creates the backend of a new instance listening on the given port
+type BackendFactory<Key, Backend> = Arc<dyn Fn(&Key, u16) -> Backend + Send + Sync>;
+
+/// supervises many backend-instances identified by a key ; every instance gets its own
+/// controller and a port out of the group's range ; instances are created on their first start
+/// and kept (with their port) until they are removed
+pub struct ProcessGroup<Key, Backend>
+where
+    Backend: RunBackendProcess,
+{
+    backend_factory: BackendFactory<Key, Backend>,
+    ports: RangeInclusive<u16>,
+    max_instances: usize,
+    instances: Arc<Mutex<BTreeMap<Key, Instance<Backend::ProcessConfig>>>>,
+}
+
+// not derived as neither the key nor the backend need to be 'Clone'
+impl<Key, Backend> Clone for ProcessGroup<Key, Backend>
+where
+    Backend: RunBackendProcess,
+{
+    fn clone(&self) -> Self {
+        Self {
+            backend_factory: self.backend_factory.clone(),
+            ports: self.ports.clone(),
+            max_instances: self.max_instances,
+            instances: self.instances.clone(),
+        }
+    }
+}
+
+impl<Key, Backend> ProcessGroup<Key, Backend>
+where
+    Key: Clone + Ord + TDebug + Send + 'static,
+    Backend: RunBackendProcess + Send + 'static,
+{
+    pub fn new(
+        ports: RangeInclusive<u16>,
+        max_instances: usize,
+        backend_factory: impl Fn(&Key, u16) -> Backend + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            backend_factory: Arc::new(backend_factory),
+            ports,
+            max_instances,
+            instances: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// starts the instance with the given key (creating it if needed) ; returns its port
+    pub async fn start(
+        &self,
+        key: Key,
+        config: Backend::ProcessConfig,
+        requested_by: impl Into<String>,
+    ) -> Result<u16> {
+        let mut instances = self.instances.lock().await;
+        if let Some(instance) = instances.get(&key) {
+            instance.controller.start(config, requested_by).await?;
+            return Ok(instance.port);
+        }
+        if instances.len() >= self.max_instances {
+            return Err(Error::GroupFull(self.max_instances));
+        }
+        let port = self
+            .ports
+            .clone()
+            .find(|port| instances.values().all(|instance| instance.port != *port))
+            .ok_or(Error::NoFreePort)?;
+        let controller = BackendController::init_backend((self.backend_factory)(&key, port)).await;
+        controller.start(config, requested_by).await?;
+        instances.insert(key, Instance { port, controller });
+        Ok(port)
+    }
+
+    pub async fn stop(&self, key: &Key, requested_by: impl Into<String>) -> Result<()> {
+        self.controller(key).await?.stop(requested_by).await
+    }
+
+    /// stops every instance of the group
+    pub async fn stop_all(&self, requested_by: impl Into<String>) -> Result<()> {
+        let requested_by = requested_by.into();
+        for instance in self.instances.lock().await.values() {
+            instance.controller.stop(requested_by.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// drops a stopped (or failed) instance and releases its port
+    pub async fn remove(&self, key: &Key) -> Result<()> {
+        let mut instances = self.instances.lock().await;
+        let instance = instances
+            .get(key)
+            .ok_or_else(|| Error::UnknownInstance(format!("{key:?}")))?;
+        match instance.controller.read_state().await? {
+            ProcessState::Stopped | ProcessState::Failed(..) => {}
+            _ => return Err(Error::InstanceActive(format!("{key:?}"))),
+        }
+        if let Some(instance) = instances.remove(key) {
+            instance.controller.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn state(&self, key: &Key) -> Result<ProcessState<Backend::ProcessConfig>> {
+        self.controller(key).await?.read_state().await
+    }
+
+    /// the states of all instances ordered by key
+    pub async fn states(&self) -> Result<Vec<InstanceState<Key, Backend::ProcessConfig>>> {
+        let instances = self.instances.lock().await;
+        let mut states = Vec::with_capacity(instances.len());
+        for (key, instance) in instances.iter() {
+            states.push(InstanceState {
+                key: key.clone(),
+                port: instance.port,
+                state: instance.controller.read_state().await?,
+            });
+        }
+        Ok(states)
+    }
+
+    pub async fn port(&self, key: &Key) -> Option<u16> {
+        self.instances
+            .lock()
+            .await
+            .get(key)
+            .map(|instance| instance.port)
+    }
+
+    /// the controller of the instance (e.g. to access its log or history)
+    pub async fn controller(&self, key: &Key) -> Result<BackendController<Backend::ProcessConfig>> {
+        self.instances
+            .lock()
+            .await
+            .get(key)
+            .map(|instance| instance.controller.clone())
+            .ok_or_else(|| Error::UnknownInstance(format!("{key:?}")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Endpoint, ExitReason, ProcessLogWriter, ProcessProtocol};
+    use std::time::Duration;
+
+    // a backend whose process runs until it is cancelled
+    struct SleepingBackend;
+
+    impl RunBackendProcess for SleepingBackend {
+        type ProcessConfig = String;
+
+        fn run_backend_process(
+            &self,
+            _process_config: String,
+            cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
+            notifier: tokio::sync::mpsc::Sender<ProcessProtocol<String>>,
+            _log: ProcessLogWriter,
+        ) -> Result<Option<Endpoint>> {
+            tokio::spawn(async move {
+                let _ = notifier.send(ProcessProtocol::ProcessStarted).await;
+                let _ = cancel_receiver.await;
+                let _ = notifier
+                    .send(ProcessProtocol::ProcessFinished(ExitReason::Unknown))
+                    .await;
+            });
+            Ok(None)
+        }
+    }
+
+    async fn wait_for_state(
+        group: &ProcessGroup<&'static str, SleepingBackend>,
+        key: &'static str,
+        stopped: bool,
+    ) {
+        for _ in 0..200 {
+            match group.state(&key).await.unwrap() {
+                ProcessState::Stopped if stopped => return,
+                ProcessState::Running(..) if !stopped => return,
+                _ => tokio::time::sleep(Duration::from_millis(5)).await,
+            }
+        }
+        panic!("instance '{key}' did not reach the expected state");
+    }
+
+    #[tokio::test]
+    async fn instances_get_ports_and_are_limited() {
+        let group = ProcessGroup::new(9000..=9001, 2, |_: &&'static str, _| SleepingBackend);
+
+        assert_eq!(
+            group.start("a", "a".to_string(), "test").await.unwrap(),
+            9000
+        );
+        assert_eq!(
+            group.start("b", "b".to_string(), "test").await.unwrap(),
+            9001
+        );
+        assert!(matches!(
+            group.start("c", "c".to_string(), "test").await,
+            Err(Error::GroupFull(2))
+        ));
+        wait_for_state(&group, "a", false).await;
+        assert!(matches!(
+            group.remove(&"a").await,
+            Err(Error::InstanceActive(_))
+        ));
+
+        group.stop(&"a", "test").await.unwrap();
+        wait_for_state(&group, "a", true).await;
+        group.remove(&"a").await.unwrap();
+        assert_eq!(group.port(&"a").await, None);
+
+        // the released port is reused
+        assert_eq!(
+            group.start("c", "c".to_string(), "test").await.unwrap(),
+            9000
+        );
+        let states = group.states().await.unwrap();
+        assert_eq!(
+            states.iter().map(|s| (s.key, s.port)).collect::<Vec<_>>(),
+            [("b", 9001), ("c", 9000)]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{ExitReason, ProcessState};
+use core::fmt::Debug as TDebug;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::broadcast::{Receiver as BroadcastReceiver, Sender as BroadcastSender, channel};
+
+/// number of transitions kept per controller (older ones are dropped)
+pub const DEFAULT_HISTORY_CAPACITY: usize = 500;
+
+/// what made a process change its state
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransitionCause {
+    // a start, a stop or a reset of the failures requested via the controller by the given requester
+    Requested(String),
+
+    // the process got ready
+    ProcessReady,
+
+    // the process did not get ready in time ; provides the reason
+    ProcessNotReady(String),
+
+    // the process finished
+    ProcessFinished(ExitReason),
+
+    // the backoff after a crash elapsed
+    Restart,
+}
+
+#[derive(Clone, Debug)]
+pub struct Transition<ProcessConfig>
+where
+    ProcessConfig: Clone + PartialEq + TDebug + Send + 'static,
+{
+    pub timestamp: SystemTime,
+    // the generation of the process run last (see 'LogLine')
+    pub generation: u64,
+    // the state entered
+    pub state: ProcessState<ProcessConfig>,
+    pub cause: TransitionCause,
+    // time the process took to get ready (set when entering 'Running')
+    pub startup_duration: Option<Duration>,
+}
+
+/// the recent state-transitions of a controller
+#[derive(Clone)]
+pub struct ProcessHistory<ProcessConfig>
+where
+    ProcessConfig: Clone + PartialEq + TDebug + Send + 'static,
+{
+    transitions: Arc<Mutex<VecDeque<Transition<ProcessConfig>>>>,
+    capacity: usize,
+    sender: BroadcastSender<Transition<ProcessConfig>>,
+}
+
+impl<ProcessConfig> ProcessHistory<ProcessConfig>
+where
+    ProcessConfig: Clone + PartialEq + TDebug + Send + 'static,
+{
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = channel(capacity.max(1));
+        Self {
+            transitions: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            sender,
+        }
+    }
+
+    pub fn transitions(&self) -> Vec<Transition<ProcessConfig>> {
+        self.transitions.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// the buffered transitions together with a receiver for every transition made afterwards
+    pub fn subscribe(
+        &self,
+    ) -> (
+        Vec<Transition<ProcessConfig>>,
+        BroadcastReceiver<Transition<ProcessConfig>>,
+    ) {
+        let transitions = self.transitions.lock().unwrap();
+        (
+            transitions.iter().cloned().collect(),
+            self.sender.subscribe(),
+        )
+    }
+
+    pub(crate) fn push(&self, transition: Transition<ProcessConfig>) {
+        let mut transitions = self.transitions.lock().unwrap();
+        if self.capacity > 0 {
+            if transitions.len() == self.capacity {
+                transitions.pop_front();
+            }
+            transitions.push_back(transition.clone());
+        }
+        // sent while locked so subscribers neither miss nor duplicate a transition
+        let _ = self.sender.send(transition);
+    }
+}
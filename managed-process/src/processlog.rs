@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use tokio::sync::broadcast::{Receiver as BroadcastReceiver, Sender as BroadcastSender, channel};
+
+/// number of lines kept per controller (older lines are dropped)
+pub const DEFAULT_LOG_CAPACITY: usize = 2000;
+
+/// the std-stream a line was written to
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    // counts the lines pushed to the log ; increases even after older lines were dropped, so
+    // readers can continue after the last line they read
+    pub seq: u64,
+    // counts the processes run by a controller ; tells lines of a restarted process apart
+    pub generation: u64,
+    pub timestamp: SystemTime,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+/// the recent output of all processes run by a controller
+#[derive(Clone)]
+pub struct ProcessLog {
+    buffer: Arc<Mutex<LogBuffer>>,
+    capacity: usize,
+    sender: BroadcastSender<LogLine>,
+}
+
+struct LogBuffer {
+    lines: VecDeque<LogLine>,
+    // the seq of the next line
+    next_seq: u64,
+}
+
+impl ProcessLog {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = channel(capacity.max(1));
+        Self {
+            buffer: Arc::new(Mutex::new(LogBuffer {
+                lines: VecDeque::with_capacity(capacity),
+                next_seq: 1,
+            })),
+            capacity,
+            sender,
+        }
+    }
+
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.buffer.lock().unwrap().lines.iter().cloned().collect()
+    }
+
+    /// the buffered lines together with a receiver for every line pushed afterwards
+    pub fn subscribe(&self) -> (Vec<LogLine>, BroadcastReceiver<LogLine>) {
+        let buffer = self.buffer.lock().unwrap();
+        (
+            buffer.lines.iter().cloned().collect(),
+            self.sender.subscribe(),
+        )
+    }
+
+    pub(crate) fn writer(&self, generation: u64) -> ProcessLogWriter {
+        ProcessLogWriter {
+            log: self.clone(),
+            generation,
+        }
+    }
+
+    fn push(&self, mut log_line: LogLine) {
+        let mut buffer = self.buffer.lock().unwrap();
+        log_line.seq = buffer.next_seq;
+        buffer.next_seq += 1;
+        if self.capacity > 0 {
+            if buffer.lines.len() == self.capacity {
+                buffer.lines.pop_front();
+            }
+            buffer.lines.push_back(log_line.clone());
+        }
+        // sent while locked so subscribers neither miss nor duplicate a line
+        let _ = self.sender.send(log_line);
+    }
+}
+
+/// handed to a process to record its output
+#[derive(Clone)]
+pub struct ProcessLogWriter {
+    log: ProcessLog,
+    generation: u64,
+}
+
+impl ProcessLogWriter {
+    pub fn push(&self, stream: LogStream, line: impl Into<String>) {
+        self.log.push(LogLine {
+            // assigned by the log
+            seq: 0,
+            generation: self.generation,
+            timestamp: SystemTime::now(),
+            stream,
+            line: line.into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn oldest_lines_are_dropped_and_subscribers_get_new_ones() {
+        let log = ProcessLog::new(2);
+        let writer = log.writer(1);
+        for line in ["a", "b", "c"] {
+            writer.push(LogStream::Stderr, line);
+        }
+
+        let (lines, mut receiver) = log.subscribe();
+        assert_eq!(
+            lines
+                .iter()
+                .map(|l| (l.seq, l.line.as_str()))
+                .collect::<Vec<_>>(),
+            [(2, "b"), (3, "c")]
+        );
+
+        log.writer(2).push(LogStream::Stdout, "d");
+        let received = receiver.recv().await.unwrap();
+        assert_eq!(
+            (received.seq, received.generation, received.line.as_str()),
+            (4, 2, "d")
+        );
+        assert_eq!(received.stream, LogStream::Stdout);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::LogLine;
+use regex::Regex;
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::broadcast::{Receiver as BroadcastReceiver, error::RecvError},
+};
+use tracing::trace;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// what has to be true for a started process to be considered ready
+#[derive(Clone, Debug)]
+pub enum ReadinessCheck {
+    // a line of the output of the process matches
+    LogLine(Regex),
+
+    // a tcp-connection to host and port can be established
+    TcpConnect {
+        host: String,
+        port: u16,
+    },
+
+    // a GET-request to host, port and path is answered with the expected status-code
+    HttpGet {
+        host: String,
+        port: u16,
+        path: String,
+        expected_status: u16,
+    },
+
+    // all of the checks pass (they are checked one after another)
+    All(Vec<ReadinessCheck>),
+}
+
+/// decides when a started process is reported as running ; a process not ready within the
+/// startup-timeout is stopped and reported as failed
+#[derive(Clone, Debug)]
+pub struct ReadinessProbe {
+    pub check: ReadinessCheck,
+    pub startup_timeout: Duration,
+}
+
+impl ReadinessProbe {
+    pub fn new(check: ReadinessCheck, startup_timeout: Duration) -> Self {
+        Self {
+            check,
+            startup_timeout,
+        }
+    }
+
+    /// waits until the check passes (or the startup-timeout elapsed) ; 'lines' are the lines
+    /// of the process buffered so far, 'receiver' provides the ones written afterwards
+    pub(crate) async fn wait_until_ready(
+        &self,
+        generation: u64,
+        lines: Vec<LogLine>,
+        receiver: BroadcastReceiver<LogLine>,
+    ) -> Result<(), String> {
+        let mut output = ProcessOutput {
+            generation,
+            lines,
+            receiver,
+        };
+        tokio::time::timeout(
+            self.startup_timeout,
+            self.check.wait_until_passed(&mut output),
+        )
+        .await
+        .map_err(|_| {
+            format!(
+                "process not ready within {}s",
+                self.startup_timeout.as_secs()
+            )
+        })?
+    }
+}
+
+// the output of the process being probed
+struct ProcessOutput {
+    generation: u64,
+    lines: Vec<LogLine>,
+    receiver: BroadcastReceiver<LogLine>,
+}
+
+impl ProcessOutput {
+    async fn wait_for_match(&mut self, regex: &Regex) -> Result<(), String> {
+        let generation = self.generation;
+        if self
+            .lines
+            .iter()
+            .any(|l| l.generation == generation && regex.is_match(&l.line))
+        {
+            return Ok(());
+        }
+        loop {
+            match self.receiver.recv().await {
+                Ok(log_line) if log_line.generation == generation => {
+                    if regex.is_match(&log_line.line) {
+                        return Ok(());
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Err("output of process is gone".to_owned()),
+            }
+        }
+    }
+}
+
+impl ReadinessCheck {
+    fn wait_until_passed<'a>(
+        &'a self,
+        output: &'a mut ProcessOutput,
+    ) -> std::pin::Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>> {
+        Box::pin(async move {
+            match self {
+                ReadinessCheck::LogLine(regex) => output.wait_for_match(regex).await,
+                ReadinessCheck::TcpConnect { host, port } => {
+                    while let Err(e) = TcpStream::connect((host.as_str(), *port)).await {
+                        trace!("tcp-readiness-check for {host}:{port} not passed yet: {e}");
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                    Ok(())
+                }
+                ReadinessCheck::HttpGet {
+                    host,
+                    port,
+                    path,
+                    expected_status,
+                } => loop {
+                    match http_get_status(host, *port, path).await {
+                        Ok(status) if status == *expected_status => return Ok(()),
+                        Ok(status) => {
+                            trace!("http-readiness-check for {host}:{port}{path} answered {status}")
+                        }
+                        Err(e) => trace!(
+                            "http-readiness-check for {host}:{port}{path} not passed yet: {e}"
+                        ),
+                    }
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                },
+                ReadinessCheck::All(checks) => {
+                    for check in checks {
+                        check.wait_until_passed(output).await?;
+                    }
+                    Ok(())
+                }
+            }
+        })
+    }
+}
+
+// a minimal http/1.1-client ; only the status-code of the answer is of interest
+async fn http_get_status(host: &str, port: u16, path: &str) -> std::io::Result<u16> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: {host}:{port}\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await?;
+
+    let mut status_line = Vec::new();
+    let mut buf = [0_u8; 256];
+    while !status_line.contains(&b'\n') {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        status_line.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8_lossy(&status_line)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| std::io::Error::other("invalid http-response"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DEFAULT_LOG_CAPACITY, LogStream, ProcessLog};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn checks_pass_on_matching_output_and_http_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0_u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+        let log = ProcessLog::new(DEFAULT_LOG_CAPACITY);
+        log.writer(1).push(LogStream::Stderr, "server is listening");
+        let probe = ReadinessProbe::new(
+            ReadinessCheck::All(vec![
+                ReadinessCheck::LogLine(Regex::new("listening").unwrap()),
+                ReadinessCheck::HttpGet {
+                    host: "127.0.0.1".into(),
+                    port,
+                    path: "/health".into(),
+                    expected_status: 200,
+                },
+            ]),
+            Duration::from_secs(5),
+        );
+
+        let (lines, receiver) = log.subscribe();
+        assert_eq!(probe.wait_until_ready(1, lines, receiver).await, Ok(()));
+
+        // lines of another generation do not count
+        let (lines, receiver) = log.subscribe();
+        let probe = ReadinessProbe::new(
+            ReadinessCheck::LogLine(Regex::new("listening").unwrap()),
+            Duration::from_millis(50),
+        );
+        assert!(probe.wait_until_ready(2, lines, receiver).await.is_err());
+    }
+}
@@ -0,0 +1,437 @@
+use crate::{
+    CommandConfig, Endpoint, ExitReason, LogStream, ProcessLogWriter, ProcessProtocol,
+    ProcessState, ReadinessProbe, RestartPolicy, Result, RunBackendProcess, StopStrategy,
+};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::{
+    Method, Request,
+    body::Bytes,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    os::unix::process::ExitStatusExt,
+    process::ExitStatus,
+    time::{Duration, Instant},
+};
+use tokio::{
+    spawn,
+    sync::{mpsc::Sender as MpscSender, oneshot::Receiver as OneShotReceiver},
+};
+use tracing::{error, warn};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// the agent is given up on after not answering this long
+const AGENT_TIMEOUT: Duration = Duration::from_secs(10);
+// larger responses of the agent are refused
+const MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
+/// the state of a process run by the remote-agent
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteState {
+    Stopped,
+    Stopping,
+    Starting,
+    Running,
+    Failed,
+}
+
+/// why a process run by the remote-agent finished (see 'ExitReason') ; 'status' is the raw
+/// wait-status
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "kind")]
+pub enum RemoteExitReason {
+    Exited { status: i32 },
+    Stopped { status: i32 },
+    Killed,
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemoteProcessStatus {
+    pub state: RemoteState,
+    // set if the state is 'Failed'
+    pub reason: Option<String>,
+    // the generation of the process run last (0 if no process was run yet)
+    pub generation: u64,
+    pub last_exit: Option<RemoteExitReason>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemoteLogLine {
+    // the seq of the line in the log of the agent (see 'LogLine')
+    pub seq: u64,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+impl From<ExitReason> for RemoteExitReason {
+    fn from(exit_reason: ExitReason) -> Self {
+        match exit_reason {
+            ExitReason::Exited(exit_status) => Self::Exited {
+                status: exit_status.into_raw(),
+            },
+            ExitReason::Stopped(exit_status) => Self::Stopped {
+                status: exit_status.into_raw(),
+            },
+            ExitReason::Killed => Self::Killed,
+            ExitReason::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl From<RemoteExitReason> for ExitReason {
+    fn from(exit_reason: RemoteExitReason) -> Self {
+        match exit_reason {
+            RemoteExitReason::Exited { status } => Self::Exited(ExitStatus::from_raw(status)),
+            RemoteExitReason::Stopped { status } => Self::Stopped(ExitStatus::from_raw(status)),
+            RemoteExitReason::Killed => Self::Killed,
+            RemoteExitReason::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl RemoteProcessStatus {
+    pub fn of<ProcessConfig>(
+        state: &ProcessState<ProcessConfig>,
+        generation: u64,
+        last_exit: Option<ExitReason>,
+    ) -> Self
+    where
+        ProcessConfig: Clone + PartialEq + core::fmt::Debug + Send + 'static,
+    {
+        let (state, reason) = match state {
+            ProcessState::Stopped => (RemoteState::Stopped, None),
+            ProcessState::Stopping(..) => (RemoteState::Stopping, None),
+            ProcessState::Starting(..) => (RemoteState::Starting, None),
+            ProcessState::Running(..) => (RemoteState::Running, None),
+            ProcessState::Failed(_, reason) => (RemoteState::Failed, Some(reason.clone())),
+        };
+        Self {
+            state,
+            reason,
+            generation,
+            last_exit: last_exit.map(RemoteExitReason::from),
+        }
+    }
+}
+
+/// client of the remote-agent (see the 'process-agent' crate) ; requests are authenticated with
+/// the agent's token
+#[derive(Clone, Debug)]
+pub struct RemoteAgent {
+    pub host: String,
+    pub port: u16,
+    pub token: String,
+    client: Client<HttpConnector, Full<Bytes>>,
+}
+
+impl RemoteAgent {
+    /// how long the agent may take to answer before it is given up on
+    pub const TIMEOUT: Duration = AGENT_TIMEOUT;
+
+    pub fn new(host: impl Into<String>, port: u16, token: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            token: token.into(),
+            client: Client::builder(TokioExecutor::new()).build(HttpConnector::new()),
+        }
+    }
+
+    pub async fn start(&self, name: &str, config: &CommandConfig) -> io::Result<()> {
+        let body = serde_json::to_vec(config).map_err(io::Error::other)?;
+        self.request(Method::POST, name, "/start", Some(body))
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn stop(&self, name: &str) -> io::Result<()> {
+        self.request(Method::POST, name, "/stop", None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn status(&self, name: &str) -> io::Result<RemoteProcessStatus> {
+        let body = self.request(Method::GET, name, "", None).await?;
+        serde_json::from_slice(&body).map_err(io::Error::other)
+    }
+
+    /// the buffered lines of the given generation following the line with the seq 'after' (all
+    /// of them if none is given)
+    pub async fn logs(
+        &self,
+        name: &str,
+        generation: u64,
+        after: Option<u64>,
+    ) -> io::Result<Vec<RemoteLogLine>> {
+        let query = match after {
+            Some(after) => format!("/logs?generation={generation}&after={after}"),
+            None => format!("/logs?generation={generation}"),
+        };
+        let body = self.request(Method::GET, name, &query, None).await?;
+        serde_json::from_slice(&body).map_err(io::Error::other)
+    }
+
+    // requests '/processes/{name}{sub_path}' ; the name is encoded as a path-segment
+    async fn request(
+        &self,
+        method: Method,
+        name: &str,
+        sub_path: &str,
+        body: Option<Vec<u8>>,
+    ) -> io::Result<Bytes> {
+        let uri = format!(
+            "http://{}:{}/processes/{}{sub_path}",
+            self.host,
+            self.port,
+            utf8_percent_encode(name, NON_ALPHANUMERIC)
+        );
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(&uri)
+            .header(AUTHORIZATION, format!("Bearer {}", self.token))
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body.unwrap_or_default())))
+            .map_err(io::Error::other)?;
+        let response = tokio::time::timeout(AGENT_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, format!("{method} {uri}")))?
+            .map_err(io::Error::other)?;
+        let status = response.status();
+        let body = Limited::new(response.into_body(), MAX_RESPONSE_BYTES)
+            .collect()
+            .await
+            .map_err(io::Error::other)?
+            .to_bytes();
+        if !status.is_success() {
+            return Err(io::Error::other(format!(
+                "agent answered {method} {uri} with {status}: {}",
+                String::from_utf8_lossy(&body)
+            )));
+        }
+        Ok(body)
+    }
+}
+
+/// runs the process on another host via the remote-agent ; the output of the remote process is
+/// forwarded to the log so log-line readiness-checks work as for local processes
+#[derive(Clone, Debug)]
+pub struct RemoteBackend {
+    agent: RemoteAgent,
+    // the name of the process at the agent
+    name: String,
+    restart_policy: RestartPolicy,
+    readiness_probe: Option<ReadinessProbe>,
+    // the one the agent stops processes with (its '--stop-grace-period')
+    agent_stop_strategy: StopStrategy,
+}
+
+impl RemoteBackend {
+    pub fn new(
+        agent: RemoteAgent,
+        name: impl Into<String>,
+        restart_policy: RestartPolicy,
+        readiness_probe: Option<ReadinessProbe>,
+        agent_stop_strategy: StopStrategy,
+    ) -> Self {
+        Self {
+            agent,
+            name: name.into(),
+            restart_policy,
+            readiness_probe,
+            agent_stop_strategy,
+        }
+    }
+}
+
+impl RemoteBackend {
+    /// runs the command at the agent on behalf of a controller of any process-config (e.g. a
+    /// backend deriving the command from its own config) ; reports to the notifier like
+    /// 'run_backend_process' does
+    pub fn run_command<ProcessConfig>(
+        &self,
+        command_config: CommandConfig,
+        cancel_receiver: OneShotReceiver<bool>,
+        notifier: MpscSender<ProcessProtocol<ProcessConfig>>,
+        log: ProcessLogWriter,
+    ) where
+        ProcessConfig: Clone + PartialEq + core::fmt::Debug + Send + 'static,
+    {
+        let remote_process = RemoteProcess {
+            agent: self.agent.clone(),
+            name: self.name.clone(),
+            announce_start: self.readiness_probe.is_none(),
+            stop_timeout: self.stop_strategy().grace_period,
+            log,
+        };
+        spawn(async move {
+            let exit_reason = remote_process
+                .run(command_config, cancel_receiver, &notifier)
+                .await
+                .unwrap_or_else(|e| {
+                    error!("remote process '{}' lost: {e}", remote_process.name);
+                    remote_process
+                        .log
+                        .push(LogStream::Stderr, format!("remote-agent: {e}"));
+                    ExitReason::Unknown
+                });
+            // the controller may be gone already
+            let _ = notifier
+                .send(ProcessProtocol::ProcessFinished(exit_reason))
+                .await;
+        });
+    }
+}
+
+impl RunBackendProcess for RemoteBackend {
+    type ProcessConfig = CommandConfig;
+
+    fn run_backend_process(
+        &self,
+        process_config: CommandConfig,
+        cancel_receiver: OneShotReceiver<bool>,
+        notifier: MpscSender<ProcessProtocol<CommandConfig>>,
+        log: ProcessLogWriter,
+    ) -> Result<Option<Endpoint>> {
+        self.run_command(process_config, cancel_receiver, notifier, log);
+        Ok(None)
+    }
+
+    fn restart_policy(&self) -> RestartPolicy {
+        self.restart_policy.clone()
+    }
+
+    fn readiness_probe(
+        &self,
+        _process_config: &CommandConfig,
+        _endpoint: Option<&Endpoint>,
+    ) -> Option<ReadinessProbe> {
+        self.readiness_probe.clone()
+    }
+
+    // the process is stopped by the agent using its own stop-strategy ; this one only tells
+    // the controller how long to wait for it
+    fn stop_strategy(&self) -> StopStrategy {
+        StopStrategy {
+            grace_period: self.agent_stop_strategy.grace_period + AGENT_TIMEOUT,
+            ..self.agent_stop_strategy.clone()
+        }
+    }
+}
+
+struct RemoteProcess {
+    agent: RemoteAgent,
+    name: String,
+    // the process has no readiness-probe and reports being started itself
+    announce_start: bool,
+    // how long the agent may take to confirm the process was stopped
+    stop_timeout: Duration,
+    log: ProcessLogWriter,
+}
+
+impl RemoteProcess {
+    async fn run<ProcessConfig>(
+        &self,
+        config: CommandConfig,
+        mut cancel_receiver: OneShotReceiver<bool>,
+        notifier: &MpscSender<ProcessProtocol<ProcessConfig>>,
+    ) -> io::Result<ExitReason>
+    where
+        ProcessConfig: Clone + PartialEq + core::fmt::Debug + Send + 'static,
+    {
+        let previous_generation = self.agent.status(&self.name).await?.generation;
+        self.agent.start(&self.name, &config).await?;
+
+        let mut started = false;
+        // set once cancelled ; the stop is sent until the agent accepted it
+        let mut stop_requested: Option<Instant> = None;
+        let mut stop_accepted = false;
+        let mut last_forwarded = None;
+        let mut last_answer = Instant::now();
+        loop {
+            if stop_requested.is_none() {
+                tokio::select! {
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    _ = &mut cancel_receiver => stop_requested = Some(Instant::now()),
+                }
+            } else {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            if let Some(stop_requested) = stop_requested {
+                if !stop_accepted {
+                    match self.agent.stop(&self.name).await {
+                        Ok(()) => stop_accepted = true,
+                        Err(e) => warn!("remote-agent did not stop '{}': {e}", self.name),
+                    }
+                }
+                // the remote process must not be orphaned silently
+                if stop_requested.elapsed() >= self.stop_timeout {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "the agent did not confirm that the remote process was stopped",
+                    ));
+                }
+            }
+
+            let status = match self.agent.status(&self.name).await {
+                Ok(status) => {
+                    last_answer = Instant::now();
+                    status
+                }
+                Err(e) if last_answer.elapsed() < AGENT_TIMEOUT || stop_requested.is_some() => {
+                    warn!("remote-agent did not answer for '{}': {e}", self.name);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            // the agent did not pick up the start yet
+            if status.generation <= previous_generation {
+                continue;
+            }
+
+            // lines missed by a failed poll are fetched with the next one
+            match self
+                .agent
+                .logs(&self.name, status.generation, last_forwarded)
+                .await
+            {
+                Ok(lines) => {
+                    for line in lines {
+                        last_forwarded = Some(line.seq);
+                        self.log.push(line.stream, line.line);
+                    }
+                }
+                Err(e) => warn!("remote-agent did not send the log of '{}': {e}", self.name),
+            }
+
+            match status.state {
+                RemoteState::Running if !started => {
+                    started = true;
+                    if self.announce_start {
+                        let _ = notifier.send(ProcessProtocol::ProcessStarted).await;
+                    }
+                }
+                RemoteState::Stopped | RemoteState::Failed => {
+                    if let Some(reason) = status.reason {
+                        self.log.push(LogStream::Stderr, reason);
+                    }
+                    return Ok(status
+                        .last_exit
+                        .map(ExitReason::from)
+                        .unwrap_or(ExitReason::Unknown));
+                }
+                _ => {}
+            }
+        }
+    }
+}
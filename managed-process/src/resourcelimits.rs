@@ -0,0 +1,237 @@
+use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::PathBuf};
+use tokio::process::Command;
+
+/// os-level controls applied to a process before it is executed ; unset fields leave the
+/// settings inherited from the server untouched
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    // niceness (-20 is the highest priority, 19 the lowest)
+    pub nice: Option<i32>,
+    // the cpus the process may run on
+    pub cpu_affinity: Option<Vec<usize>>,
+    // limit of the memory in bytes ; enforced as 'memory.max' of the cgroup (which is required
+    // for it), since limiting the virtual memory breaks processes reserving large address-spaces
+    // (e.g. cuda or mmap-ed models)
+    pub max_memory: Option<u64>,
+    // limit of open file-descriptors (RLIMIT_NOFILE)
+    pub max_open_files: Option<u64>,
+    // cgroup-v2 directory the process is moved to (created if missing), e.g.
+    // '/sys/fs/cgroup/mai.slice/embedding'
+    pub cgroup: Option<PathBuf>,
+}
+
+impl ResourceLimits {
+    /// the limits set here, completed by the ones set in 'fallback'
+    pub fn or(self, fallback: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            nice: self.nice.or(fallback.nice),
+            cpu_affinity: self.cpu_affinity.or_else(|| fallback.cpu_affinity.clone()),
+            max_memory: self.max_memory.or(fallback.max_memory),
+            max_open_files: self.max_open_files.or(fallback.max_open_files),
+            cgroup: self.cgroup.or_else(|| fallback.cgroup.clone()),
+        }
+    }
+
+    /// sets a single limit given as 'key=value' ; keys are 'nice', 'cpus' (e.g. '0-7,16'),
+    /// 'max-memory-mb', 'max-open-files' and 'cgroup'
+    pub fn set(&mut self, setting: &str) -> Result<(), String> {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("expected 'key=value' but got '{setting}'"))?;
+        let invalid = |_| format!("invalid value for '{key}': '{value}'");
+        match key.trim() {
+            "nice" => self.nice = Some(value.trim().parse().map_err(invalid)?),
+            "cpus" => self.cpu_affinity = Some(parse_cpu_list(value)?),
+            "max-memory-mb" => {
+                self.max_memory = Some(value.trim().parse::<u64>().map_err(invalid)? * 1024 * 1024)
+            }
+            "max-open-files" => self.max_open_files = Some(value.trim().parse().map_err(invalid)?),
+            "cgroup" => self.cgroup = Some(PathBuf::from(value.trim())),
+            _ => return Err(format!("unknown resource-limit '{key}'")),
+        }
+        Ok(())
+    }
+
+    /// fails if the limits cannot be enforced together (a memory-limit requires a cgroup)
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_memory.is_some() && self.cgroup.is_none() {
+            return Err(
+                "'max-memory-mb' is enforced via a cgroup and requires 'cgroup'".to_owned(),
+            );
+        }
+        Ok(())
+    }
+
+    /// makes the command apply the limits in the spawned process right before it is executed ;
+    /// fails if the limits are invalid or the cgroup cannot be created (or its memory-limit set)
+    pub fn apply(&self, cmd: &mut Command) -> io::Result<()> {
+        if *self == ResourceLimits::default() {
+            return Ok(());
+        }
+        self.validate().map_err(io::Error::other)?;
+
+        // everything allocating is prepared here ; only async-signal-safe calls are made in
+        // the forked process
+        let cpu_set = match &self.cpu_affinity {
+            Some(cpus) => {
+                // SAFETY: cpu_set_t is plain data for which all-zero is the empty set
+                let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+                for cpu in cpus {
+                    if *cpu >= libc::CPU_SETSIZE as usize {
+                        return Err(io::Error::other(format!("cpu {cpu} out of range")));
+                    }
+                    // SAFETY: the cpu is within the set
+                    unsafe { libc::CPU_SET(*cpu, &mut cpu_set) };
+                }
+                Some(cpu_set)
+            }
+            None => None,
+        };
+        let cgroup_procs = match &self.cgroup {
+            Some(cgroup) => {
+                std::fs::create_dir_all(cgroup)?;
+                // requires the memory-controller to be enabled for the parent cgroup
+                if let Some(max_memory) = self.max_memory {
+                    std::fs::write(cgroup.join("memory.max"), max_memory.to_string())?;
+                }
+                Some(
+                    CString::new(cgroup.join("cgroup.procs").as_os_str().as_bytes())
+                        .map_err(io::Error::other)?,
+                )
+            }
+            None => None,
+        };
+        let nice = self.nice;
+        let max_open_files = self.max_open_files;
+
+        let pre_exec = move || {
+            // SAFETY: plain syscalls on the current process with valid arguments
+            unsafe {
+                if let Some(nice) = nice
+                    && libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(cpu_set) = &cpu_set
+                    && libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), cpu_set) != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(max_open_files) = max_open_files {
+                    let rlimit = libc::rlimit {
+                        rlim_cur: max_open_files as libc::rlim_t,
+                        rlim_max: max_open_files as libc::rlim_t,
+                    };
+                    if libc::setrlimit(libc::RLIMIT_NOFILE, &rlimit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                if let Some(cgroup_procs) = &cgroup_procs {
+                    // writing '0' moves the writing process itself
+                    let fd = libc::open(cgroup_procs.as_ptr(), libc::O_WRONLY);
+                    if fd < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    let written = libc::write(fd, b"0".as_ptr().cast(), 1);
+                    libc::close(fd);
+                    if written != 1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+            }
+            Ok(())
+        };
+        // SAFETY: the closure only makes async-signal-safe calls and does not allocate
+        unsafe {
+            cmd.pre_exec(pre_exec);
+        }
+        Ok(())
+    }
+}
+
+// e.g. '0-3,8,10-11'
+fn parse_cpu_list(cpu_list: &str) -> Result<Vec<usize>, String> {
+    let invalid = |_| format!("invalid cpu-list '{cpu_list}'");
+    let mut cpus = Vec::new();
+    for range in cpu_list.split(',').map(str::trim) {
+        match range.split_once('-') {
+            Some((first, last)) => {
+                let first = first.trim().parse::<usize>().map_err(invalid)?;
+                let last = last.trim().parse::<usize>().map_err(invalid)?;
+                if first > last {
+                    return Err(format!(
+                        "invalid cpu-list '{cpu_list}': range '{range}' is reversed"
+                    ));
+                }
+                cpus.extend(first..=last)
+            }
+            None => cpus.push(range.parse().map_err(invalid)?),
+        }
+    }
+    Ok(cpus)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn limits_are_parsed_and_applied_to_the_spawned_process() {
+        // the cpus the tests may run on are not necessarily the first ones
+        // SAFETY: cpu_set_t is plain data for which all-zero is the empty set
+        let mut allowed: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        // SAFETY: the set is valid and large enough
+        assert_eq!(
+            unsafe { libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut allowed) },
+            0
+        );
+        let cpu = (0..libc::CPU_SETSIZE as usize)
+            // SAFETY: the cpu is within the set
+            .find(|cpu| unsafe { libc::CPU_ISSET(*cpu, &allowed) })
+            .unwrap();
+
+        let mut limits = ResourceLimits::default();
+        limits.set("nice=5").unwrap();
+        limits.set("max-open-files=64").unwrap();
+        limits.set(&format!("cpus={cpu}")).unwrap();
+        assert!(limits.set("cpus=a-b").is_err());
+        let limits = limits.or(&ResourceLimits {
+            nice: Some(10),
+            cpu_affinity: Some(vec![0, 1, 2]),
+            ..Default::default()
+        });
+        assert_eq!(limits.nice, Some(5));
+        assert_eq!(limits.cpu_affinity, Some(vec![cpu]));
+        assert_eq!(parse_cpu_list("0-2, 8").unwrap(), vec![0, 1, 2, 8]);
+        assert!(parse_cpu_list("3-1").is_err());
+
+        let mut cmd = Command::new("sh");
+        cmd.args([
+            "-c",
+            "nice; ulimit -n; grep Cpus_allowed_list /proc/self/status",
+        ]);
+        limits.apply(&mut cmd).unwrap();
+        let output = cmd.output().await.unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<_> = stdout.lines().map(str::trim).collect();
+        assert_eq!(lines[..2], ["5", "64"]);
+        assert!(lines[2].ends_with(&format!("\t{cpu}")), "{}", lines[2]);
+    }
+
+    #[test]
+    fn the_memory_limit_is_set_on_the_cgroup() {
+        let cgroup = std::env::temp_dir().join(format!("mai-cgroup-{}", std::process::id()));
+        let mut limits = ResourceLimits::default();
+        limits.set("max-memory-mb=16").unwrap();
+        assert!(limits.validate().is_err());
+        assert!(limits.apply(&mut Command::new("true")).is_err());
+
+        limits.cgroup = Some(cgroup.clone());
+        limits.apply(&mut Command::new("true")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(cgroup.join("memory.max")).unwrap(),
+            (16 * 1024 * 1024).to_string()
+        );
+        std::fs::remove_dir_all(cgroup).unwrap();
+    }
+}
@@ -1,14 +1,24 @@
-use crate::{ProcessProtocol, ProcessState, RunBackendProcess};
+use crate::{
+    DEFAULT_HISTORY_CAPACITY, DEFAULT_LOG_CAPACITY, Error, ExitReason, ProcessHistory, ProcessLog,
+    ProcessProtocol, ProcessState, RestartPolicy, Result, RunBackendProcess, Transition,
+    TransitionCause,
+};
 use std::{
     fmt::Debug,
-    process::ExitStatus,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime},
 };
-use tokio::sync::{
-    mpsc::Sender as MpscSender,
-    oneshot::{Sender as OneShotSender, channel as oneshot_channel},
+use tokio::{
+    sync::{
+        mpsc::Sender as MpscSender,
+        oneshot::{Sender as OneShotSender, channel as oneshot_channel},
+    },
+    task::AbortHandle,
 };
-use tracing::error;
+use tracing::{error, info, warn};
+
+// times a process exiting while starting is spawned again (see 'respawn_on')
+const MAX_RESPAWNS: u32 = 3;
 
 pub struct ProcessStateManager<Backend, ProcessConfig>
 where
@@ -18,6 +28,29 @@ where
     state: Option<ProcessState<ProcessConfig>>,
     optional_cancel_sender: Arc<Mutex<Option<OneShotSender<bool>>>>,
     controller_sender_proto: MpscSender<ProcessProtocol<ProcessConfig>>,
+    restart_policy: RestartPolicy,
+    // the config of the last started process and the times it crashed
+    last_config: Option<ProcessConfig>,
+    crashes: Vec<Instant>,
+    // configs that crashed too often ; they are not started until the failures are reset
+    excluded_configs: Vec<ProcessConfig>,
+    // set while 'Starting' only waits for the restart-timer
+    restart_pending: bool,
+    log: ProcessLog,
+    // incremented for every process run
+    generation: u64,
+    // the probe waiting for the current process to get ready
+    probe_task: Option<AbortHandle>,
+    // set while a process which did not get ready is being stopped
+    startup_failure: Option<String>,
+    // time a stopping process gets before it is killed
+    stop_grace_period: Duration,
+    last_exit: Option<ExitReason>,
+    history: ProcessHistory<ProcessConfig>,
+    // set when entering 'Starting' ; tells how long the process took to get ready
+    starting_since: Option<Instant>,
+    // the times the current config was spawned again as its process exited while starting
+    respawns: u32,
 }
 
 impl<Backend, ProcessConfig> ProcessStateManager<Backend, ProcessConfig>
@@ -29,11 +62,227 @@ where
         backend: Backend,
         controller_sender: MpscSender<ProcessProtocol<ProcessConfig>>,
     ) -> Self {
+        let restart_policy = backend.restart_policy();
+        let stop_grace_period = backend.stop_strategy().grace_period;
         Self {
             backend,
             state: Some(ProcessState::<ProcessConfig>::Stopped),
             optional_cancel_sender: Arc::new(Mutex::new(None)),
             controller_sender_proto: controller_sender,
+            restart_policy,
+            last_config: None,
+            crashes: Vec::new(),
+            excluded_configs: Vec::new(),
+            restart_pending: false,
+            log: ProcessLog::new(DEFAULT_LOG_CAPACITY),
+            generation: 0,
+            probe_task: None,
+            startup_failure: None,
+            stop_grace_period,
+            last_exit: None,
+            history: ProcessHistory::new(DEFAULT_HISTORY_CAPACITY),
+            starting_since: None,
+            respawns: 0,
+        }
+    }
+
+    pub fn log(&self) -> ProcessLog {
+        self.log.clone()
+    }
+
+    pub fn history(&self) -> ProcessHistory<ProcessConfig> {
+        self.history.clone()
+    }
+
+    // sets the state and records the transition to it
+    fn enter(&mut self, state: ProcessState<ProcessConfig>, cause: TransitionCause) {
+        let startup_duration = match &state {
+            ProcessState::<ProcessConfig>::Starting(..) => {
+                self.starting_since = Some(Instant::now());
+                None
+            }
+            ProcessState::<ProcessConfig>::Running(..) => {
+                self.starting_since.take().map(|since| since.elapsed())
+            }
+            _ => None,
+        };
+        self.history.push(Transition {
+            timestamp: SystemTime::now(),
+            generation: self.generation,
+            state: state.clone(),
+            cause,
+            startup_duration,
+        });
+        self.state = Some(state);
+    }
+
+    // returns the state to continue with: 'Starting' or 'Failed' if the process was not spawned
+    fn run_backend_process(&mut self, config: ProcessConfig) -> ProcessState<ProcessConfig> {
+        if self.last_config.as_ref() != Some(&config) {
+            self.crashes.clear();
+            self.last_config = Some(config.clone());
+        }
+        let (cancel_sender, cancel_receiver) = oneshot_channel::<bool>();
+        self.generation += 1;
+        self.abort_probe();
+        // subscribed before spawning so no line of the process is missed
+        let log_subscription = self.log.subscribe();
+        match self.backend.run_backend_process(
+            config.clone(),
+            cancel_receiver,
+            self.controller_sender_proto.clone(),
+            self.log.writer(self.generation),
+        ) {
+            Ok(endpoint) => {
+                *self.cancel_sender() = Some(cancel_sender);
+                if let Some(readiness_probe) =
+                    self.backend.readiness_probe(&config, endpoint.as_ref())
+                {
+                    let (lines, receiver) = log_subscription;
+                    let generation = self.generation;
+                    let notifier = self.controller_sender_proto.clone();
+                    let probe_task = tokio::spawn(async move {
+                        let message = match readiness_probe
+                            .wait_until_ready(generation, lines, receiver)
+                            .await
+                        {
+                            Ok(()) => ProcessProtocol::ProcessReady(generation),
+                            Err(reason) => ProcessProtocol::ProcessNotReady(generation, reason),
+                        };
+                        let _ = notifier.send(message).await;
+                    });
+                    self.probe_task = Some(probe_task.abort_handle());
+                }
+                ProcessState::<ProcessConfig>::Starting(config, endpoint)
+            }
+            Err(e) => {
+                error!("{e} (config: {config:?})");
+                ProcessState::<ProcessConfig>::Failed(config, e.to_string())
+            }
+        }
+    }
+
+    fn stopping(
+        &self,
+        config: ProcessConfig,
+        next_config: Option<ProcessConfig>,
+    ) -> ProcessState<ProcessConfig> {
+        ProcessState::<ProcessConfig>::Stopping(
+            config,
+            next_config,
+            Instant::now() + self.stop_grace_period,
+        )
+    }
+
+    fn abort_probe(&mut self) {
+        if let Some(probe_task) = self.probe_task.take() {
+            probe_task.abort();
+        }
+    }
+
+    // a panic while holding the lock leaves the sender as it is ; it is used anyway
+    fn cancel_sender(&self) -> MutexGuard<'_, Option<OneShotSender<bool>>> {
+        self.optional_cancel_sender
+            .lock()
+            .unwrap_or_else(|poisoned| {
+                warn!("cancel-sender was poisoned ; using it anyway");
+                poisoned.into_inner()
+            })
+    }
+
+    // the process may have finished already (its 'ProcessFinished' is on the way then)
+    fn cancel_process(&self) {
+        match self.cancel_sender().take() {
+            Some(cancel_sender) => {
+                if cancel_sender.send(true).is_err() {
+                    warn!("process to cancel finished already");
+                }
+            }
+            None => error!("err: expected cancel_sender to be available but was None"),
+        }
+    }
+
+    // behaves like a process: it is cancelled the same way and reports 'ProcessFinished' then
+    fn schedule_restart(&mut self, backoff: Duration) {
+        let (cancel_sender, cancel_receiver) = oneshot_channel::<bool>();
+        *self.cancel_sender() = Some(cancel_sender);
+        let notifier = self.controller_sender_proto.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {
+                    let _ = notifier.send(ProcessProtocol::RestartProcess).await;
+                }
+                _ = cancel_receiver => {
+                    let _ = notifier.send(ProcessProtocol::ProcessFinished(ExitReason::Unknown)).await;
+                }
+            }
+        });
+        self.restart_pending = true;
+    }
+
+    fn on_process_crashed(&mut self, config: ProcessConfig, exit_reason: ExitReason) {
+        let reason = exit_reason.to_string();
+        let cause = TransitionCause::ProcessFinished(exit_reason);
+        match self.restart_policy.clone() {
+            RestartPolicy::Never => {
+                error!("{reason} ; not restarting it (config: {config:?})");
+                self.enter(ProcessState::<ProcessConfig>::Failed(config, reason), cause);
+            }
+            RestartPolicy::OnFailure {
+                initial_backoff,
+                max_backoff,
+                max_retries,
+                window,
+            } => {
+                let now = Instant::now();
+                self.crashes
+                    .retain(|crashed_at| now.duration_since(*crashed_at) <= window);
+                self.crashes.push(now);
+                let crashes = self.crashes.len() as u32;
+
+                if crashes > max_retries {
+                    let reason = format!(
+                        "{reason} ; crashed {crashes} times within {}s",
+                        window.as_secs()
+                    );
+                    error!("{reason} ; excluding config until failures are reset: {config:?}");
+                    self.excluded_configs.push(config.clone());
+                    self.enter(ProcessState::<ProcessConfig>::Failed(config, reason), cause);
+                } else {
+                    let backoff = initial_backoff
+                        .saturating_mul(2_u32.saturating_pow(crashes - 1))
+                        .min(max_backoff);
+                    warn!(
+                        "{reason} ; restarting in {}ms (retry {crashes} of {max_retries})",
+                        backoff.as_millis()
+                    );
+                    self.schedule_restart(backoff);
+                    self.enter(ProcessState::<ProcessConfig>::Starting(config, None), cause);
+                }
+            }
+        }
+    }
+
+    pub fn on_restart_process(&mut self) {
+        if !self.restart_pending {
+            return;
+        }
+        self.restart_pending = false;
+        if let Some(ProcessState::<ProcessConfig>::Starting(config, _)) = self.state.clone() {
+            info!("restarting crashed process");
+            let state = self.run_backend_process(config);
+            self.enter(state, TransitionCause::Restart);
+        }
+    }
+
+    pub fn on_reset_failures(&mut self, requested_by: String) {
+        self.crashes.clear();
+        self.excluded_configs.clear();
+        if let Some(ProcessState::<ProcessConfig>::Failed(_, _)) = &self.state {
+            self.enter(
+                ProcessState::<ProcessConfig>::Stopped,
+                TransitionCause::Requested(requested_by),
+            );
         }
     }
 
@@ -41,125 +290,523 @@ where
         self.controller_sender_proto.clone()
     }
 
-    pub fn on_process_finished(&mut self, _optional_exit_status: Option<ExitStatus>) {
+    // the process of the current generation exited while starting and told that spawning it
+    // again is worth it
+    fn respawn_wanted(&self) -> bool {
+        self.respawns < MAX_RESPAWNS
+            && self.backend.respawn_on().is_some_and(|respawn_on| {
+                self.log.lines().iter().any(|line| {
+                    line.generation == self.generation && respawn_on.is_match(&line.line)
+                })
+            })
+    }
+
+    pub fn on_process_finished(&mut self, exit_reason: ExitReason) {
         let state = self.state.take();
+        let process_finished = !self.restart_pending;
+        // a restart-timer being cancelled is no process finishing
+        if !self.restart_pending {
+            info!("{exit_reason}");
+            self.last_exit = Some(exit_reason);
+        }
+        self.restart_pending = false;
+        self.abort_probe();
+        let startup_failure = self.startup_failure.take();
+        let cause = TransitionCause::ProcessFinished(exit_reason);
 
         match state {
-            Some(ProcessState::<ProcessConfig>::Stopping(_, Some(next_config_handle))) => {
-                let (cancel_sender, cancel_receiver) = oneshot_channel::<bool>();
-                let mut ocs = self.optional_cancel_sender.lock().unwrap();
-                *ocs = Some(cancel_sender);
-                self.backend.run_backend_process(
-                    next_config_handle.clone(),
-                    cancel_receiver,
-                    self.controller_sender_proto.clone(),
+            Some(ProcessState::<ProcessConfig>::Stopping(_, Some(next_config_handle), _)) => {
+                let state = self.run_backend_process(next_config_handle);
+                self.enter(state, cause);
+            }
+            Some(ProcessState::<ProcessConfig>::Stopping(config, None, _))
+                if startup_failure.is_some() =>
+            {
+                self.enter(
+                    ProcessState::<ProcessConfig>::Failed(
+                        config,
+                        startup_failure.unwrap_or_default(),
+                    ),
+                    cause,
                 );
-                self.state = Some(ProcessState::<ProcessConfig>::Starting(next_config_handle));
             }
-            _ => self.state = Some(ProcessState::<ProcessConfig>::Stopped),
+            Some(ProcessState::<ProcessConfig>::Starting(config, _))
+                if process_finished && self.respawn_wanted() =>
+            {
+                self.respawns += 1;
+                warn!(
+                    "{exit_reason} while starting ; spawning it again (attempt {} of {MAX_RESPAWNS})",
+                    self.respawns
+                );
+                let state = self.run_backend_process(config);
+                self.enter(state, cause);
+            }
+            Some(
+                ProcessState::<ProcessConfig>::Starting(config, _)
+                | ProcessState::<ProcessConfig>::Running(config, _),
+            ) if exit_reason.is_crash() => self.on_process_crashed(config, exit_reason),
+            _ => self.enter(ProcessState::<ProcessConfig>::Stopped, cause),
         }
     }
 
     pub fn on_process_started(&mut self) {
-        if let Some(ProcessState::<ProcessConfig>::Starting(config)) = self.state.take() {
-            self.state = Some(ProcessState::<ProcessConfig>::Running(config));
+        match self.state.take() {
+            Some(ProcessState::<ProcessConfig>::Starting(config, endpoint)) => {
+                self.respawns = 0;
+                self.enter(
+                    ProcessState::<ProcessConfig>::Running(config, endpoint),
+                    TransitionCause::ProcessReady,
+                );
+            }
+            state => {
+                warn!("ignoring 'ProcessStarted' received in state {state:?}");
+                self.state = state;
+            }
+        }
+    }
+
+    pub fn on_process_ready(&mut self, generation: u64) {
+        if generation != self.generation {
+            return;
+        }
+        self.probe_task = None;
+        self.on_process_started();
+    }
+
+    // the process is stopped and reported as failed once it finished
+    pub fn on_process_not_ready(&mut self, generation: u64, reason: String) {
+        if generation != self.generation {
+            return;
+        }
+        self.probe_task = None;
+        if let Some(ProcessState::<ProcessConfig>::Starting(config, _)) = self.state.take() {
+            error!("{reason} ; stopping it (config: {config:?})");
+            let state = self.stopping(config, None);
+            self.enter(state, TransitionCause::ProcessNotReady(reason.clone()));
+            self.startup_failure = Some(reason);
+            self.cancel_process();
         } else {
-            panic!("received 'ProcessStarted' but state is not 'Starting'")
+            warn!(
+                "ignoring failed readiness-probe received in state {:?}",
+                self.state
+            );
         }
     }
 
-    pub fn on_start_process(&mut self, config: ProcessConfig) {
+    pub fn on_start_process(&mut self, config: ProcessConfig, requested_by: String) -> Result<()> {
+        if self.excluded_configs.contains(&config) {
+            warn!(
+                "not starting config which crashed repeatedly (reset failures first): {config:?}"
+            );
+            return Err(Error::ConfigExcluded(format!("{config:?}")));
+        }
         match &self.state {
-            Some(ProcessState::<ProcessConfig>::Running(cur_config)) if *cur_config == config => {
-                return;
+            Some(ProcessState::<ProcessConfig>::Running(cur_config, _))
+                if *cur_config == config =>
+            {
+                return Ok(());
             }
-            Some(ProcessState::<ProcessConfig>::Starting(starting_config))
+            Some(ProcessState::<ProcessConfig>::Starting(starting_config, _))
                 if *starting_config == config =>
             {
-                return;
+                return Ok(());
             }
-            Some(ProcessState::<ProcessConfig>::Stopping(_, Some(next_config)))
+            Some(ProcessState::<ProcessConfig>::Stopping(_, Some(next_config), _))
                 if *next_config == config =>
             {
-                return;
+                return Ok(());
             }
             _ => {}
         }
 
+        self.respawns = 0;
+        let cause = TransitionCause::Requested(requested_by);
         let state = self.state.take();
         match state {
-            Some(ProcessState::<ProcessConfig>::Running(old_config)) => {
-                self.state = Some(ProcessState::<ProcessConfig>::Stopping(
-                    old_config,
-                    Some(config),
-                ));
-
-                if let Some(cancel_sender) = self.optional_cancel_sender.lock().unwrap().take() {
-                    cancel_sender.send(true).unwrap();
-                } else {
-                    error!("err: expected cancel_sender to be available but was None");
-                }
+            Some(ProcessState::<ProcessConfig>::Running(old_config, _)) => {
+                let state = self.stopping(old_config, Some(config));
+                self.enter(state, cause);
+                self.cancel_process();
             }
-            Some(ProcessState::<ProcessConfig>::Starting(old_config)) => {
-                self.state = Some(ProcessState::<ProcessConfig>::Stopping(
-                    old_config,
-                    Some(config),
-                ));
-                if let Some(cancel_sender) = self.optional_cancel_sender.lock().unwrap().take() {
-                    cancel_sender.send(true).unwrap();
-                } else {
-                    error!("err: expected cancel_sender to be available but was None");
-                }
+            Some(ProcessState::<ProcessConfig>::Starting(old_config, _)) => {
+                let state = self.stopping(old_config, Some(config));
+                self.enter(state, cause);
+                self.cancel_process();
             }
-            Some(ProcessState::<ProcessConfig>::Stopping(old_config, _)) => {
-                self.state = Some(ProcessState::<ProcessConfig>::Stopping(
-                    old_config,
-                    Some(config),
-                ));
-            }
-            Some(ProcessState::<ProcessConfig>::Stopped) => {
-                let (cancel_sender, cancel_receiver) = oneshot_channel::<bool>();
-                let mut ocs = self.optional_cancel_sender.lock().unwrap();
-                *ocs = Some(cancel_sender);
-                self.backend.run_backend_process(
-                    config.clone(),
-                    cancel_receiver,
-                    self.controller_sender(),
+            Some(ProcessState::<ProcessConfig>::Stopping(old_config, _, deadline)) => {
+                self.enter(
+                    ProcessState::<ProcessConfig>::Stopping(old_config, Some(config), deadline),
+                    cause,
                 );
-                self.state = Some(ProcessState::<ProcessConfig>::Starting(config));
             }
-            _ => unreachable!(),
+            Some(ProcessState::<ProcessConfig>::Stopped)
+            | Some(ProcessState::<ProcessConfig>::Failed(_, _)) => {
+                let state = self.run_backend_process(config);
+                self.enter(state, cause);
+            }
+            None => {
+                warn!("ignoring start of config in unknown state: {config:?}");
+                self.state = state;
+                return Err(Error::StateUnknown);
+            }
         }
+        Ok(())
     }
 
-    pub fn on_stop_process(&mut self) {
+    pub fn on_stop_process(&mut self, requested_by: String) {
         match &self.state {
-            Some(ProcessState::Stopped) | Some(ProcessState::Stopping(_, None)) => return,
+            Some(ProcessState::Stopped)
+            | Some(ProcessState::Stopping(_, None, _))
+            | Some(ProcessState::Failed(_, _)) => return,
             _ => {}
         }
+        let cause = TransitionCause::Requested(requested_by);
         let state = self.state.take();
 
         match state {
-            Some(ProcessState::<ProcessConfig>::Running(cfg)) => {
-                self.state = Some(ProcessState::<ProcessConfig>::Stopping(cfg, None));
-                if let Some(cancel_sender) = self.optional_cancel_sender.lock().unwrap().take() {
-                    cancel_sender.send(true).unwrap();
-                }
+            Some(ProcessState::<ProcessConfig>::Running(cfg, _)) => {
+                let state = self.stopping(cfg, None);
+                self.enter(state, cause);
+                self.cancel_process();
             }
-            Some(ProcessState::<ProcessConfig>::Starting(cfg)) => {
-                self.state = Some(ProcessState::<ProcessConfig>::Stopping(cfg, None));
-                if let Some(cancel_sender) = self.optional_cancel_sender.lock().unwrap().take() {
-                    cancel_sender.send(true).unwrap();
-                }
+            Some(ProcessState::<ProcessConfig>::Starting(cfg, _)) => {
+                let state = self.stopping(cfg, None);
+                self.enter(state, cause);
+                self.cancel_process();
+            }
+            Some(ProcessState::<ProcessConfig>::Stopping(cfg, Some(_), deadline)) => {
+                self.enter(
+                    ProcessState::<ProcessConfig>::Stopping(cfg, None, deadline),
+                    cause,
+                );
+            }
+            state => {
+                warn!("ignoring stop in state {state:?}");
+                self.state = state;
             }
-            Some(ProcessState::<ProcessConfig>::Stopping(cfg, Some(_))) => {
-                self.state = Some(ProcessState::<ProcessConfig>::Stopping(cfg, None));
+        }
+    }
+
+    pub fn on_read_config(&self, back_chan: OneShotSender<Result<ProcessState<ProcessConfig>>>) {
+        // the reader may have given up waiting
+        let _ = back_chan.send(self.state.clone().ok_or(Error::StateUnknown));
+    }
+
+    pub fn on_read_last_exit(&self, back_chan: OneShotSender<Option<ExitReason>>) {
+        let _ = back_chan.send(self.last_exit);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        BackendController, Endpoint, Error, ExitReason, LogStream, ProcessLogWriter,
+        ProcessProtocol, ProcessState, ReadinessCheck, ReadinessProbe, RestartPolicy, Result,
+        RunBackendProcess, TransitionCause,
+    };
+    use regex::Regex;
+    use std::{
+        os::unix::process::ExitStatusExt,
+        process::ExitStatus,
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
+
+    // a backend whose process crashes right after being started
+    struct CrashingBackend {
+        runs: Arc<AtomicU32>,
+    }
+
+    impl RunBackendProcess for CrashingBackend {
+        type ProcessConfig = String;
+
+        fn run_backend_process(
+            &self,
+            _process_config: String,
+            _cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
+            notifier: tokio::sync::mpsc::Sender<ProcessProtocol<String>>,
+            _log: ProcessLogWriter,
+        ) -> Result<Option<Endpoint>> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                notifier
+                    .send(ProcessProtocol::ProcessFinished(ExitReason::Exited(
+                        ExitStatus::from_raw(1 << 8),
+                    )))
+                    .await
+                    .unwrap();
+            });
+            Ok(None)
+        }
+
+        fn restart_policy(&self) -> RestartPolicy {
+            RestartPolicy::OnFailure {
+                initial_backoff: Duration::from_millis(5),
+                max_backoff: Duration::from_millis(10),
+                max_retries: 2,
+                window: Duration::from_secs(60),
+            }
+        }
+    }
+
+    async fn wait_for_failed(controller: &BackendController<String>) -> String {
+        for _ in 0..200 {
+            if let ProcessState::Failed(_, reason) = controller.read_state().await.unwrap() {
+                return reason;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("process was not reported as failed")
+    }
+
+    #[tokio::test]
+    async fn crash_looping_config_is_restarted_then_excluded_until_reset() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let controller =
+            BackendController::init_backend(CrashingBackend { runs: runs.clone() }).await;
+
+        controller.start("model-a".into(), "test").await.unwrap();
+        let reason = wait_for_failed(&controller).await;
+        assert!(reason.contains("crashed 3 times"), "{reason}");
+        // initial start plus two retries
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // the excluded config is refused ...
+        assert!(matches!(
+            controller.start("model-a".into(), "test").await,
+            Err(Error::ConfigExcluded(_))
+        ));
+        assert!(matches!(
+            controller.read_state().await.unwrap(),
+            ProcessState::Failed(_, _)
+        ));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // ... until the failures are reset
+        controller.reset_failures("test").await.unwrap();
+        assert!(matches!(
+            controller.read_state().await.unwrap(),
+            ProcessState::Stopped
+        ));
+        controller.start("model-a".into(), "test").await.unwrap();
+        wait_for_failed(&controller).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 6);
+    }
+
+    // a backend whose command does not exist
+    struct MissingBackend;
+
+    impl RunBackendProcess for MissingBackend {
+        type ProcessConfig = String;
+
+        fn run_backend_process(
+            &self,
+            _process_config: String,
+            _cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
+            _notifier: tokio::sync::mpsc::Sender<ProcessProtocol<String>>,
+            _log: ProcessLogWriter,
+        ) -> Result<Option<Endpoint>> {
+            Err(Error::SpawnFailed(std::io::ErrorKind::NotFound.into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_in_an_unknown_state_are_ignored() {
+        let (sender, _receiver) = tokio::sync::mpsc::channel(1);
+        let mut state_manager = super::ProcessStateManager::new(MissingBackend, sender);
+        state_manager.state = None;
+
+        assert!(matches!(
+            state_manager.on_start_process("model-a".into(), "test".into()),
+            Err(Error::StateUnknown)
+        ));
+        state_manager.on_stop_process("test".into());
+        assert!(state_manager.state.is_none());
+
+        let (state_sender, state_receiver) = tokio::sync::oneshot::channel();
+        state_manager.on_read_config(state_sender);
+        assert!(matches!(
+            state_receiver.await.unwrap(),
+            Err(Error::StateUnknown)
+        ));
+    }
+
+    #[tokio::test]
+    async fn spawn_failure_is_reported_as_failed() {
+        let controller = BackendController::init_backend(MissingBackend).await;
+
+        controller.start("model-a".into(), "test").await.unwrap();
+        let reason = wait_for_failed(&controller).await;
+        assert!(reason.starts_with("failed to spawn process"), "{reason}");
+
+        // stopping a failed process is ignored
+        controller.stop("test").await.unwrap();
+        assert!(matches!(
+            controller.read_state().await.unwrap(),
+            ProcessState::Failed(_, _)
+        ));
+    }
+
+    // a backend whose process never prints what its readiness-probe waits for
+    struct NeverReadyBackend;
+
+    impl RunBackendProcess for NeverReadyBackend {
+        type ProcessConfig = String;
+
+        fn run_backend_process(
+            &self,
+            _process_config: String,
+            cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
+            notifier: tokio::sync::mpsc::Sender<ProcessProtocol<String>>,
+            log: ProcessLogWriter,
+        ) -> Result<Option<Endpoint>> {
+            log.push(LogStream::Stderr, "loading model");
+            tokio::spawn(async move {
+                let _ = cancel_receiver.await;
+                let _ = notifier
+                    .send(ProcessProtocol::ProcessFinished(ExitReason::Stopped(
+                        ExitStatus::from_raw(libc::SIGTERM),
+                    )))
+                    .await;
+            });
+            Ok(None)
+        }
+
+        fn readiness_probe(
+            &self,
+            _process_config: &String,
+            _endpoint: Option<&Endpoint>,
+        ) -> Option<ReadinessProbe> {
+            Some(ReadinessProbe::new(
+                ReadinessCheck::LogLine(Regex::new("listening").unwrap()),
+                Duration::from_millis(50),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn process_not_ready_within_startup_timeout_is_stopped_and_reported_as_failed() {
+        let controller = BackendController::init_backend(NeverReadyBackend).await;
+
+        controller.start("model-a".into(), "test").await.unwrap();
+        assert!(matches!(
+            controller.read_state().await.unwrap(),
+            ProcessState::Starting(..)
+        ));
+        let reason = wait_for_failed(&controller).await;
+        assert!(reason.contains("not ready within"), "{reason}");
+        assert!(matches!(
+            controller.last_exit().await.unwrap(),
+            Some(ExitReason::Stopped(_))
+        ));
+
+        let causes: Vec<_> = controller
+            .history()
+            .transitions()
+            .into_iter()
+            .map(|transition| transition.cause)
+            .collect();
+        assert!(matches!(
+            causes.as_slice(),
+            [
+                TransitionCause::Requested(requested_by),
+                TransitionCause::ProcessNotReady(_),
+                TransitionCause::ProcessFinished(ExitReason::Stopped(_)),
+            ] if requested_by == "test"
+        ));
+    }
+
+    // a backend whose first processes exit as their port is taken already
+    struct PortTakenBackend {
+        runs: Arc<AtomicU32>,
+        taken: u32,
+    }
+
+    impl RunBackendProcess for PortTakenBackend {
+        type ProcessConfig = String;
+
+        fn run_backend_process(
+            &self,
+            _process_config: String,
+            cancel_receiver: tokio::sync::oneshot::Receiver<bool>,
+            notifier: tokio::sync::mpsc::Sender<ProcessProtocol<String>>,
+            log: ProcessLogWriter,
+        ) -> Result<Option<Endpoint>> {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            let port = 9000 + run as u16;
+            if run <= self.taken {
+                log.push(
+                    LogStream::Stderr,
+                    format!("couldn't bind to port {port}: address already in use"),
+                );
+                tokio::spawn(async move {
+                    let _ = notifier
+                        .send(ProcessProtocol::ProcessFinished(ExitReason::Exited(
+                            ExitStatus::from_raw(1 << 8),
+                        )))
+                        .await;
+                });
+            } else {
+                tokio::spawn(async move {
+                    let _ = notifier.send(ProcessProtocol::ProcessStarted).await;
+                    let _ = cancel_receiver.await;
+                    let _ = notifier
+                        .send(ProcessProtocol::ProcessFinished(ExitReason::Unknown))
+                        .await;
+                });
             }
-            _ => unreachable!(),
+            Ok(Some(Endpoint {
+                host: "127.0.0.1".to_owned(),
+                port,
+            }))
+        }
+
+        fn respawn_on(&self) -> Option<Regex> {
+            Some(Regex::new("address already in use").unwrap())
         }
     }
 
-    pub fn on_read_config(&self, back_chan: OneShotSender<ProcessState<ProcessConfig>>) {
-        back_chan.send(self.state.clone().unwrap()).unwrap();
+    #[tokio::test]
+    async fn process_exiting_as_its_port_is_taken_is_spawned_again_on_another_one() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let controller = BackendController::init_backend(PortTakenBackend {
+            runs: runs.clone(),
+            taken: 1,
+        })
+        .await;
+
+        controller.start("model-a".into(), "test").await.unwrap();
+        let mut state = controller.read_state().await.unwrap();
+        for _ in 0..200 {
+            if matches!(state, ProcessState::Running(..)) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            state = controller.read_state().await.unwrap();
+        }
+        assert!(
+            matches!(
+                &state,
+                ProcessState::Running(config, Some(Endpoint { port: 9002, .. }))
+                    if config == "model-a"
+            ),
+            "{state:?}"
+        );
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        // a port taken over and over is a failure like any other
+        let runs = Arc::new(AtomicU32::new(0));
+        let controller = BackendController::init_backend(PortTakenBackend {
+            runs: runs.clone(),
+            taken: u32::MAX,
+        })
+        .await;
+        controller.start("model-a".into(), "test").await.unwrap();
+        let reason = wait_for_failed(&controller).await;
+        assert!(reason.contains("exited"), "{reason}");
+        assert_eq!(runs.load(Ordering::SeqCst), 1 + super::MAX_RESPAWNS);
     }
 }
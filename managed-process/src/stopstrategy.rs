@@ -0,0 +1,143 @@
+use std::{fmt::Display, process::ExitStatus, time::Duration};
+use tokio::process::Child;
+use tracing::{info, warn};
+
+/// how a process is stopped: it is sent the signal and killed if it is still alive after the
+/// grace-period
+#[derive(Clone, Debug, PartialEq)]
+pub struct StopStrategy {
+    pub signal: libc::c_int,
+    pub grace_period: Duration,
+}
+
+impl Default for StopStrategy {
+    fn default() -> Self {
+        Self {
+            signal: libc::SIGTERM,
+            grace_period: Duration::from_secs(10),
+        }
+    }
+}
+
+/// why a process finished
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExitReason {
+    // the process exited on its own
+    Exited(ExitStatus),
+
+    // the process exited after being sent the stop-signal
+    Stopped(ExitStatus),
+
+    // the process did not exit within the grace-period and was killed
+    Killed,
+
+    // the exit-status could not be determined (or there was no process to stop)
+    Unknown,
+}
+
+impl ExitReason {
+    /// the process exited on its own with an error
+    pub fn is_crash(&self) -> bool {
+        matches!(self, ExitReason::Exited(exit_status) if !exit_status.success())
+    }
+}
+
+impl Display for ExitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExitReason::Exited(exit_status) => write!(f, "process exited with {exit_status}"),
+            ExitReason::Stopped(exit_status) => {
+                write!(f, "process stopped and exited with {exit_status}")
+            }
+            ExitReason::Killed => write!(f, "process killed after grace-period"),
+            ExitReason::Unknown => write!(f, "process finished for unknown reason"),
+        }
+    }
+}
+
+impl StopStrategy {
+    pub fn new(signal: libc::c_int, grace_period: Duration) -> Self {
+        Self {
+            signal,
+            grace_period,
+        }
+    }
+
+    /// sends the signal to the process and kills it if it does not exit within the grace-period
+    pub async fn stop(&self, proc_handle: &mut Child, process_name: &str) -> ExitReason {
+        let Some(pid) = proc_handle.id() else {
+            // the process exited already
+            return match proc_handle.try_wait() {
+                Ok(Some(exit_status)) => ExitReason::Exited(exit_status),
+                _ => ExitReason::Unknown,
+            };
+        };
+
+        info!(
+            "sending signal {} to {process_name}-process ({pid})",
+            self.signal
+        );
+        // SAFETY: the pid belongs to the child which was not reaped yet (id() returned Some)
+        if unsafe { libc::kill(pid as libc::pid_t, self.signal) } != 0 {
+            warn!(
+                "error sending signal {} to {process_name}-process ({pid}): {}",
+                self.signal,
+                std::io::Error::last_os_error()
+            );
+        }
+
+        match tokio::time::timeout(self.grace_period, proc_handle.wait()).await {
+            Ok(Ok(exit_status)) => {
+                info!("{process_name}-process exited with {exit_status}");
+                ExitReason::Stopped(exit_status)
+            }
+            Ok(Err(e)) => {
+                warn!("error waiting for {process_name}-process to exit: {e}");
+                ExitReason::Unknown
+            }
+            Err(_) => {
+                warn!(
+                    "{process_name}-process did not exit within {}s after signal {} ; killing it",
+                    self.grace_period.as_secs(),
+                    self.signal
+                );
+                if let Err(e) = proc_handle.kill().await {
+                    warn!("error killing {process_name}-process: {e}");
+                }
+                ExitReason::Killed
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use tokio::process::Command;
+
+    #[tokio::test]
+    async fn process_exits_on_signal_within_grace_period_or_is_killed() {
+        let stop_strategy = StopStrategy::default();
+        let mut proc_handle = Command::new("sleep").arg("60").spawn().unwrap();
+        let start = std::time::Instant::now();
+
+        let exit_reason = stop_strategy.stop(&mut proc_handle, "sleep").await;
+
+        assert!(start.elapsed() < stop_strategy.grace_period);
+        match exit_reason {
+            ExitReason::Stopped(exit_status) => {
+                assert_eq!(exit_status.signal(), Some(libc::SIGTERM))
+            }
+            exit_reason => panic!("unexpected exit-reason {exit_reason:?}"),
+        }
+
+        // 'sleep' ignores SIGCHLD
+        let stop_strategy = StopStrategy::new(libc::SIGCHLD, Duration::from_millis(50));
+        let mut proc_handle = Command::new("sleep").arg("60").spawn().unwrap();
+        assert_eq!(
+            stop_strategy.stop(&mut proc_handle, "sleep").await,
+            ExitReason::Killed
+        );
+    }
+}
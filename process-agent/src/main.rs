@@ -0,0 +1,428 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, Request, State},
+    http::{StatusCode, header::AUTHORIZATION},
+    middleware::{Next, from_fn_with_state},
+    response::Response,
+    routing::{get, post},
+};
+use managed_process::{
+    BackendController, CommandBackend, CommandConfig, Error, RemoteLogLine, RemoteProcessStatus,
+    ResourceLimits, StopStrategy,
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tracing::{Level, info, warn};
+
+const MAIAGENT_LOG_KEY: &str = "MAIAGENT_LOG";
+const MAIAGENT_TOKEN_KEY: &str = "MAIAGENT_TOKEN";
+const DEFAULT_PORT: u16 = 11450;
+// arguments naming one of these (e.g. '--api-key') are not logged with their values
+const SECRET_ARG_MARKERS: [&str; 4] = ["key", "token", "secret", "password"];
+
+// the processes run on behalf of the gateway by their names
+#[derive(Clone)]
+struct AgentState {
+    token: Arc<String>,
+    // the programs the gateway may run ; others are refused
+    allowed_programs: Arc<HashSet<String>>,
+    stop_strategy: StopStrategy,
+    controllers: Arc<Mutex<HashMap<String, BackendController<CommandConfig>>>>,
+}
+
+impl AgentState {
+    async fn controller(&self, name: &str) -> Option<BackendController<CommandConfig>> {
+        self.controllers.lock().await.get(name).cloned()
+    }
+}
+
+#[derive(Deserialize)]
+struct LogsQuery {
+    generation: u64,
+    // the seq of the last line read
+    after: Option<u64>,
+}
+
+fn create_app(
+    token: String,
+    allowed_programs: HashSet<String>,
+    stop_strategy: StopStrategy,
+) -> Router {
+    let state = AgentState {
+        token: Arc::new(token),
+        allowed_programs: Arc::new(allowed_programs),
+        stop_strategy,
+        controllers: Arc::new(Mutex::new(HashMap::new())),
+    };
+    Router::new()
+        .route("/processes/{name}", get(status))
+        .route("/processes/{name}/start", post(start))
+        .route("/processes/{name}/stop", post(stop))
+        .route("/processes/{name}/logs", get(logs))
+        .layer(from_fn_with_state(state.clone(), check_auth))
+        .with_state(state)
+}
+
+async fn check_auth(
+    State(state): State<AgentState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    match req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|auth_header| auth_header.to_str().ok())
+        .and_then(|auth_header| auth_header.strip_prefix("Bearer "))
+    {
+        Some(bearer_token) if constant_time_eq(bearer_token.as_bytes(), state.token.as_bytes()) => {
+            Ok(next.run(req).await)
+        }
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+// takes the same time wherever the tokens differ (only their length is disclosed)
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+async fn start(
+    State(state): State<AgentState>,
+    Path(name): Path<String>,
+    Json(config): Json<CommandConfig>,
+) -> Result<StatusCode, StatusCode> {
+    if !state.allowed_programs.contains(&config.program) {
+        warn!("refusing to run '{}' as process '{name}'", config.program);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let mut controllers = state.controllers.lock().await;
+    let controller = match controllers.get(&name) {
+        Some(controller) => controller.clone(),
+        None => {
+            let controller = BackendController::init_backend(CommandBackend::new(
+                state.stop_strategy.clone(),
+                ResourceLimits::default(),
+            ))
+            .await;
+            controllers.insert(name.clone(), controller.clone());
+            controller
+        }
+    };
+    info!("starting process '{name}': {}", redacted(&config));
+    controller
+        .start(config, "remote-agent")
+        .await
+        .map_err(|e| match e {
+            // crash-looping ; refused until the failures are reset
+            Error::ConfigExcluded(_) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+// the command-line as logged: the values of the environment and of secret-looking arguments
+// are left out
+fn redacted(config: &CommandConfig) -> String {
+    let is_secret = |arg: &str| {
+        let arg = arg.to_ascii_lowercase();
+        arg.starts_with('-') && SECRET_ARG_MARKERS.iter().any(|m| arg.contains(m))
+    };
+    let mut args = Vec::with_capacity(config.args.len());
+    let mut redact_next = false;
+    for arg in &config.args {
+        if redact_next {
+            args.push("[redacted]".to_owned());
+            redact_next = false;
+        } else if is_secret(arg) {
+            match arg.split_once('=') {
+                Some((name, _)) => args.push(format!("{name}=[redacted]")),
+                None => {
+                    args.push(arg.clone());
+                    redact_next = true;
+                }
+            }
+        } else {
+            args.push(arg.clone());
+        }
+    }
+    let env: Vec<_> = config.env.iter().map(|(key, _)| key.as_str()).collect();
+    format!(
+        "{} {} (env: {})",
+        config.program,
+        args.join(" "),
+        env.join(", ")
+    )
+}
+
+async fn stop(
+    State(state): State<AgentState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let controller = state.controller(&name).await.ok_or(StatusCode::NOT_FOUND)?;
+    info!("stopping process '{name}'");
+    controller
+        .stop("remote-agent")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+// processes not started yet are reported as stopped
+async fn status(
+    State(state): State<AgentState>,
+    Path(name): Path<String>,
+) -> Result<Json<RemoteProcessStatus>, StatusCode> {
+    let Some(controller) = state.controller(&name).await else {
+        return Ok(Json(RemoteProcessStatus::of::<CommandConfig>(
+            &managed_process::ProcessState::Stopped,
+            0,
+            None,
+        )));
+    };
+    let process_state = controller
+        .read_state()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let last_exit = controller
+        .last_exit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let generation = controller
+        .history()
+        .transitions()
+        .last()
+        .map(|transition| transition.generation)
+        .unwrap_or(0);
+    Ok(Json(RemoteProcessStatus::of(
+        &process_state,
+        generation,
+        last_exit,
+    )))
+}
+
+async fn logs(
+    State(state): State<AgentState>,
+    Path(name): Path<String>,
+    Query(query): Query<LogsQuery>,
+) -> Result<Json<Vec<RemoteLogLine>>, StatusCode> {
+    let controller = state.controller(&name).await.ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(
+        controller
+            .log()
+            .lines()
+            .into_iter()
+            .filter(|line| {
+                line.generation == query.generation && query.after.is_none_or(|a| line.seq > a)
+            })
+            .map(|line| RemoteLogLine {
+                seq: line.seq,
+                stream: line.stream,
+                line: line.line,
+            })
+            .collect(),
+    ))
+}
+
+#[tokio::main]
+async fn main() {
+    let max_level = match std::env::var(MAIAGENT_LOG_KEY) {
+        Ok(max_log_level) => max_log_level.parse().unwrap_or(Level::ERROR),
+        Err(_) => Level::INFO,
+    };
+    tracing_subscriber::fmt().with_max_level(max_level).init();
+
+    let mut args = std::env::args();
+    let (listen, allowed_programs, stop_strategy) = {
+        // other addresses must be secured by tls or an ssh-tunnel (the agent speaks plain http)
+        let mut listen = SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT));
+        let mut allowed_programs = HashSet::new();
+        let mut stop_strategy = StopStrategy::default();
+        while let Some(a) = args.next() {
+            if a == "--listen" {
+                match args.next().map(|listen_value| listen_value.parse()) {
+                    Some(Ok(listen_value)) => listen = listen_value,
+                    _ => panic!("invalid or no value for \"--listen\" provided"),
+                }
+            }
+            if a == "--allow-program" {
+                match args.next() {
+                    Some(program) => {
+                        allowed_programs.insert(program);
+                    }
+                    None => panic!("no value for \"--allow-program\" provided"),
+                }
+            }
+            if a == "--stop-grace-period" {
+                match args.next().map(|secs| secs.parse::<u64>()) {
+                    Some(Ok(secs)) => stop_strategy.grace_period = Duration::from_secs(secs),
+                    _ => panic!("invalid or no value for \"--stop-grace-period\" provided"),
+                }
+            }
+        }
+        if allowed_programs.is_empty() {
+            panic!("no program allowed to run (add \"--allow-program <program>\")");
+        }
+        (listen, allowed_programs, stop_strategy)
+    };
+    let token = std::env::var(MAIAGENT_TOKEN_KEY)
+        .unwrap_or_else(|_| panic!("no token provided (set \"{MAIAGENT_TOKEN_KEY}\")"));
+    // an empty token would accept 'Authorization: Bearer '
+    if token.trim().is_empty() {
+        panic!("the token must not be empty (set \"{MAIAGENT_TOKEN_KEY}\")");
+    }
+
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .unwrap_or_else(|e| panic!("error binding {listen}: {e}"));
+    info!("remote-agent listening on {listen}");
+    if !listen.ip().is_loopback() {
+        warn!("remote-agent speaks plain http ; secure {listen} by tls or an ssh-tunnel");
+    }
+    axum::serve(listener, create_app(token, allowed_programs, stop_strategy))
+        .await
+        .unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use managed_process::{ExitReason, ProcessState, RemoteAgent, RemoteBackend, RestartPolicy};
+
+    async fn serve_agent() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                create_app(
+                    "secret".to_string(),
+                    HashSet::from(["sh".to_string()]),
+                    StopStrategy::default(),
+                ),
+            )
+            .await
+            .unwrap()
+        });
+        port
+    }
+
+    async fn run_via_agent(
+        agent: &RemoteAgent,
+        name: &str,
+        script: &str,
+    ) -> BackendController<CommandConfig> {
+        let controller = BackendController::init_backend(RemoteBackend::new(
+            agent.clone(),
+            name,
+            RestartPolicy::Never,
+            None,
+            StopStrategy::default(),
+        ))
+        .await;
+        let config = CommandConfig {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: Vec::new(),
+            working_dir: None,
+        };
+        controller.start(config, "test").await.unwrap();
+        controller
+    }
+
+    async fn wait_for_line(controller: &BackendController<CommandConfig>, line: &str) -> bool {
+        for _ in 0..200 {
+            if let ProcessState::Running(..) = controller.read_state().await.unwrap()
+                && controller.log().lines().iter().any(|l| l.line == line)
+            {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn gateway_runs_process_via_agent() {
+        let port = serve_agent().await;
+
+        // a wrong token is refused
+        let intruder = RemoteAgent::new("127.0.0.1", port, "guess");
+        assert!(intruder.status("sleeper").await.is_err());
+
+        // programs which are not allowed are refused
+        let agent = RemoteAgent::new("127.0.0.1", port, "secret");
+        let forbidden = CommandConfig {
+            program: "rm".to_string(),
+            args: vec!["-rf".to_string(), "/tmp/nothing".to_string()],
+            env: Vec::new(),
+            working_dir: None,
+        };
+        let e = agent.start("remover", &forbidden).await.unwrap_err();
+        assert!(e.to_string().contains("403"), "{e}");
+
+        let controller = run_via_agent(&agent, "sleeper", "echo hello ; exec sleep 30").await;
+        assert!(
+            wait_for_line(&controller, "hello").await,
+            "remote process did not start"
+        );
+
+        controller.stop("test").await.unwrap();
+        for _ in 0..100 {
+            if let ProcessState::Stopped = controller.read_state().await.unwrap() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(matches!(
+            controller.last_exit().await.unwrap(),
+            Some(ExitReason::Stopped(_))
+        ));
+        assert!(matches!(
+            agent.status("sleeper").await.unwrap().state,
+            managed_process::RemoteState::Stopped
+        ));
+    }
+
+    #[test]
+    fn secrets_are_not_logged() {
+        let config = CommandConfig {
+            program: "llama-server".to_string(),
+            args: ["--alias", "qwen", "--api-key", "s3cr3t", "--hf-token=t0k3n"]
+                .map(String::from)
+                .to_vec(),
+            env: vec![("HF_TOKEN".to_string(), "t0k3n".to_string())],
+            working_dir: None,
+        };
+        assert_eq!(
+            redacted(&config),
+            "llama-server --alias qwen --api-key [redacted] --hf-token=[redacted] (env: HF_TOKEN)"
+        );
+    }
+
+    #[tokio::test]
+    async fn lines_are_forwarded_after_the_log_of_the_agent_wrapped() {
+        let port = serve_agent().await;
+        let agent = RemoteAgent::new("127.0.0.1", port, "secret");
+        // more lines than the agent keeps, then a line after the gateway caught up
+        let script = format!(
+            "seq 1 {} ; sleep 1 ; echo later ; exec sleep 30",
+            managed_process::DEFAULT_LOG_CAPACITY + 500
+        );
+        let controller = run_via_agent(&agent, "chatty process", &script).await;
+        assert!(
+            wait_for_line(&controller, "later").await,
+            "lines written after the log wrapped were not forwarded"
+        );
+        // lines are forwarded once
+        let lines = controller.log().lines();
+        assert_eq!(lines.iter().filter(|l| l.line == "later").count(), 1);
+        assert_eq!(lines.iter().filter(|l| l.line == "2500").count(), 1);
+        controller.stop("test").await.unwrap();
+    }
+}
@@ -0,0 +1,58 @@
+use staticmodelconfig::{ModelCatalog, compare_with_catalog, discover_models};
+use std::path::PathBuf;
+
+const DEFAULT_ROOT: &str = "/model_data/huggingface";
+
+// scans the model-directories given as arguments (default '/model_data/huggingface') and
+// reports new, missing and moved models compared with the catalog ; with '--write' the proposed
+// configurations of new models are added to the catalog
+fn main() {
+    let mut write = false;
+    let mut roots = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--write" => write = true,
+            root => roots.push(PathBuf::from(root)),
+        }
+    }
+    if roots.is_empty() {
+        roots.push(PathBuf::from(DEFAULT_ROOT));
+    }
+
+    let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    dir.push("static_config_files");
+    let mut catalog = ModelCatalog::load(&dir)
+        .unwrap_or_else(|e| panic!("error reading model-catalog from dir {dir:#?}: {e}"));
+
+    let discovered_models = discover_models(&roots)
+        .unwrap_or_else(|e| panic!("error scanning model-directories {roots:#?}: {e}"));
+    let report = compare_with_catalog(&discovered_models, &catalog.configurations());
+
+    for (alias, path) in &report.missing {
+        println!("missing: '{alias}' refers to '{path}' which does not exist");
+    }
+    for moved in &report.moved {
+        println!(
+            "moved: '{}' from '{}' to '{}'",
+            moved.alias, moved.old_path, moved.new_path
+        );
+    }
+    for (path, reason) in &report.unreadable {
+        println!("unreadable: '{}': {reason}", path.display());
+    }
+    for model_configuration in report.new {
+        println!(
+            "new: '{}' ({}, max-ctx-size {}, capabilities {:?})",
+            model_configuration.alias,
+            model_configuration.model_path,
+            u64::from(&model_configuration.max_ctx_size),
+            model_configuration.capabilities
+        );
+        if write {
+            let alias = model_configuration.alias.clone();
+            if let Err(e) = catalog.create(model_configuration) {
+                println!("skipping '{alias}': {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use staticmodelconfig::ModelCatalog;
+use std::path::PathBuf;
+
+// prints the configurations with the given aliases (all if none are given) as resolved from their
+// json-files, i.e. with everything taken from their bases and variant-overlays
+fn main() {
+    let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    dir.push("static_config_files");
+    let catalog = ModelCatalog::load(&dir)
+        .unwrap_or_else(|e| panic!("error reading model-catalog from dir {dir:#?}: {e}"));
+
+    let aliases: Vec<String> = std::env::args().skip(1).collect();
+    for model_configuration in catalog.configurations() {
+        if !aliases.is_empty() && !aliases.contains(&model_configuration.alias) {
+            continue;
+        }
+        if let Some(base) = catalog.variant_of(&model_configuration.alias) {
+            println!("// variant of '{base}'");
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&model_configuration)
+                .unwrap_or_else(|e| panic!("error serializing model_configuration: {e}"))
+        );
+    }
+    for alias in aliases {
+        if catalog.get(&alias).is_none() {
+            eprintln!("no model with alias '{alias}'");
+        }
+    }
+}
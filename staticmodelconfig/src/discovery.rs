@@ -0,0 +1,441 @@
+use crate::{ContextSizeAwareAlias, Error, GgufMetadata, ModelConfiguration, Result};
+use inference_backends::ContextSize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+// proposed configurations never exceed the largest context-size of the model-list
+const MAX_PROPOSED_CTX_SIZE: u64 = 262144;
+// multimodal projectors are picked in this order if a directory holds several of them
+const MMPROJ_PREFERENCE: [&str; 3] = ["bf16", "f16", "f32"];
+
+/// a model found in a model-directory ; split models are grouped by their first shard
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredModel {
+    // the (first shard of the) model
+    pub model_path: PathBuf,
+    // all shards in order (just the model for models which are not split)
+    pub shards: Vec<PathBuf>,
+    // the projector of the model's directory (or one of its parents within the root)
+    pub mmproj_path: Option<PathBuf>,
+}
+
+/// a catalog-entry whose model-file was found at another location
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovedModel {
+    pub alias: String,
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// the result of comparing the discovered models with the catalog
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryReport {
+    // proposals for models no catalog-entry refers to
+    pub new: Vec<ModelConfiguration>,
+    // catalog-entries (alias, path) referring to files which do not exist
+    pub missing: Vec<(String, String)>,
+    pub moved: Vec<MovedModel>,
+    // models which could not be read (path, reason)
+    pub unreadable: Vec<(PathBuf, String)>,
+}
+
+/// scans the roots (e.g. '/model_data/huggingface' holding '<org>/<repo>/...gguf') for models ;
+/// shards other than the first ones and projectors are not reported as models of their own
+pub fn discover_models(roots: &[PathBuf]) -> Result<Vec<DiscoveredModel>> {
+    let mut models = Vec::new();
+    for root in roots {
+        let mut gguf_files = Vec::new();
+        collect_gguf_files(root, &mut gguf_files)?;
+        gguf_files.sort();
+
+        let mut split_models = BTreeMap::<String, Vec<(u64, PathBuf)>>::new();
+        for gguf_file in gguf_files.iter().filter(|f| !is_mmproj_file(f)) {
+            match crate::split_model_prefix(gguf_file) {
+                Some((prefix, n)) => split_models
+                    .entry(prefix)
+                    .or_default()
+                    .push((n, gguf_file.clone())),
+                None => models.push(DiscoveredModel {
+                    model_path: gguf_file.clone(),
+                    shards: vec![gguf_file.clone()],
+                    mmproj_path: find_mmproj(gguf_file, root, &gguf_files),
+                }),
+            }
+        }
+        for mut shards in split_models.into_values() {
+            shards.sort();
+            let shards: Vec<PathBuf> = shards.into_iter().map(|(_, shard)| shard).collect();
+            models.push(DiscoveredModel {
+                model_path: shards[0].clone(),
+                mmproj_path: find_mmproj(&shards[0], root, &gguf_files),
+                shards,
+            });
+        }
+    }
+    models.sort_by(|a, b| a.model_path.cmp(&b.model_path));
+    Ok(models)
+}
+
+fn collect_gguf_files(dir: &Path, gguf_files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).map_err(Error::IoError)? {
+        let path = entry.map_err(Error::IoError)?.path();
+        if path.is_dir() {
+            collect_gguf_files(&path, gguf_files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "gguf")
+        {
+            gguf_files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_mmproj_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().to_lowercase().starts_with("mmproj"))
+}
+
+// quantizations of a repo are often kept in sub-directories while the projector is shared
+fn find_mmproj(model_path: &Path, root: &Path, gguf_files: &[PathBuf]) -> Option<PathBuf> {
+    let mut dir = model_path.parent();
+    while let Some(current_dir) = dir {
+        let mut candidates: Vec<&PathBuf> = gguf_files
+            .iter()
+            .filter(|f| f.parent() == Some(current_dir) && is_mmproj_file(f))
+            .collect();
+        candidates.sort_by_key(|candidate| {
+            let name = candidate.to_string_lossy().to_lowercase();
+            MMPROJ_PREFERENCE
+                .iter()
+                .position(|preferred| name.contains(preferred))
+                .unwrap_or(MMPROJ_PREFERENCE.len())
+        });
+        if let Some(mmproj) = candidates.first() {
+            return Some((*mmproj).clone());
+        }
+        if current_dir == root {
+            break;
+        }
+        dir = current_dir.parent();
+    }
+    None
+}
+
+impl DiscoveredModel {
+    /// the alias is derived from the file-name (e.g. 'gemma-4-12b-it-UD-Q8_K_XL.gguf' becomes
+    /// 'gemma-4-12b-it-ud-q8-k-xl') ; metadata, capabilities and the context-size are read from
+    /// the gguf-files
+    pub fn propose_configuration(&self) -> Result<ModelConfiguration> {
+        let model = GgufMetadata::read(&self.model_path)?;
+        let mmproj = self
+            .mmproj_path
+            .as_deref()
+            .map(GgufMetadata::read)
+            .transpose()?;
+
+        let mut model_configuration = ModelConfiguration {
+            alias: self.alias(),
+            extends: None,
+            model_path: self.model_path.to_string_lossy().into_owned(),
+            max_ctx_size: ContextSize::T8192,
+            vocab_type: 0,
+            n_vocab: 0,
+            n_ctx_train: 0,
+            n_embd: 0,
+            n_params: 0,
+            size: 0,
+            capabilities: Vec::new(),
+            architecture: None,
+            quantization: None,
+            chat_template: None,
+            mmproj_path: None,
+            model_draft: None,
+            keep_alive: None,
+            prio: None,
+            n_gpu_layers: None,
+            flash_attn: None,
+            fit: None,
+            batch_size: None,
+            ubatch_size: None,
+            cache_type_v: None,
+            cache_ram: None,
+            cache_reuse: None,
+            spec_type: None,
+            spec_draft_n_max: None,
+            cache_type_k: None,
+            temp: None,
+            repeat_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            min_p: None,
+            top_k: None,
+            top_p: None,
+            jinja: false,
+            no_mmap: false,
+            mlock: false,
+            no_warmup: false,
+            no_context_shift: false,
+            no_cont_batching: false,
+            chat_template_kwargs: None,
+            reasoning: None,
+            reasoning_budget: None,
+            embeddings: false,
+            no_cache_prompt: false,
+            resource_limits: None,
+            backend_host: None,
+        };
+        model_configuration.apply_gguf_metadata(&model, mmproj.as_ref())?;
+        model_configuration.max_ctx_size = model_configuration
+            .n_ctx_train
+            .min(MAX_PROPOSED_CTX_SIZE)
+            .into();
+        model_configuration.jinja =
+            !model_configuration.embeddings && model.chat_template().is_some();
+        if !model_configuration.embeddings {
+            model_configuration.mmproj_path = self
+                .mmproj_path
+                .as_ref()
+                .map(|mmproj_path| mmproj_path.to_string_lossy().into_owned());
+        }
+        Ok(model_configuration)
+    }
+
+    fn alias(&self) -> String {
+        let file_name = self
+            .model_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = match crate::split_model_prefix(Path::new(&file_name)) {
+            Some((prefix, _)) => prefix,
+            None => file_name.trim_end_matches(".gguf").to_owned(),
+        };
+        let alias: String = stem
+            .to_lowercase()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        // the alias must not be mistaken for a context-size-aware alias
+        if ContextSizeAwareAlias::try_from(alias.clone()).is_ok() {
+            format!("{alias}-model")
+        } else {
+            alias
+        }
+    }
+}
+
+/// compares the discovered models with the catalog ; files of catalog-entries which do not exist
+/// anymore are reported as moved if a model with the same file-name was discovered
+pub fn compare_with_catalog(
+    discovered_models: &[DiscoveredModel],
+    model_configurations: &[ModelConfiguration],
+) -> DiscoveryReport {
+    let mut report = DiscoveryReport::default();
+    let mut referenced: HashSet<PathBuf> = HashSet::new();
+
+    for model_configuration in model_configurations {
+        for path in [
+            Some(&model_configuration.model_path),
+            model_configuration.mmproj_path.as_ref(),
+            model_configuration.model_draft.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if Path::new(path).is_file() {
+                referenced.insert(PathBuf::from(path));
+                continue;
+            }
+            let moved_to = (path == &model_configuration.model_path)
+                .then(|| {
+                    discovered_models.iter().find(|discovered_model| {
+                        discovered_model.model_path.file_name() == Path::new(path).file_name()
+                    })
+                })
+                .flatten();
+            match moved_to {
+                Some(discovered_model) => {
+                    referenced.insert(discovered_model.model_path.clone());
+                    report.moved.push(MovedModel {
+                        alias: model_configuration.alias.clone(),
+                        old_path: path.clone(),
+                        new_path: discovered_model.model_path.to_string_lossy().into_owned(),
+                    });
+                }
+                None => report
+                    .missing
+                    .push((model_configuration.alias.clone(), path.clone())),
+            }
+        }
+    }
+
+    let taken_aliases: HashSet<&str> = model_configurations
+        .iter()
+        .map(|model_configuration| model_configuration.alias.as_str())
+        .collect();
+    for discovered_model in discovered_models {
+        if referenced.contains(&discovered_model.model_path) {
+            continue;
+        }
+        match discovered_model.propose_configuration() {
+            Ok(mut proposal) => {
+                if taken_aliases.contains(proposal.alias.as_str()) {
+                    proposal.alias = format!("{}-new", proposal.alias);
+                }
+                report.new.push(proposal);
+            }
+            Err(e) => report
+                .unreadable
+                .push((discovered_model.model_path.clone(), e.to_string())),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{GgufValue, gguf::test::write_gguf};
+
+    #[test]
+    fn shards_are_grouped_and_projectors_paired() {
+        let root = std::env::temp_dir().join(format!("discovery-test-{}", std::process::id()));
+        let repo = root.join("org").join("Tiny-GGUF");
+        std::fs::create_dir_all(repo.join("UD-Q8_K_XL")).unwrap();
+        let llama = |context_length| {
+            vec![
+                ("general.architecture", GgufValue::String("llama".into())),
+                ("llama.context_length", GgufValue::UInt(context_length)),
+                ("llama.embedding_length", GgufValue::UInt(8)),
+                (
+                    "tokenizer.chat_template",
+                    GgufValue::String("{{ x }}".into()),
+                ),
+            ]
+        };
+        write_gguf(
+            &repo.join("Tiny-Q4_K_M.gguf"),
+            &llama(32768),
+            &[],
+            &[("w", &[8])],
+        );
+        for n in 1..=2 {
+            write_gguf(
+                &repo.join(format!("UD-Q8_K_XL/Tiny-UD-Q8_K_XL-0000{n}-of-00002.gguf")),
+                &llama(32768),
+                &[],
+                &[("w", &[8])],
+            );
+        }
+        for mmproj in ["mmproj-F32.gguf", "mmproj-BF16.gguf"] {
+            write_gguf(
+                &repo.join(mmproj),
+                &[
+                    ("general.architecture", GgufValue::String("clip".into())),
+                    ("clip.has_vision_encoder", GgufValue::Bool(true)),
+                ],
+                &[],
+                &[],
+            );
+        }
+
+        let discovered_models = discover_models(std::slice::from_ref(&root)).unwrap();
+        assert_eq!(discovered_models.len(), 2);
+        assert_eq!(discovered_models[1].shards.len(), 2);
+        assert!(
+            discovered_models
+                .iter()
+                .all(|m| m.mmproj_path == Some(repo.join("mmproj-BF16.gguf")))
+        );
+
+        // the catalog refers to the unsplit model at an old location
+        let mut moved = discovered_models[0].propose_configuration().unwrap();
+        assert_eq!(moved.alias, "tiny-q4-k-m");
+        assert_eq!(u64::from(&moved.max_ctx_size), 32768);
+        assert_eq!(moved.capabilities, ["completion", "multimodal"]);
+        assert_eq!(
+            (
+                moved.architecture.as_deref(),
+                moved.chat_template.as_deref()
+            ),
+            (Some("llama"), Some("{{ x }}"))
+        );
+        moved.model_path = "/old/location/Tiny-Q4_K_M.gguf".into();
+        let report = compare_with_catalog(&discovered_models, &[moved]);
+        assert_eq!(report.moved.len(), 1);
+        assert_eq!(
+            report
+                .new
+                .iter()
+                .map(|c| c.alias.as_str())
+                .collect::<Vec<_>>(),
+            ["tiny-ud-q8-k-xl"]
+        );
+        assert!(report.missing.is_empty() && report.unreadable.is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn configurations_are_updated_from_their_gguf_files() {
+        let dir = std::env::temp_dir().join(format!("gguf-update-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model_path = dir.join("embedder-F16.gguf");
+        write_gguf(
+            &model_path,
+            &[
+                ("general.architecture", GgufValue::String("bert".into())),
+                ("general.file_type", GgufValue::UInt(1)),
+                ("bert.context_length", GgufValue::UInt(8192)),
+                ("bert.embedding_length", GgufValue::UInt(8)),
+                ("bert.pooling_type", GgufValue::UInt(2)),
+            ],
+            &[],
+            &[("token_embd.weight", &[8, 4])],
+        );
+        let mut model_configuration: ModelConfiguration =
+            serde_json::from_value(serde_json::json!({
+                "alias": "embedder",
+                "model-path": model_path,
+                "max-ctx-size": 8192,
+                "vocab-type": 0,
+                "n-vocab": 0,
+                "n-ctx-train": 0,
+                "n-embd": 0,
+                "n-params": 0,
+                "size": 0,
+                "capabilities": ["completion", "multimodal", "tools"]
+            }))
+            .unwrap();
+
+        model_configuration.update_from_gguf_files().unwrap();
+        // capabilities set by hand are kept
+        assert_eq!(model_configuration.capabilities, ["embedding", "tools"]);
+        assert!(model_configuration.embeddings);
+        assert_eq!(
+            (
+                model_configuration.architecture.as_deref(),
+                model_configuration.quantization.as_deref(),
+                model_configuration.chat_template.as_deref()
+            ),
+            (Some("bert"), Some("F16"), None)
+        );
+        assert_eq!(
+            (
+                model_configuration.n_ctx_train,
+                model_configuration.n_params,
+                model_configuration.size
+            ),
+            (8192, 32, 32 * 4)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
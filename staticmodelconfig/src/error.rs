@@ -7,6 +7,14 @@ pub enum Error {
     NotAJsonFile(PathBuf),
     IoError(std::io::Error),
     DeserializationError(String),
+    // the configuration is not valid ; provides the reason
+    InvalidConfiguration(String),
+    // the catalog holds a configuration with the alias already
+    AliasTaken(String),
+    // the catalog holds no configuration with the alias
+    UnknownAlias(String),
+    // the file is no (supported) gguf-file ; provides the reason
+    InvalidGguf(PathBuf, String),
 }
 
 impl Display for Error {
@@ -15,6 +23,12 @@ impl Display for Error {
             Self::NotAJsonFile(p) => write!(f, "the provided PathBuf {p:#?} is not a json-file"),
             Self::IoError(e) => write!(f, "nested io error: {e}"),
             Self::DeserializationError(text) => write!(f, "{text}"),
+            Self::InvalidConfiguration(reason) => {
+                write!(f, "invalid model-configuration: {reason}")
+            }
+            Self::AliasTaken(alias) => write!(f, "a model with alias '{alias}' exists already"),
+            Self::UnknownAlias(alias) => write!(f, "no model with alias '{alias}'"),
+            Self::InvalidGguf(p, reason) => write!(f, "invalid gguf-file {p:#?}: {reason}"),
         }
     }
 }
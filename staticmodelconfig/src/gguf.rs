@@ -0,0 +1,688 @@
+use crate::{Error, Result};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+// strings (keys, values) longer than this are considered corrupt
+const MAX_STRING_LEN: u64 = 64 * 1024 * 1024;
+// arrays nested deeper than this are considered corrupt (llama.cpp does not nest them at all)
+const MAX_ARRAY_DEPTH: u32 = 8;
+
+/// a metadata-value of a gguf-file ; arrays are skipped while reading, only the type and the
+/// number of their elements are kept
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array { element_type: u32, len: u64 },
+}
+
+impl GgufValue {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::UInt(v) => Some(*v),
+            Self::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+/// describes a tensor of a gguf-file (its data is not read)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GgufTensor {
+    pub name: String,
+    pub dims: Vec<u64>,
+    // the ggml-type of the tensor
+    pub ggml_type: u32,
+}
+
+impl GgufTensor {
+    /// none if the number overflows (such tensors are rejected when reading the file)
+    pub fn n_elements(&self) -> Option<u64> {
+        self.dims
+            .iter()
+            .try_fold(1_u64, |n_elements, dim| n_elements.checked_mul(*dim))
+    }
+}
+
+/// the header of a gguf-file (metadata and tensor-infos) ; read without loading the tensor-data,
+/// so reading even large models takes only milliseconds
+#[derive(Debug, Clone)]
+pub struct GgufMetadata {
+    pub version: u32,
+    pub metadata: BTreeMap<String, GgufValue>,
+    pub tensors: Vec<GgufTensor>,
+    // elements of all tensors (of all shards for split models)
+    pub parameter_count: u64,
+    // bytes of the tensor-data (of all shards for split models)
+    pub size: u64,
+}
+
+impl GgufMetadata {
+    /// reads a model ; for split models ('...-00001-of-00003.gguf') the tensors of all shards are
+    /// read (the metadata is taken from the first shard)
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut gguf_metadata = Self::read_file(path)?;
+        for shard in gguf_metadata.other_shards(path)? {
+            let shard_metadata = Self::read_file(&shard)?;
+            gguf_metadata.tensors.extend(shard_metadata.tensors);
+            gguf_metadata.parameter_count = gguf_metadata
+                .parameter_count
+                .checked_add(shard_metadata.parameter_count)
+                .ok_or_else(|| too_large(path, "the parameter-count"))?;
+            gguf_metadata.size = gguf_metadata
+                .size
+                .checked_add(shard_metadata.size)
+                .ok_or_else(|| too_large(path, "the tensor-data"))?;
+        }
+        Ok(gguf_metadata)
+    }
+
+    /// reads a single file (a shard of a split model is read on its own)
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(Error::IoError)?;
+        let file_len = file.metadata().map_err(Error::IoError)?.len();
+        let mut reader = GgufReader {
+            inner: BufReader::new(file),
+            path,
+        };
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != GGUF_MAGIC {
+            return Err(reader.invalid("not a gguf-file"));
+        }
+        let version = reader.read_u32()?;
+        if !(2..=3).contains(&version) {
+            return Err(reader.invalid(&format!("unsupported gguf-version {version}")));
+        }
+        let tensor_count = reader.read_u64()?;
+        let metadata_count = reader.read_u64()?;
+
+        let mut metadata = BTreeMap::new();
+        for _ in 0..metadata_count {
+            let key = reader.read_string()?;
+            let value_type = reader.read_u32()?;
+            let value = reader.read_value(value_type)?;
+            metadata.insert(key, value);
+        }
+
+        let mut tensors = Vec::new();
+        let mut parameter_count = 0_u64;
+        for _ in 0..tensor_count {
+            let name = reader.read_string()?;
+            let n_dims = reader.read_u32()?;
+            if n_dims > 8 {
+                return Err(reader.invalid(&format!("tensor '{name}' has {n_dims} dimensions")));
+            }
+            let dims = (0..n_dims)
+                .map(|_| reader.read_u64())
+                .collect::<Result<Vec<_>>>()?;
+            let ggml_type = reader.read_u32()?;
+            // the offset of the tensor-data
+            reader.read_u64()?;
+            let tensor = GgufTensor {
+                name,
+                dims,
+                ggml_type,
+            };
+            parameter_count = tensor
+                .n_elements()
+                .and_then(|n_elements| parameter_count.checked_add(n_elements))
+                .ok_or_else(|| reader.invalid(&format!("tensor '{}' is too large", tensor.name)))?;
+            tensors.push(tensor);
+        }
+
+        // the size of tensors of unknown types is estimated by the length of the data-section
+        let size = match reader.tensor_data_len(&tensors)? {
+            Some(size) => size,
+            None => {
+                let alignment = metadata
+                    .get("general.alignment")
+                    .and_then(GgufValue::as_u64)
+                    .filter(|alignment| *alignment > 0)
+                    .unwrap_or(32);
+                let header_len = reader.inner.stream_position().map_err(Error::IoError)?;
+                file_len.saturating_sub(header_len.div_ceil(alignment) * alignment)
+            }
+        };
+
+        Ok(Self {
+            version,
+            metadata,
+            tensors,
+            parameter_count,
+            size,
+        })
+    }
+
+    fn other_shards(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let Some(split_count) = self.get_u64("split.count").filter(|count| *count > 1) else {
+            return Ok(Vec::new());
+        };
+        let Some((prefix, _)) = split_model_prefix(path) else {
+            return Err(Error::InvalidGguf(
+                path.to_path_buf(),
+                format!("split into {split_count} shards but not named like one"),
+            ));
+        };
+        Ok((2..=split_count)
+            .map(|n| PathBuf::from(format!("{prefix}-{n:05}-of-{split_count:05}.gguf")))
+            .collect())
+    }
+
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.metadata.get(key).and_then(GgufValue::as_u64)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).and_then(GgufValue::as_str)
+    }
+
+    // keys specific to the architecture are prefixed with its name
+    fn get_arch_u64(&self, key: &str) -> Option<u64> {
+        self.get_u64(&format!("{}.{key}", self.architecture()?))
+    }
+
+    pub fn architecture(&self) -> Option<&str> {
+        self.get_str("general.architecture")
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.get_str("general.name")
+    }
+
+    pub fn context_length(&self) -> Option<u64> {
+        self.get_arch_u64("context_length")
+    }
+
+    pub fn embedding_length(&self) -> Option<u64> {
+        self.get_arch_u64("embedding_length")
+    }
+
+    /// set for models computing embeddings
+    pub fn pooling_type(&self) -> Option<u64> {
+        self.get_arch_u64("pooling_type")
+    }
+
+    /// the quantization of the model as named by llama.cpp (e.g. 'Q4_K_M')
+    pub fn quantization(&self) -> Option<&'static str> {
+        file_type_name(self.get_u64("general.file_type")?)
+    }
+
+    pub fn chat_template(&self) -> Option<&str> {
+        self.get_str("tokenizer.chat_template")
+    }
+
+    pub fn n_vocab(&self) -> Option<u64> {
+        match self.metadata.get("tokenizer.ggml.tokens")? {
+            GgufValue::Array { len, .. } => Some(*len),
+            _ => None,
+        }
+    }
+
+    /// the vocab-type as numbered by llama.cpp (as reported by llama-server)
+    pub fn vocab_type(&self) -> Option<u8> {
+        Some(match self.get_str("tokenizer.ggml.model")? {
+            "no_vocab" | "none" => 0,
+            "llama" => 1,
+            "gpt2" => 2,
+            "bert" => 3,
+            "t5" => 4,
+            "rwkv" => 5,
+            "plamo2" => 6,
+            _ => return None,
+        })
+    }
+
+    /// multimodal projectors ('mmproj-*.gguf') are stored with the architecture 'clip'
+    pub fn is_mmproj(&self) -> bool {
+        self.architecture() == Some("clip")
+    }
+
+    pub fn has_vision_encoder(&self) -> bool {
+        self.metadata
+            .get("clip.has_vision_encoder")
+            .and_then(GgufValue::as_bool)
+            .unwrap_or(false)
+    }
+
+    pub fn has_audio_encoder(&self) -> bool {
+        self.metadata
+            .get("clip.has_audio_encoder")
+            .and_then(GgufValue::as_bool)
+            .unwrap_or(false)
+    }
+}
+
+/// splits the path of a shard of a split model ('<prefix>-00001-of-00003.gguf') into the prefix
+/// (including the directory) and the number of the shard
+pub fn split_model_prefix(path: &Path) -> Option<(String, u64)> {
+    let stem = path.to_str()?.strip_suffix(".gguf")?;
+    let (rest, count) = stem.rsplit_once("-of-")?;
+    let (prefix, n) = rest.rsplit_once('-')?;
+    if n.len() != 5 || count.len() != 5 {
+        return None;
+    }
+    count.parse::<u64>().ok()?;
+    Some((prefix.to_owned(), n.parse().ok()?))
+}
+
+fn too_large(path: &Path, what: &str) -> Error {
+    Error::InvalidGguf(path.to_path_buf(), format!("{what} is too large"))
+}
+
+struct GgufReader<'a> {
+    inner: BufReader<File>,
+    path: &'a Path,
+}
+
+impl GgufReader<'_> {
+    fn invalid(&self, reason: &str) -> Error {
+        Error::InvalidGguf(self.path.to_path_buf(), reason.to_owned())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_exact(buf).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                self.invalid("truncated header")
+            } else {
+                Error::IoError(e)
+            }
+        })
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let len = self.read_u64()?;
+        if len > MAX_STRING_LEN {
+            return Err(self.invalid(&format!("string of {len} bytes")));
+        }
+        let mut buf = vec![0; len as usize];
+        self.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|_| self.invalid("string is not utf-8"))
+    }
+
+    fn read_value(&mut self, value_type: u32) -> Result<GgufValue> {
+        Ok(match value_type {
+            0 => GgufValue::UInt(u8::from_le_bytes(self.read_bytes()?).into()),
+            1 => GgufValue::Int(i8::from_le_bytes(self.read_bytes()?).into()),
+            2 => GgufValue::UInt(u16::from_le_bytes(self.read_bytes()?).into()),
+            3 => GgufValue::Int(i16::from_le_bytes(self.read_bytes()?).into()),
+            4 => GgufValue::UInt(self.read_u32()?.into()),
+            5 => GgufValue::Int(i32::from_le_bytes(self.read_bytes()?).into()),
+            6 => GgufValue::Float(f32::from_le_bytes(self.read_bytes()?).into()),
+            7 => GgufValue::Bool(self.read_bytes::<1>()?[0] != 0),
+            8 => GgufValue::String(self.read_string()?),
+            9 => {
+                let element_type = self.read_u32()?;
+                let len = self.read_u64()?;
+                self.skip_array(element_type, len, 1)?;
+                GgufValue::Array { element_type, len }
+            }
+            10 => GgufValue::UInt(self.read_u64()?),
+            11 => GgufValue::Int(i64::from_le_bytes(self.read_bytes()?)),
+            12 => GgufValue::Float(f64::from_le_bytes(self.read_bytes()?)),
+            _ => return Err(self.invalid(&format!("unknown value-type {value_type}"))),
+        })
+    }
+
+    fn skip_array(&mut self, element_type: u32, len: u64, depth: u32) -> Result<()> {
+        if depth > MAX_ARRAY_DEPTH {
+            return Err(self.invalid(&format!("arrays nested more than {MAX_ARRAY_DEPTH} deep")));
+        }
+        let element_size: u64 = match element_type {
+            0 | 1 | 7 => 1,
+            2 | 3 => 2,
+            4..=6 => 4,
+            10..=12 => 8,
+            8 => {
+                for _ in 0..len {
+                    let string_len = self.read_u64()?;
+                    self.skip(string_len)?;
+                }
+                return Ok(());
+            }
+            9 => {
+                for _ in 0..len {
+                    let nested_type = self.read_u32()?;
+                    let nested_len = self.read_u64()?;
+                    self.skip_array(nested_type, nested_len, depth + 1)?;
+                }
+                return Ok(());
+            }
+            _ => return Err(self.invalid(&format!("unknown array-type {element_type}"))),
+        };
+        let n_bytes = len
+            .checked_mul(element_size)
+            .ok_or_else(|| self.invalid("array too large"))?;
+        self.skip(n_bytes)
+    }
+
+    // none if a tensor is of a type unknown to this reader
+    fn tensor_data_len(&self, tensors: &[GgufTensor]) -> Result<Option<u64>> {
+        let mut len = 0_u64;
+        for tensor in tensors {
+            let Some((block_size, type_size)) = ggml_type_size(tensor.ggml_type) else {
+                return Ok(None);
+            };
+            len = tensor
+                .n_elements()
+                .and_then(|n_elements| (n_elements / block_size).checked_mul(type_size))
+                .and_then(|n_bytes| len.checked_add(n_bytes))
+                .ok_or_else(|| too_large(self.path, "the tensor-data"))?;
+        }
+        Ok(Some(len))
+    }
+
+    fn skip(&mut self, n_bytes: u64) -> Result<()> {
+        let offset = i64::try_from(n_bytes).map_err(|_| self.invalid("value too large"))?;
+        self.inner.seek_relative(offset).map_err(Error::IoError)
+    }
+}
+
+// block-size and bytes per block of the ggml-types
+fn ggml_type_size(ggml_type: u32) -> Option<(u64, u64)> {
+    Some(match ggml_type {
+        0 => (1, 4),      // F32
+        1 => (1, 2),      // F16
+        2 => (32, 18),    // Q4_0
+        3 => (32, 20),    // Q4_1
+        6 => (32, 22),    // Q5_0
+        7 => (32, 24),    // Q5_1
+        8 => (32, 34),    // Q8_0
+        9 => (32, 36),    // Q8_1
+        10 => (256, 84),  // Q2_K
+        11 => (256, 110), // Q3_K
+        12 => (256, 144), // Q4_K
+        13 => (256, 176), // Q5_K
+        14 => (256, 210), // Q6_K
+        15 => (256, 292), // Q8_K
+        16 => (256, 66),  // IQ2_XXS
+        17 => (256, 74),  // IQ2_XS
+        18 => (256, 98),  // IQ3_XXS
+        19 => (256, 50),  // IQ1_S
+        20 => (32, 18),   // IQ4_NL
+        21 => (256, 110), // IQ3_S
+        22 => (256, 82),  // IQ2_S
+        23 => (256, 136), // IQ4_XS
+        24 => (1, 1),     // I8
+        25 => (1, 2),     // I16
+        26 => (1, 4),     // I32
+        27 => (1, 8),     // I64
+        28 => (1, 8),     // F64
+        29 => (256, 56),  // IQ1_M
+        30 => (1, 2),     // BF16
+        34 => (256, 54),  // TQ1_0
+        35 => (256, 66),  // TQ2_0
+        39 => (32, 17),   // MXFP4
+        _ => return None,
+    })
+}
+
+// the names of llama.cpp's file-types ('general.file_type')
+fn file_type_name(file_type: u64) -> Option<&'static str> {
+    Some(match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        36 => "TQ1_0",
+        37 => "TQ2_0",
+        38 => "MXFP4_MOE",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    // builds a gguf-file (version 3) with the given metadata and f32-tensors
+    pub(crate) fn write_gguf(
+        path: &Path,
+        metadata: &[(&str, GgufValue)],
+        string_arrays: &[(&str, &[&str])],
+        tensors: &[(&str, &[u64])],
+    ) {
+        fn string(buf: &mut Vec<u8>, s: &str) {
+            buf.extend((s.len() as u64).to_le_bytes());
+            buf.extend(s.as_bytes());
+        }
+        let mut buf = Vec::new();
+        buf.extend(GGUF_MAGIC);
+        buf.extend(3u32.to_le_bytes());
+        buf.extend((tensors.len() as u64).to_le_bytes());
+        buf.extend(((metadata.len() + string_arrays.len()) as u64).to_le_bytes());
+        for (key, value) in metadata {
+            string(&mut buf, key);
+            match value {
+                GgufValue::UInt(v) => {
+                    buf.extend(4u32.to_le_bytes());
+                    buf.extend((*v as u32).to_le_bytes());
+                }
+                GgufValue::Int(v) => {
+                    buf.extend(11u32.to_le_bytes());
+                    buf.extend(v.to_le_bytes());
+                }
+                GgufValue::Float(v) => {
+                    buf.extend(12u32.to_le_bytes());
+                    buf.extend(v.to_le_bytes());
+                }
+                GgufValue::Bool(v) => {
+                    buf.extend(7u32.to_le_bytes());
+                    buf.push(*v as u8);
+                }
+                GgufValue::String(v) => {
+                    buf.extend(8u32.to_le_bytes());
+                    string(&mut buf, v);
+                }
+                // arrays are given as 'string_arrays'
+                other => panic!("unsupported test value {other:?}"),
+            }
+        }
+        for (key, values) in string_arrays {
+            string(&mut buf, key);
+            buf.extend(9u32.to_le_bytes());
+            buf.extend(8u32.to_le_bytes());
+            buf.extend((values.len() as u64).to_le_bytes());
+            for value in values.iter() {
+                string(&mut buf, value);
+            }
+        }
+        let mut offset = 0_u64;
+        for (name, dims) in tensors {
+            string(&mut buf, name);
+            buf.extend((dims.len() as u32).to_le_bytes());
+            for dim in dims.iter() {
+                buf.extend(dim.to_le_bytes());
+            }
+            buf.extend(0u32.to_le_bytes());
+            buf.extend(offset.to_le_bytes());
+            // tensors with overflowing sizes are written as well (to be rejected when reading)
+            offset = dims
+                .iter()
+                .fold(4_u64, |n_bytes, dim| n_bytes.wrapping_mul(*dim))
+                .wrapping_add(offset);
+        }
+        std::fs::write(path, buf).unwrap();
+    }
+
+    #[test]
+    fn metadata_of_split_models_and_projectors_is_read() {
+        let dir = std::env::temp_dir().join(format!("gguf-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let arch = |s: &str| GgufValue::String(s.to_owned());
+        write_gguf(
+            &dir.join("tiny-00001-of-00002.gguf"),
+            &[
+                ("general.architecture", arch("llama")),
+                ("general.file_type", GgufValue::UInt(15)),
+                ("llama.context_length", GgufValue::UInt(4096)),
+                ("llama.embedding_length", GgufValue::UInt(8)),
+                ("llama.pooling_type", GgufValue::Int(1)),
+                ("llama.rope.freq_base", GgufValue::Float(10000.0)),
+                ("split.count", GgufValue::UInt(2)),
+                ("tokenizer.ggml.model", arch("gpt2")),
+                ("tokenizer.chat_template", arch("{{ messages }}")),
+            ],
+            &[("tokenizer.ggml.tokens", &["a", "b", "c"])],
+            &[("token_embd.weight", &[8, 3])],
+        );
+        write_gguf(
+            &dir.join("tiny-00002-of-00002.gguf"),
+            &[("split.count", GgufValue::UInt(2))],
+            &[],
+            &[("output.weight", &[8, 3]), ("output_norm.weight", &[8])],
+        );
+        write_gguf(
+            &dir.join("mmproj-F32.gguf"),
+            &[
+                ("general.architecture", arch("clip")),
+                ("clip.has_vision_encoder", GgufValue::Bool(true)),
+            ],
+            &[],
+            &[],
+        );
+
+        let model = GgufMetadata::read(&dir.join("tiny-00001-of-00002.gguf")).unwrap();
+        assert_eq!(model.architecture(), Some("llama"));
+        assert_eq!(model.context_length(), Some(4096));
+        assert_eq!(model.embedding_length(), Some(8));
+        assert_eq!(model.pooling_type(), Some(1));
+        assert_eq!(
+            model.metadata.get("llama.rope.freq_base"),
+            Some(&GgufValue::Float(10000.0))
+        );
+        assert_eq!(model.quantization(), Some("Q4_K_M"));
+        assert_eq!(model.chat_template(), Some("{{ messages }}"));
+        assert_eq!((model.vocab_type(), model.n_vocab()), (Some(2), Some(3)));
+        assert_eq!(model.parameter_count, 56);
+        assert_eq!(model.size, 56 * 4);
+        assert!(!model.is_mmproj());
+
+        let mmproj = GgufMetadata::read(&dir.join("mmproj-F32.gguf")).unwrap();
+        assert!(mmproj.is_mmproj() && mmproj.has_vision_encoder() && !mmproj.has_audio_encoder());
+
+        std::fs::write(dir.join("broken.gguf"), b"GGUF\x03\x00").unwrap();
+        assert!(matches!(
+            GgufMetadata::read(&dir.join("broken.gguf")),
+            Err(Error::InvalidGguf(..))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn oversized_tensors_and_deeply_nested_arrays_are_rejected() {
+        let dir = std::env::temp_dir().join(format!("gguf-limits-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let invalid = |file_name: &str| {
+            matches!(
+                GgufMetadata::read(&dir.join(file_name)),
+                Err(Error::InvalidGguf(..))
+            )
+        };
+
+        write_gguf(
+            &dir.join("many-elements.gguf"),
+            &[],
+            &[],
+            &[("token_embd.weight", &[u64::MAX, 2])],
+        );
+        assert!(invalid("many-elements.gguf"));
+        write_gguf(
+            &dir.join("many-parameters.gguf"),
+            &[],
+            &[],
+            &[("a.weight", &[u64::MAX]), ("b.weight", &[1])],
+        );
+        assert!(invalid("many-parameters.gguf"));
+        // the element-count fits but not the bytes of the f32-values
+        write_gguf(
+            &dir.join("many-bytes.gguf"),
+            &[],
+            &[],
+            &[("token_embd.weight", &[u64::MAX / 2])],
+        );
+        assert!(invalid("many-bytes.gguf"));
+
+        let mut buf = Vec::new();
+        buf.extend(GGUF_MAGIC);
+        buf.extend(3u32.to_le_bytes());
+        buf.extend(0u64.to_le_bytes());
+        buf.extend(1u64.to_le_bytes());
+        buf.extend(6u64.to_le_bytes());
+        buf.extend(b"nested");
+        buf.extend(9u32.to_le_bytes());
+        for _ in 0..=MAX_ARRAY_DEPTH {
+            buf.extend(9u32.to_le_bytes());
+            buf.extend(1u64.to_le_bytes());
+        }
+        buf.extend(4u32.to_le_bytes());
+        buf.extend(0u64.to_le_bytes());
+        std::fs::write(dir.join("nested.gguf"), &buf).unwrap();
+        assert!(invalid("nested.gguf"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
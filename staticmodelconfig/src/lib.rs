@@ -1,6 +1,14 @@
+mod discovery;
+pub use discovery::{
+    DiscoveredModel, DiscoveryReport, MovedModel, compare_with_catalog, discover_models,
+};
+mod gguf;
+pub use gguf::{GgufMetadata, GgufTensor, GgufValue, split_model_prefix};
 mod model;
 pub use model::contextsizeawarealias::ContextSizeAwareAlias;
-pub use model::modelconfiguration::ModelConfiguration;
+pub use model::modelcatalog::ModelCatalog;
+pub use model::modelconfiguration::{ModelConfiguration, ResourceLimitsConfiguration};
 pub use model::modellist::ModelList;
+pub use model::modelroutes::{ModelRoute, ModelRoutes};
 mod error;
 pub use error::{Error, Result};
@@ -0,0 +1,290 @@
+use super::modelconfiguration::ModelConfiguration;
+use crate::{ContextSizeAwareAlias, Error, Result};
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+const ALIAS: &str = "alias";
+const EXTENDS: &str = "extends";
+// overlays by alias-suffix ; every variant is a configuration of its own
+const VARIANTS: &str = "variants";
+// abstract documents are not served themselves, just their variants and the documents extending
+// them ; unlike the other keys this one is not inherited
+const ABSTRACT: &str = "abstract";
+
+/// the content of a json-file of the catalog ; a document extending another one holds just the
+/// keys which differ from the base (top-level keys replace the ones of the base, null removes
+/// them)
+#[derive(Debug, Clone)]
+pub(crate) struct CatalogDocument {
+    pub(crate) json_file: PathBuf,
+    pub(crate) content: Map<String, Value>,
+}
+
+/// a configuration resolved from the documents
+#[derive(Debug, Clone)]
+pub(crate) struct ResolvedEntry {
+    pub(crate) model_configuration: ModelConfiguration,
+    // the alias of the document defining the variant
+    pub(crate) variant_of: Option<String>,
+    pub(crate) is_abstract: bool,
+}
+
+impl CatalogDocument {
+    pub(crate) fn load(json_file: &Path) -> Result<Self> {
+        let data = std::fs::read(json_file).map_err(Error::IoError)?;
+        let content = serde_json::from_slice::<Map<String, Value>>(&data).map_err(|e| {
+            Error::DeserializationError(format!(
+                "failed to deserialize {json_file:?} as catalog-document: {e}"
+            ))
+        })?;
+        let document = Self {
+            json_file: json_file.to_path_buf(),
+            content,
+        };
+        if document.alias().is_none() {
+            return Err(Error::DeserializationError(format!(
+                "{json_file:?} holds no alias"
+            )));
+        }
+        Ok(document)
+    }
+
+    pub(crate) fn alias(&self) -> Option<&str> {
+        self.content.get(ALIAS).and_then(Value::as_str)
+    }
+
+    pub(crate) fn extends(&self) -> Option<&str> {
+        self.content.get(EXTENDS).and_then(Value::as_str)
+    }
+
+    pub(crate) fn variants(&self) -> Option<&Value> {
+        self.content.get(VARIANTS)
+    }
+
+    pub(crate) fn set_variants(&mut self, variants: Option<Value>) {
+        match variants {
+            Some(variants) => self.content.insert(VARIANTS.to_owned(), variants),
+            None => self.content.remove(VARIANTS),
+        };
+    }
+
+    pub(crate) fn is_abstract(&self) -> bool {
+        self.content.get(ABSTRACT) == Some(&Value::Bool(true))
+    }
+
+    pub(crate) fn set_abstract(&mut self, is_abstract: bool) {
+        match is_abstract {
+            true => self.content.insert(ABSTRACT.to_owned(), Value::Bool(true)),
+            false => self.content.remove(ABSTRACT),
+        };
+    }
+
+    /// keeps the keys the previous document set to false explicitly (e.g. '"no-mmap": false'),
+    /// which the serialized configuration leaves out
+    pub(crate) fn keep_explicit_false(&mut self, previous: &CatalogDocument) {
+        for (key, value) in &previous.content {
+            if *value == Value::Bool(false) && self.content.get(key).is_none_or(Value::is_null) {
+                self.content.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    /// the document for the configuration ; if it extends another configuration just the
+    /// differences to the resolved base are kept (keys of the base missing in the configuration
+    /// are set to null, except for the variants which are inherited)
+    pub(crate) fn from_configuration(
+        model_configuration: &ModelConfiguration,
+        json_file: PathBuf,
+        documents: &BTreeMap<String, CatalogDocument>,
+    ) -> Result<Self> {
+        // the serialized configuration is read back, so floats compare equal to the ones read
+        // from json-files
+        let data = serde_json::to_vec(model_configuration)
+            .map_err(|e| Error::DeserializationError(format!("failed to serialize: {e}")))?;
+        let mut content = serde_json::from_slice::<Map<String, Value>>(&data)
+            .map_err(|e| Error::DeserializationError(format!("failed to serialize: {e}")))?;
+        if let Some(base) = &model_configuration.extends {
+            let base = resolve_document(base, documents, &mut Vec::new())?;
+            let removed: Vec<String> = base
+                .keys()
+                .filter(|key| {
+                    !matches!(key.as_str(), ALIAS | VARIANTS | ABSTRACT)
+                        && !content.contains_key(*key)
+                })
+                .cloned()
+                .collect();
+            content.retain(|key, value| {
+                matches!(key.as_str(), ALIAS | EXTENDS) || base.get(key) != Some(value)
+            });
+            for key in removed {
+                content.insert(key, Value::Null);
+            }
+        }
+        Ok(Self { json_file, content })
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        super::save_to_json_file_atomically(&self.json_file, &self.content)
+    }
+}
+
+fn merge(base: &mut Map<String, Value>, overlay: &Map<String, Value>) {
+    for (key, value) in overlay {
+        if value.is_null() {
+            base.remove(key);
+        } else {
+            base.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+// the document merged with all of its bases (without 'extends')
+fn resolve_document(
+    alias: &str,
+    documents: &BTreeMap<String, CatalogDocument>,
+    stack: &mut Vec<String>,
+) -> Result<Map<String, Value>> {
+    let invalid = |reason: String| Err(Error::InvalidConfiguration(reason));
+    if stack.iter().any(|a| a == alias) {
+        return invalid(format!(
+            "'{alias}' extends itself ({} -> {alias})",
+            stack.join(" -> ")
+        ));
+    }
+    let Some(document) = documents.get(alias) else {
+        return match stack.last() {
+            Some(extending) => invalid(format!("'{extending}' extends unknown '{alias}'")),
+            None => Err(Error::UnknownAlias(alias.to_owned())),
+        };
+    };
+    let mut resolved = match document.content.get(EXTENDS) {
+        Some(Value::String(base)) => {
+            stack.push(alias.to_owned());
+            let mut resolved = resolve_document(base, documents, stack)?;
+            stack.pop();
+            resolved.remove(ABSTRACT);
+            resolved
+        }
+        Some(Value::Null) | None => Map::new(),
+        Some(_) => return invalid(format!("'{alias}' must extend an alias")),
+    };
+    merge(&mut resolved, &document.content);
+    resolved.remove(EXTENDS);
+    Ok(resolved)
+}
+
+fn to_model_configuration(
+    mut content: Map<String, Value>,
+    alias: &str,
+    extends: Option<&str>,
+    json_file: &Path,
+) -> Result<ModelConfiguration> {
+    content.insert(ALIAS.to_owned(), Value::String(alias.to_owned()));
+    match extends {
+        Some(extends) => content.insert(EXTENDS.to_owned(), Value::String(extends.to_owned())),
+        None => content.remove(EXTENDS),
+    };
+    serde_json::from_value(Value::Object(content)).map_err(|e| {
+        Error::DeserializationError(format!(
+            "failed to deserialize '{alias}' of {json_file:?} as model_configuraton: {e}"
+        ))
+    })
+}
+
+/// resolves the configurations of all documents and their variants ('<alias>-<variant>') ; the
+/// aliases must neither collide with each other nor with context-size-aware aliases
+pub(crate) fn resolve_documents(
+    documents: &BTreeMap<String, CatalogDocument>,
+) -> Result<BTreeMap<String, ResolvedEntry>> {
+    let mut entries = BTreeMap::new();
+    for (alias, document) in documents {
+        let mut resolved = resolve_document(alias, documents, &mut Vec::new())?;
+        let variants = resolved.remove(VARIANTS);
+        let is_abstract = match resolved.remove(ABSTRACT) {
+            Some(Value::Bool(is_abstract)) => is_abstract,
+            None => false,
+            Some(_) => {
+                return Err(Error::InvalidConfiguration(format!(
+                    "'abstract' of '{alias}' must be a boolean"
+                )));
+            }
+        };
+        let extends = document.extends();
+        entries.insert(
+            alias.clone(),
+            ResolvedEntry {
+                model_configuration: to_model_configuration(
+                    resolved.clone(),
+                    alias,
+                    extends,
+                    &document.json_file,
+                )?,
+                variant_of: None,
+                is_abstract,
+            },
+        );
+
+        let variants = match variants {
+            Some(Value::Object(variants)) => variants,
+            None => continue,
+            Some(_) => {
+                return Err(Error::InvalidConfiguration(format!(
+                    "the variants of '{alias}' must be overlays by alias-suffix"
+                )));
+            }
+        };
+        for (suffix, overlay) in variants {
+            let Value::Object(overlay) = overlay else {
+                return Err(Error::InvalidConfiguration(format!(
+                    "variant '{suffix}' of '{alias}' must be an overlay"
+                )));
+            };
+            let variant_alias = format!("{alias}-{suffix}");
+            if documents.contains_key(&variant_alias) || entries.contains_key(&variant_alias) {
+                return Err(Error::InvalidConfiguration(format!(
+                    "variant '{suffix}' of '{alias}' collides with '{variant_alias}'"
+                )));
+            }
+            let mut variant = resolved.clone();
+            merge(&mut variant, &overlay);
+            variant.remove(VARIANTS);
+            entries.insert(
+                variant_alias.clone(),
+                ResolvedEntry {
+                    model_configuration: to_model_configuration(
+                        variant,
+                        &variant_alias,
+                        Some(alias),
+                        &document.json_file,
+                    )?,
+                    variant_of: Some(alias.clone()),
+                    is_abstract: false,
+                },
+            );
+        }
+    }
+    check_collisions(&entries)?;
+    Ok(entries)
+}
+
+// aliases must not be mistaken for context-size-aware aliases of other models (like 'phi-4',
+// 'phi-large' or 'phi@auto' along with 'phi')
+fn check_collisions(entries: &BTreeMap<String, ResolvedEntry>) -> Result<()> {
+    for alias in entries.keys() {
+        let model = ContextSizeAwareAlias::try_from(alias.clone())
+            .map(|caa| caa.model())
+            .ok()
+            .or_else(|| ContextSizeAwareAlias::auto_sized_model(alias).map(str::to_owned));
+        if let Some(model) = model
+            && entries.contains_key(&model)
+        {
+            return Err(Error::InvalidConfiguration(format!(
+                "'{alias}' collides with a context-size-aware alias of '{model}'"
+            )));
+        }
+    }
+    Ok(())
+}
@@ -6,32 +6,108 @@ const CTX_SIZE_HINT_T65536: &str = "moderate";
 const CTX_SIZE_HINT_T32768: &str = "small";
 const CTX_SIZE_HINT_T16384: &str = "tiny";
 const CTX_SIZE_HINT_T8192: &str = "min";
+// the context-size is picked per request (fitting the prompt)
+const CTX_SIZE_HINT_AUTO: &str = "auto";
+// 'alias@32k' ; context-size-aware aliases are generated with it
+const CTX_SIZE_SEPARATOR: char = '@';
+// 'alias-large', 'alias-32k' or 'alias-40000' ; still accepted but ambiguous for aliases ending in
+// a number ('phi-4'), which the catalog takes precedence for
+const LEGACY_CTX_SIZE_SEPARATOR: char = '-';
+// context-sizes below it are taken as part of the alias with the legacy separator ('phi-8')
+const LEGACY_MIN_CTX_SIZE: u64 = 8192;
+const KILO_TOKENS: u64 = 1024;
 
+/// a model of the catalog along with the context-size to serve it with ; written as
+/// 'alias@<context-size-hint>' (e.g. 'alias@large', 'alias@32k' or 'alias@40000') while
+/// 'alias-<context-size-hint>' is still accepted
 pub struct ContextSizeAwareAlias(String, ContextSize);
 
 impl ContextSizeAwareAlias {
+    /// the model of an alias with the 'auto' context-size-hint
+    pub fn auto_sized_model(alias: &str) -> Option<&str> {
+        [CTX_SIZE_SEPARATOR, LEGACY_CTX_SIZE_SEPARATOR]
+            .into_iter()
+            .find_map(|separator| {
+                alias
+                    .strip_suffix(CTX_SIZE_HINT_AUTO)
+                    .and_then(|model| model.strip_suffix(separator))
+            })
+    }
+
+    /// the alias of the model with the 'auto' context-size-hint
+    pub fn auto_sized_alias(model: &str) -> String {
+        format!("{model}{CTX_SIZE_SEPARATOR}{CTX_SIZE_HINT_AUTO}")
+    }
+
+    /// the deprecated form of 'auto_sized_alias' ('alias-auto')
+    pub fn legacy_auto_sized_alias(model: &str) -> String {
+        format!("{model}{LEGACY_CTX_SIZE_SEPARATOR}{CTX_SIZE_HINT_AUTO}")
+    }
+
+    /// the model and the context-size (if any) the name refers to, given the models of the
+    /// catalog ; a model of the catalog is taken as it is (so 'phi-4' is no context-size-aware
+    /// alias if it is a model itself)
+    pub fn resolve(
+        name: &str,
+        is_model: impl Fn(&str) -> bool,
+    ) -> Option<(String, Option<ContextSize>)> {
+        if is_model(name) {
+            return Some((name.to_owned(), None));
+        }
+        Self::try_from(name.to_owned())
+            .ok()
+            .filter(|caa| is_model(&caa.0))
+            .map(|caa| (caa.0, Some(caa.1)))
+    }
+
+    /// the model with the smallest context-size holding the required tokens ; the context-sizes
+    /// are the ones with a context-size-hint up to the max-ctx-size of the model and the
+    /// max-ctx-size itself (which is returned if none of them is large enough)
+    pub fn fitting(model: String, required_ctx_size: u64, max_ctx_size: ContextSize) -> Self {
+        let ctx_size = [
+            ContextSize::T8192,
+            ContextSize::T16384,
+            ContextSize::T32768,
+            ContextSize::T65536,
+            ContextSize::T131072,
+            ContextSize::T262144,
+        ]
+        .into_iter()
+        .take_while(|ctx_size| *ctx_size <= max_ctx_size)
+        .find(|ctx_size| u64::from(ctx_size) >= required_ctx_size)
+        .unwrap_or(max_ctx_size);
+        Self(model, ctx_size)
+    }
+
     pub fn model(&self) -> String {
         self.0.clone()
     }
     pub fn context_size(&self) -> ContextSize {
         self.1
     }
+    /// the deprecated form of the alias ('alias-small') for the context-sizes with a named hint
+    pub fn legacy_alias(&self) -> Option<String> {
+        let context_size_hint = match self.1 {
+            ContextSize::T262144 => CTX_SIZE_HINT_T262144,
+            ContextSize::T131072 => CTX_SIZE_HINT_T131072,
+            ContextSize::T65536 => CTX_SIZE_HINT_T65536,
+            ContextSize::T32768 => CTX_SIZE_HINT_T32768,
+            ContextSize::T16384 => CTX_SIZE_HINT_T16384,
+            ContextSize::T8192 => CTX_SIZE_HINT_T8192,
+            ContextSize::Custom(_) => return None,
+        };
+        Some(format!(
+            "{}{LEGACY_CTX_SIZE_SEPARATOR}{context_size_hint}",
+            self.0
+        ))
+    }
+
     pub fn alias(&self) -> String {
-        if let ContextSize::Custom(c) = self.context_size() {
-            format!("{}-{c}", self.0)
+        let ctx_size = u64::from(&self.1);
+        if ctx_size % KILO_TOKENS == 0 {
+            format!("{}{CTX_SIZE_SEPARATOR}{}k", self.0, ctx_size / KILO_TOKENS)
         } else {
-            let suffix = match self.context_size() {
-                ContextSize::T262144 => CTX_SIZE_HINT_T262144,
-                ContextSize::T131072 => CTX_SIZE_HINT_T131072,
-                ContextSize::T65536 => CTX_SIZE_HINT_T65536,
-                ContextSize::T32768 => CTX_SIZE_HINT_T32768,
-                ContextSize::T16384 => CTX_SIZE_HINT_T16384,
-                ContextSize::T8192 => CTX_SIZE_HINT_T8192,
-                ContextSize::Custom(_) => unreachable!(
-                    "this is handled in the then-branch of the surrounding conditional; we're in the else-branch"
-                ),
-            };
-            format!("{}-{suffix}", self.0)
+            format!("{}{CTX_SIZE_SEPARATOR}{ctx_size}", self.0)
         }
     }
 }
@@ -42,30 +118,110 @@ impl From<(String, ContextSize)> for ContextSizeAwareAlias {
     }
 }
 
+fn parse_context_size_hint(context_size_hint: &str) -> Option<ContextSize> {
+    let context_size = match context_size_hint {
+        CTX_SIZE_HINT_T262144 => ContextSize::T262144,
+        CTX_SIZE_HINT_T131072 => ContextSize::T131072,
+        CTX_SIZE_HINT_T65536 => ContextSize::T65536,
+        CTX_SIZE_HINT_T32768 => ContextSize::T32768,
+        CTX_SIZE_HINT_T16384 => ContextSize::T16384,
+        CTX_SIZE_HINT_T8192 => ContextSize::T8192,
+        custom_value => {
+            let value = match custom_value.strip_suffix('k') {
+                Some(value) => value.parse::<u64>().ok()?.checked_mul(KILO_TOKENS)?,
+                None => custom_value.parse::<u64>().ok()?,
+            };
+            ContextSize::from(value)
+        }
+    };
+    (u64::from(&context_size) > 0).then_some(context_size)
+}
+
 impl TryFrom<String> for ContextSizeAwareAlias {
     type Error = String;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        if let Some((alias, context_size_hint)) = value.rsplit_once("-") {
-            let context_size = match context_size_hint {
-                CTX_SIZE_HINT_T262144 => ContextSize::T262144,
-                CTX_SIZE_HINT_T131072 => ContextSize::T131072,
-                CTX_SIZE_HINT_T65536 => ContextSize::T65536,
-                CTX_SIZE_HINT_T32768 => ContextSize::T32768,
-                CTX_SIZE_HINT_T16384 => ContextSize::T16384,
-                CTX_SIZE_HINT_T8192 => ContextSize::T8192,
-                custom_value => {
-                    let val = custom_value.parse::<u64>().map_err(|_| format!(
-                        "value '{value}' contains invalid content-size-hint '{context_size_hint}'"
-                    ))?;
-                    ContextSize::Custom(val)
+        let (alias, context_size_hint, min_ctx_size) = match value.rsplit_once(CTX_SIZE_SEPARATOR) {
+            Some((alias, context_size_hint)) => (alias, context_size_hint, 1),
+            None => match value.rsplit_once(LEGACY_CTX_SIZE_SEPARATOR) {
+                Some((alias, context_size_hint)) => (alias, context_size_hint, LEGACY_MIN_CTX_SIZE),
+                None => {
+                    return Err(format!(
+                        "value '{value}' does not contain context-size-hint"
+                    ));
                 }
-            };
-            Ok(ContextSizeAwareAlias(alias.into(), context_size))
-        } else {
-            Err(format!(
-                "value '{value}' does not contain context-size-hint"
-            ))
+            },
+        };
+        match parse_context_size_hint(context_size_hint) {
+            Some(context_size) if !alias.is_empty() && u64::from(&context_size) >= min_ctx_size => {
+                Ok(ContextSizeAwareAlias(alias.into(), context_size))
+            }
+            _ => Err(format!(
+                "value '{value}' contains invalid content-size-hint '{context_size_hint}'"
+            )),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_smallest_fitting_context_size_up_to_the_max_ctx_size_is_picked() {
+        let fitting = |required_ctx_size, max_ctx_size| {
+            ContextSizeAwareAlias::fitting("model".to_owned(), required_ctx_size, max_ctx_size)
+                .alias()
+        };
+        assert_eq!(fitting(100, ContextSize::T131072), "model@8k");
+        assert_eq!(fitting(8193, ContextSize::T131072), "model@16k");
+        assert_eq!(fitting(70000, ContextSize::T131072), "model@128k");
+        assert_eq!(fitting(200000, ContextSize::T131072), "model@128k");
+        assert_eq!(fitting(50000, ContextSize::Custom(40000)), "model@40000");
+        assert_eq!(fitting(200000, ContextSize::from(262144)), "model@256k");
+        assert_eq!(
+            ContextSizeAwareAlias::auto_sized_model(&ContextSizeAwareAlias::auto_sized_alias(
+                "model"
+            )),
+            Some("model")
+        );
+        assert_eq!(
+            ContextSizeAwareAlias::auto_sized_model("model-auto"),
+            Some("model")
+        );
+    }
+
+    #[test]
+    fn models_of_the_catalog_take_precedence_over_context_size_hints() {
+        let models = ["phi", "phi-4", "qwen3-8b"];
+        let resolve = |name: &str| {
+            ContextSizeAwareAlias::resolve(name, |model| models.contains(&model))
+                .map(|(model, ctx_size)| (model, ctx_size.map(|c| u64::from(&c))))
+        };
+        assert_eq!(resolve("phi-4"), Some(("phi-4".to_owned(), None)));
+        assert_eq!(
+            resolve("phi-4@32k"),
+            Some(("phi-4".to_owned(), Some(32768)))
+        );
+        assert_eq!(
+            resolve("phi-4@large"),
+            Some(("phi-4".to_owned(), Some(131072)))
+        );
+        assert_eq!(resolve("phi-8"), None);
+        assert_eq!(resolve("phi-1"), None);
+        assert_eq!(resolve("phi-4k"), None);
+        assert_eq!(resolve("phi@8"), Some(("phi".to_owned(), Some(8))));
+        assert_eq!(resolve("phi-8192"), Some(("phi".to_owned(), Some(8192))));
+        assert_eq!(resolve("phi-32k"), Some(("phi".to_owned(), Some(32768))));
+        assert_eq!(resolve("phi-40000"), Some(("phi".to_owned(), Some(40000))));
+        assert_eq!(resolve("phi@40000"), Some(("phi".to_owned(), Some(40000))));
+        assert_eq!(
+            resolve("qwen3-8b-max"),
+            Some(("qwen3-8b".to_owned(), Some(262144)))
+        );
+        assert_eq!(resolve("qwen3-8b"), Some(("qwen3-8b".to_owned(), None)));
+        assert_eq!(resolve("qwen3@8k"), None);
+        assert_eq!(resolve("phi@0"), None);
+        assert_eq!(resolve("phi@8m"), None);
+    }
+}
@@ -1,3 +1,23 @@
+pub(crate) mod catalogdocument;
 pub(crate) mod contextsizeawarealias;
+pub(crate) mod modelcatalog;
 pub(crate) mod modelconfiguration;
 pub(crate) mod modellist;
+pub(crate) mod modelroutes;
+
+use crate::{Error, Result};
+use serde::Serialize;
+use std::path::Path;
+
+// writes to a temporary file first which then replaces the file, so readers never see a partially
+// written file
+pub(crate) fn save_to_json_file_atomically(file: &Path, value: &impl Serialize) -> Result<()> {
+    let data = serde_json::to_vec_pretty(value)
+        .map_err(|e| Error::DeserializationError(format!("failed to serialize: {e}")))?;
+    let file_name = file
+        .file_name()
+        .ok_or_else(|| Error::NotAJsonFile(file.to_path_buf()))?;
+    let tmp_file = file.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    std::fs::write(&tmp_file, data).map_err(Error::IoError)?;
+    std::fs::rename(&tmp_file, file).map_err(Error::IoError)
+}
@@ -0,0 +1,402 @@
+use super::{
+    catalogdocument::{CatalogDocument, ResolvedEntry, resolve_documents},
+    modelconfiguration::ModelConfiguration,
+};
+use crate::{Error, Result};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// the model-configurations of a directory by their aliases ; every change is validated and
+/// written to the directory right away (new configurations are saved as '<alias>.json')
+///
+/// a json-file may extend the configuration of another one ('"extends": <alias>') and define
+/// variants ('"variants": {<suffix>: {..}}' adding the configurations '<alias>-<suffix>') ; both
+/// hold the keys differing from their base only and are resolved when loading ; abstract json-files
+/// ('"abstract": true') serve as bases only
+pub struct ModelCatalog {
+    dir: PathBuf,
+    documents: BTreeMap<String, CatalogDocument>,
+    entries: BTreeMap<String, ResolvedEntry>,
+}
+
+impl ModelCatalog {
+    /// of several json-files with the same alias only the first one (by file-name) is used
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut json_files = Vec::new();
+        for entry in dir.read_dir().map_err(Error::IoError)? {
+            let path = entry.map_err(Error::IoError)?.path();
+            if path.is_file()
+                && path.file_name().is_some_and(|name| {
+                    name.to_string_lossy()
+                        .to_ascii_lowercase()
+                        .ends_with(".json")
+                })
+            {
+                json_files.push(path);
+            }
+        }
+        json_files.sort();
+
+        let mut documents = BTreeMap::<String, CatalogDocument>::new();
+        for json_file in json_files {
+            let document = CatalogDocument::load(&json_file)?;
+            let alias = document.alias().unwrap_or_default().to_owned();
+            if let Some(first) = documents.get(&alias) {
+                warn!(
+                    "ignoring {json_file:?} as its alias '{alias}' is used by {:?} already",
+                    first.json_file
+                );
+                continue;
+            }
+            documents.insert(alias, document);
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            entries: resolve_documents(&documents)?,
+            documents,
+        })
+    }
+
+    /// all resolved configurations ordered by alias (without the abstract ones)
+    pub fn configurations(&self) -> Vec<ModelConfiguration> {
+        self.entries
+            .values()
+            .filter(|entry| !entry.is_abstract)
+            .map(|entry| entry.model_configuration.clone())
+            .collect()
+    }
+
+    /// the configurations serving as bases only (e.g. to update their metadata)
+    pub fn abstract_configurations(&self) -> Vec<ModelConfiguration> {
+        self.entries
+            .values()
+            .filter(|entry| entry.is_abstract)
+            .map(|entry| entry.model_configuration.clone())
+            .collect()
+    }
+
+    /// none for abstract configurations
+    pub fn get(&self, alias: &str) -> Option<&ModelConfiguration> {
+        self.entries
+            .get(alias)
+            .filter(|entry| !entry.is_abstract)
+            .map(|entry| &entry.model_configuration)
+    }
+
+    /// the alias of the configuration defining the variant
+    pub fn variant_of(&self, alias: &str) -> Option<&str> {
+        self.entries
+            .get(alias)
+            .and_then(|entry| entry.variant_of.as_deref())
+    }
+
+    pub fn create(&mut self, model_configuration: ModelConfiguration) -> Result<()> {
+        if self.entries.contains_key(&model_configuration.alias)
+            || self.documents.contains_key(&model_configuration.alias)
+        {
+            return Err(Error::AliasTaken(model_configuration.alias));
+        }
+        model_configuration.validate()?;
+        let json_file = self.dir.join(format!("{}.json", model_configuration.alias));
+        if json_file.exists() {
+            return Err(Error::AliasTaken(model_configuration.alias));
+        }
+        let document =
+            CatalogDocument::from_configuration(&model_configuration, json_file, &self.documents)?;
+        self.change(None, Some(document))
+    }
+
+    /// replaces the configuration with the given alias ; the new configuration may come with
+    /// another alias (the configuration is renamed then) ; the variants of the json-file, whether
+    /// it is abstract and keys set to false explicitly are kept while variants themselves cannot
+    /// be changed (but their base can)
+    pub fn update(&mut self, alias: &str, model_configuration: ModelConfiguration) -> Result<()> {
+        let previous = self.document(alias)?;
+        model_configuration.validate()?;
+        let json_file = if model_configuration.alias != alias {
+            if self.entries.contains_key(&model_configuration.alias) {
+                return Err(Error::AliasTaken(model_configuration.alias));
+            }
+            let json_file = self.dir.join(format!("{}.json", model_configuration.alias));
+            if json_file.exists() {
+                return Err(Error::AliasTaken(model_configuration.alias));
+            }
+            json_file
+        } else {
+            previous.json_file.clone()
+        };
+        let (previous_variants, previous_abstract) =
+            (previous.variants().cloned(), previous.is_abstract());
+        let mut document =
+            CatalogDocument::from_configuration(&model_configuration, json_file, &self.documents)?;
+        document.set_variants(previous_variants);
+        document.set_abstract(previous_abstract);
+        document.keep_explicit_false(previous);
+        self.change(Some(alias), Some(document))
+    }
+
+    /// configurations other ones extend cannot be deleted
+    pub fn delete(&mut self, alias: &str) -> Result<ModelConfiguration> {
+        self.document(alias)?;
+        if let Some((extending, _)) = self
+            .documents
+            .iter()
+            .find(|(_, document)| document.extends() == Some(alias))
+        {
+            return Err(Error::InvalidConfiguration(format!(
+                "'{alias}' is extended by '{extending}'"
+            )));
+        }
+        let model_configuration = self.entries[alias].model_configuration.clone();
+        self.change(Some(alias), None)?;
+        Ok(model_configuration)
+    }
+
+    fn document(&self, alias: &str) -> Result<&CatalogDocument> {
+        if let Some(base) = self.variant_of(alias) {
+            return Err(Error::InvalidConfiguration(format!(
+                "'{alias}' is a variant of '{base}' ; change the variants of '{base}' instead"
+            )));
+        }
+        self.documents
+            .get(alias)
+            .ok_or_else(|| Error::UnknownAlias(alias.to_owned()))
+    }
+
+    // the whole catalog is resolved before any file is touched, so a change breaking other
+    // configurations (e.g. their base) is rejected
+    fn change(&mut self, remove: Option<&str>, insert: Option<CatalogDocument>) -> Result<()> {
+        let mut documents = self.documents.clone();
+        let removed = remove.and_then(|alias| documents.remove(alias));
+        if let Some(document) = &insert {
+            documents.insert(
+                document.alias().unwrap_or_default().to_owned(),
+                document.clone(),
+            );
+        }
+        let entries = resolve_documents(&documents)?;
+
+        if let Some(document) = &insert {
+            document.save()?;
+        }
+        if let Some(removed) = removed
+            && insert
+                .as_ref()
+                .is_none_or(|document| document.json_file != removed.json_file)
+        {
+            std::fs::remove_file(&removed.json_file).map_err(Error::IoError)?;
+        }
+        self.documents = documents;
+        self.entries = entries;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn changes_are_validated_and_written_to_the_directory() {
+        let dir = std::env::temp_dir().join(format!("modelcatalog-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model_path = dir.join("model.gguf");
+        std::fs::write(&model_path, b"GGUF").unwrap();
+        let model_configuration: ModelConfiguration = serde_json::from_value(serde_json::json!({
+            "alias": "tiny-model",
+            "model-path": model_path,
+            "max-ctx-size": 8192,
+            "vocab-type": 2,
+            "n-vocab": 1000,
+            "n-ctx-train": 8192,
+            "n-embd": 64,
+            "n-params": 1000,
+            "size": 4,
+            "capabilities": ["completion"]
+        }))
+        .unwrap();
+
+        let mut catalog = ModelCatalog::load(&dir).unwrap();
+        catalog.create(model_configuration.clone()).unwrap();
+        assert!(matches!(
+            catalog.create(model_configuration.clone()),
+            Err(Error::AliasTaken(_))
+        ));
+        let mut invalid = model_configuration.clone();
+        invalid.alias = "other".to_owned();
+        invalid.model_path = dir.join("missing.gguf").to_string_lossy().into();
+        assert!(matches!(
+            catalog.create(invalid),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        // the context-window is limited by the one the model was trained for
+        for max_ctx_size in [0, 256, 16384] {
+            let mut invalid = model_configuration.clone();
+            invalid.alias = "other".to_owned();
+            invalid.max_ctx_size = max_ctx_size.into();
+            assert!(matches!(
+                catalog.create(invalid),
+                Err(Error::InvalidConfiguration(_))
+            ));
+        }
+
+        // renaming moves the file
+        let mut renamed = model_configuration;
+        renamed.alias = "renamed-model".to_owned();
+        catalog.update("tiny-model", renamed).unwrap();
+        assert!(!dir.join("tiny-model.json").exists());
+        let reloaded = ModelCatalog::load(&dir).unwrap();
+        assert_eq!(
+            reloaded
+                .configurations()
+                .iter()
+                .map(|c| c.alias.as_str())
+                .collect::<Vec<_>>(),
+            ["renamed-model"]
+        );
+
+        // aliases must not be mistaken for context-size-aware aliases of other models
+        let mut colliding = catalog.get("renamed-model").unwrap().clone();
+        colliding.alias = "renamed-model-32k".to_owned();
+        assert!(matches!(
+            catalog.create(colliding),
+            Err(Error::InvalidConfiguration(_))
+        ));
+
+        catalog.delete("renamed-model").unwrap();
+        assert!(
+            ModelCatalog::load(&dir)
+                .unwrap()
+                .configurations()
+                .is_empty()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extensions_and_variants_are_resolved_and_saved_as_differences() {
+        let dir = std::env::temp_dir().join(format!("modelcatalog-extends-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model_path = dir.join("model.gguf");
+        std::fs::write(&model_path, b"GGUF").unwrap();
+        let write = |alias: &str, content: serde_json::Value| {
+            std::fs::write(dir.join(format!("{alias}.json")), content.to_string()).unwrap()
+        };
+        write(
+            "base",
+            serde_json::json!({
+                "alias": "base",
+                "model-path": model_path,
+                "max-ctx-size": 8192,
+                "vocab-type": 2,
+                "n-vocab": 1000,
+                "n-ctx-train": 8192,
+                "n-embd": 64,
+                "n-params": 1000,
+                "size": 4,
+                "capabilities": ["completion"],
+                "temp": 1.0,
+                "jinja": true,
+                "variants": {
+                    "thinking": {},
+                    "non-thinking": { "reasoning": "off", "temp": 0.7 }
+                }
+            }),
+        );
+        write(
+            "quant",
+            serde_json::json!({ "alias": "quant", "extends": "base", "size": 2, "jinja": null }),
+        );
+        // serves its variants only ; being abstract is not inherited
+        write(
+            "abstract-base",
+            serde_json::json!({
+                "alias": "abstract-base",
+                "abstract": true,
+                "extends": "base",
+                "no-mmap": false,
+                "variants": { "thinking": {} }
+            }),
+        );
+        write(
+            "abstract-quant",
+            serde_json::json!({ "alias": "abstract-quant", "extends": "abstract-base", "size": 2 }),
+        );
+
+        let mut catalog = ModelCatalog::load(&dir).unwrap();
+        assert_eq!(
+            catalog
+                .configurations()
+                .iter()
+                .map(|c| c.alias.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "abstract-base-thinking",
+                "abstract-quant",
+                "abstract-quant-thinking",
+                "base",
+                "base-non-thinking",
+                "base-thinking",
+                "quant",
+                "quant-non-thinking",
+                "quant-thinking"
+            ]
+        );
+        let variant = catalog.get("quant-non-thinking").unwrap().clone();
+        assert_eq!(
+            (variant.size, variant.temp, variant.jinja),
+            (2, Some(0.7), false)
+        );
+        assert_eq!(catalog.variant_of("quant-non-thinking"), Some("quant"));
+        assert!(matches!(
+            catalog.update("quant-thinking", variant.clone()),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            catalog.delete("base"),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        assert!(catalog.get("abstract-base").is_none());
+
+        // abstract configurations keep being abstract and their explicit defaults when updated
+        let mut abstract_base = catalog.abstract_configurations().remove(0);
+        abstract_base.temp = Some(0.8);
+        catalog.update("abstract-base", abstract_base).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join("abstract-base.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            saved,
+            serde_json::json!({
+                "alias": "abstract-base",
+                "abstract": true,
+                "extends": "base",
+                "no-mmap": false,
+                "temp": 0.8,
+                "variants": { "thinking": {} }
+            })
+        );
+        assert_eq!(catalog.get("abstract-quant").unwrap().temp, Some(0.8));
+
+        // a copy of the variant keeps the differences to its base only (inheriting its variants)
+        let mut copy = variant;
+        copy.alias = "copy".to_owned();
+        catalog.create(copy).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join("copy.json")).unwrap()).unwrap();
+        assert_eq!(
+            saved,
+            serde_json::json!({
+                "alias": "copy",
+                "extends": "quant",
+                "reasoning": "off",
+                "temp": 0.7
+            })
+        );
+        assert!(catalog.get("copy-thinking").is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{Error, Result};
-use inference_backends::{ContextSize, OnOffAutoValue};
+use crate::{Error, GgufMetadata, Result};
+use inference_backends::{ContextSize, OnOffAutoValue, ResourceLimits};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
@@ -8,6 +8,11 @@ use std::path::{Path, PathBuf};
 pub struct ModelConfiguration {
     pub alias: String,
 
+    // the alias of the configuration this one is based on ; the json-file holds the differences
+    // to it only (see ModelCatalog)
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub extends: Option<String>,
+
     pub model_path: String,
 
     pub max_ctx_size: ContextSize,
@@ -20,12 +25,25 @@ pub struct ModelConfiguration {
     pub size: u64,
     pub capabilities: Vec<String>,
 
+    // as read from the gguf-file (e.g. 'llama' and 'Q4_K_M')
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub architecture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub quantization: Option<String>,
+    // the template embedded in the gguf-file (applied by llama-server with 'jinja')
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub chat_template: Option<String>,
+
     #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
     pub mmproj_path: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
     pub model_draft: Option<String>,
 
+    // seconds the model stays loaded after its last request (negative values keep it loaded)
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub keep_alive: Option<i64>,
+
     #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
     pub prio: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
@@ -116,13 +134,73 @@ pub struct ModelConfiguration {
         default = "default_to_false"
     )]
     pub no_cache_prompt: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub resource_limits: Option<ResourceLimitsConfiguration>,
+
+    // the remote-agent (as named by the gateway's '--agent') the model is run at ; its paths
+    // are paths on the agent's host
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub backend_host: Option<String>,
+}
+
+/// os-level controls for the process serving the model ; unset values fall back to the ones
+/// configured for the backend
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ResourceLimitsConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub nice: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub cpu_affinity: Option<Vec<usize>>,
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub max_memory_mb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub max_open_files: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub cgroup: Option<String>,
+}
+
+impl From<&ResourceLimitsConfiguration> for ResourceLimits {
+    fn from(value: &ResourceLimitsConfiguration) -> Self {
+        ResourceLimits {
+            nice: value.nice,
+            cpu_affinity: value.cpu_affinity.clone(),
+            max_memory: value.max_memory_mb.map(|mb| mb * 1024 * 1024),
+            max_open_files: value.max_open_files,
+            cgroup: value.cgroup.as_ref().map(Into::into),
+        }
+    }
 }
 
+impl From<&ResourceLimits> for ResourceLimitsConfiguration {
+    fn from(value: &ResourceLimits) -> Self {
+        ResourceLimitsConfiguration {
+            nice: value.nice,
+            cpu_affinity: value.cpu_affinity.clone(),
+            max_memory_mb: value.max_memory.map(|bytes| bytes / (1024 * 1024)),
+            max_open_files: value.max_open_files,
+            cgroup: value
+                .cgroup
+                .as_ref()
+                .map(|cgroup| cgroup.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+// llama-server does not support larger context-windows
+const MAX_CTX_SIZE: u64 = 1 << 20;
+// smaller context-windows do not even fit the prompts of embedding-models
+const MIN_CTX_SIZE: u64 = 512;
+// the capabilities derived from the gguf-files ; others (set by hand) are kept when updating
+const GGUF_CAPABILITIES: [&str; 3] = ["completion", "embedding", "multimodal"];
+
 fn default_to_false() -> bool {
     false
 }
 
 impl ModelConfiguration {
+    /// reads the file as it is ; bases and variants are resolved by the ModelCatalog only
     pub fn load_from_json_file(file: &Path) -> Result<Self> {
         if !file.is_file()
             || !file
@@ -144,6 +222,112 @@ impl ModelConfiguration {
         }
     }
 
+    /// takes the metadata, the capabilities and whether it computes embeddings of the model from
+    /// its gguf-files (reading just their headers)
+    pub fn update_from_gguf_files(&mut self) -> Result<()> {
+        let model = GgufMetadata::read(Path::new(&self.model_path))?;
+        let mmproj = self
+            .mmproj_path
+            .as_deref()
+            .map(|mmproj_path| GgufMetadata::read(Path::new(mmproj_path)))
+            .transpose()?;
+        self.apply_gguf_metadata(&model, mmproj.as_ref())
+    }
+
+    pub fn apply_gguf_metadata(
+        &mut self,
+        model: &GgufMetadata,
+        mmproj: Option<&GgufMetadata>,
+    ) -> Result<()> {
+        let missing = |key: &str| {
+            Error::InvalidGguf(
+                PathBuf::from(&self.model_path),
+                format!("no {key} in the metadata"),
+            )
+        };
+        self.n_ctx_train = model
+            .context_length()
+            .ok_or_else(|| missing("context-length"))?;
+        self.n_embd = model
+            .embedding_length()
+            .ok_or_else(|| missing("embedding-length"))?;
+        self.vocab_type = model.vocab_type().unwrap_or_default();
+        self.n_vocab = model.n_vocab().unwrap_or_default();
+        self.n_params = model.parameter_count;
+        self.size = model.size;
+        self.architecture = model.architecture().map(Into::into);
+        self.quantization = model.quantization().map(Into::into);
+        self.chat_template = model.chat_template().map(Into::into);
+        // models with a pooling-type compute embeddings
+        self.embeddings = model.pooling_type().is_some();
+
+        let mut capabilities = vec![if self.embeddings {
+            "embedding"
+        } else {
+            "completion"
+        }];
+        if !self.embeddings
+            && mmproj
+                .is_some_and(|mmproj| mmproj.has_vision_encoder() || mmproj.has_audio_encoder())
+        {
+            capabilities.push("multimodal");
+        }
+        self.capabilities
+            .retain(|capability| !GGUF_CAPABILITIES.contains(&capability.as_str()));
+        self.capabilities
+            .splice(0..0, capabilities.into_iter().map(Into::into));
+        Ok(())
+    }
+
+    /// checks the alias (it names the json-file ; collisions with context-size-aware aliases are
+    /// checked by the catalog), the files referenced and the context-limits
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| Err(Error::InvalidConfiguration(reason));
+        if self.alias.is_empty()
+            || !self
+                .alias
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            || self.alias.starts_with('.')
+        {
+            return invalid(format!(
+                "alias '{}' must consist of letters, digits, '-', '_' and '.' only",
+                self.alias
+            ));
+        }
+        // the files of a model run at a remote-agent are not available here
+        for (name, path) in [
+            ("model-path", Some(&self.model_path)),
+            ("mmproj-path", self.mmproj_path.as_ref()),
+            ("model-draft", self.model_draft.as_ref()),
+        ] {
+            if let Some(path) = path
+                && self.backend_host.is_none()
+                && !Path::new(path).is_file()
+            {
+                return invalid(format!("{name} '{path}' is not a file"));
+            }
+        }
+        if self.n_ctx_train == 0 {
+            return invalid("n-ctx-train must not be 0".to_owned());
+        }
+        // the model was not trained for larger context-windows
+        let max_ctx_size: u64 = (&self.max_ctx_size).into();
+        let limit = self.n_ctx_train.min(MAX_CTX_SIZE);
+        if !(MIN_CTX_SIZE..=limit).contains(&max_ctx_size) {
+            return invalid(format!(
+                "max-ctx-size {max_ctx_size} is not within {MIN_CTX_SIZE} and {limit} (the n-ctx-train of the model)"
+            ));
+        }
+        Ok(())
+    }
+
+    /// writes the configuration to a temporary file first which then replaces the file, so
+    /// readers never see a partially written configuration
+    pub fn save_to_json_file(&self, file: &Path) -> Result<()> {
+        super::save_to_json_file_atomically(file, self)
+    }
+
     pub fn load_from_json_files(dir: &Path) -> Result<(Vec<Self>, Vec<PathBuf>)> {
         let (mut model_configurations, mut json_files) = (Vec::new(), Vec::new());
 
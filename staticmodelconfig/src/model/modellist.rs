@@ -82,6 +82,20 @@ impl ModelList {
             data_meta,
         );
     }
+
+    /// the configuration listed by a name still accepted but to be replaced by another one
+    pub fn add_deprecated_model_configuration(
+        &mut self,
+        model_configuration: &ModelConfiguration,
+        replaced_by: &str,
+    ) {
+        self.add_model_configuration(model_configuration);
+        if let (Some(model), Some(data)) = (self.models.last_mut(), self.data.last_mut()) {
+            model.description = format!("deprecated ; use '{replaced_by}'");
+            model.tags = vec![String::from("deprecated")];
+            data.replaced_by = Some(replaced_by.to_owned());
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -143,6 +157,9 @@ pub struct Data {
     created: u64,
     owned_by: String,
     meta: DataMeta,
+    // set for deprecated names
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    replaced_by: Option<String>,
 }
 
 impl Data {
@@ -156,6 +173,7 @@ impl Data {
                 .as_secs(),
             owned_by: String::from(OWNER),
             meta: data_meta,
+            replaced_by: None,
         }
     }
 }
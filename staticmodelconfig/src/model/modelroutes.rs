@@ -0,0 +1,175 @@
+use super::modelcatalog::ModelCatalog;
+use crate::{ContextSizeAwareAlias, Error, Result};
+use inference_backends::ContextSize;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// the target of a virtual model-name: a model of the catalog, optionally with a context-size
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModelRoute {
+    pub alias: String,
+    #[serde(skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub ctx_size: Option<ContextSize>,
+}
+
+impl ModelRoute {
+    /// the name of the model to serve (context-size-aware if a context-size is set)
+    pub fn target(&self) -> String {
+        match self.ctx_size {
+            Some(ctx_size) => ContextSizeAwareAlias::from((self.alias.clone(), ctx_size)).alias(),
+            None => self.alias.clone(),
+        }
+    }
+}
+
+/// virtual model-names (e.g. 'coder' or 'embed') clients use instead of the aliases of the
+/// catalog ; the routes of an api-key (by key-name) take precedence over the general ones
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModelRoutes {
+    #[serde(default)]
+    pub routes: BTreeMap<String, ModelRoute>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub keys: BTreeMap<String, BTreeMap<String, ModelRoute>>,
+}
+
+impl ModelRoutes {
+    /// a file which does not exist (yet) holds no routes
+    pub fn load_from_json_file(file: &Path) -> Result<Self> {
+        if !file.exists() {
+            return Ok(Self::default());
+        }
+        let data = std::fs::read(file).map_err(Error::IoError)?;
+        serde_json::from_slice(&data).map_err(|e| {
+            Error::DeserializationError(format!(
+                "failed to deserialize {file:?} as model-routes: {e}"
+            ))
+        })
+    }
+
+    pub fn save_to_json_file(&self, file: &Path) -> Result<()> {
+        super::save_to_json_file_atomically(file, self)
+    }
+
+    pub fn resolve(&self, name: &str, key_name: Option<&str>) -> Option<&ModelRoute> {
+        key_name
+            .and_then(|key_name| self.keys.get(key_name))
+            .and_then(|routes| routes.get(name))
+            .or_else(|| self.routes.get(name))
+    }
+
+    /// the routes of the api-key with the key-name (the general ones if none is given)
+    pub fn routes_mut(&mut self, key_name: Option<&str>) -> &mut BTreeMap<String, ModelRoute> {
+        match key_name {
+            Some(key_name) => self.keys.entry(key_name.to_owned()).or_default(),
+            None => &mut self.routes,
+        }
+    }
+
+    /// the names must not be (context-size-aware) aliases of the catalog while the targets must
+    /// be models of the catalog ; routes are given for the key-names of the gateway only
+    pub fn validate(&self, catalog: &ModelCatalog, key_names: &[String]) -> Result<()> {
+        let invalid = |reason: String| Err(Error::InvalidConfiguration(reason));
+        if let Some(key_name) = self
+            .keys
+            .keys()
+            .find(|key_name| !key_names.contains(key_name))
+        {
+            return invalid(format!(
+                "routes for the unknown key-name '{key_name}' (known are {})",
+                key_names.join(", ")
+            ));
+        }
+        for (name, route) in self.routes.iter().chain(self.keys.values().flatten()) {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                return invalid(format!(
+                    "model-name '{name}' must consist of letters, digits, '-', '_' and '.' only"
+                ));
+            }
+            let is_model = |model: &str| catalog.get(model).is_some();
+            if ContextSizeAwareAlias::resolve(name, is_model).is_some()
+                || ContextSizeAwareAlias::auto_sized_model(name).is_some_and(is_model)
+            {
+                return invalid(format!(
+                    "model-name '{name}' is a (context-size-aware) alias of the catalog"
+                ));
+            }
+            let Some(model_configuration) = catalog.get(&route.alias) else {
+                return Err(Error::UnknownAlias(route.alias.clone()));
+            };
+            if let Some(ctx_size) = &route.ctx_size {
+                let ctx_size: u64 = ctx_size.into();
+                let max_ctx_size: u64 = (&model_configuration.max_ctx_size).into();
+                if ctx_size == 0 || ctx_size > max_ctx_size {
+                    return invalid(format!(
+                        "ctx-size {ctx_size} of '{name}' is not within 1 and {max_ctx_size}"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn routes_of_api_keys_take_precedence_and_targets_are_validated() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("static_config_files");
+        let catalog = ModelCatalog::load(&dir).unwrap();
+        let mut model_routes: ModelRoutes = serde_json::from_str(
+            &std::fs::read_to_string(dir.join("../model_routes.json")).unwrap(),
+        )
+        .unwrap();
+        let key_names = ["default".to_owned(), "ci".to_owned()];
+        model_routes.validate(&catalog, &key_names).unwrap();
+        model_routes.routes_mut(Some("ci")).insert(
+            "coder".to_owned(),
+            ModelRoute {
+                alias: "qwen3.6-27b-mtp-ud-q8-k-xl-non-thinking-reasoning".to_owned(),
+                ctx_size: Some(ContextSize::Custom(200000)),
+            },
+        );
+        assert_eq!(
+            model_routes.resolve("coder", Some("ci")).unwrap().target(),
+            "qwen3.6-27b-mtp-ud-q8-k-xl-non-thinking-reasoning@200000"
+        );
+        assert_eq!(
+            model_routes
+                .resolve("coder", Some("default"))
+                .unwrap()
+                .target(),
+            "qwen3.6-27b-mtp-ud-q8-k-xl-thinking-precise-coding@128k"
+        );
+        assert!(model_routes.resolve("bge-m3", None).is_none());
+        model_routes.validate(&catalog, &key_names).unwrap();
+        // routes must be given for the key-names of the gateway
+        assert!(matches!(
+            model_routes.validate(&catalog, &key_names[..1]),
+            Err(Error::InvalidConfiguration(_))
+        ));
+
+        // names must not shadow the catalog and targets must exist
+        let mut shadowing = model_routes.clone();
+        shadowing
+            .routes_mut(None)
+            .insert("bge-m3".to_owned(), model_routes.routes["embed"].clone());
+        assert!(matches!(
+            shadowing.validate(&catalog, &key_names),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        model_routes.routes_mut(None).get_mut("chat").unwrap().alias = "missing".to_owned();
+        assert!(matches!(
+            model_routes.validate(&catalog, &key_names),
+            Err(Error::UnknownAlias(_))
+        ));
+    }
+}